@@ -0,0 +1,58 @@
+use serde::Deserialize;
+
+use crate::config::Condition;
+use crate::config::predicate::optional_scalar;
+
+// Columns to empty in every row, such as password hashes or tokens
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct NullFieldConfig {
+    pub table: String,
+    pub fields: Vec<String>,
+    // Written instead of NULL; required for `not null` columns unless type_default is set
+    #[serde(default, deserialize_with = "optional_scalar")]
+    pub default: Option<String>,
+    // Write a value suited to each column's declared type (0, a blank, 12/31/1899, ...) instead of NULL
+    #[serde(default)]
+    pub type_default: bool,
+    // Apply only to rows matching every condition, tested on the exported values
+    #[serde(default, rename = "where")]
+    pub where_clause: Vec<Condition>,
+}
+
+// Columns to remove from the refreshed export, both from the UNL rows and the schema
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct DropColumnsConfig {
+    pub table: String,
+    pub fields: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_rules_parsing() {
+        let config_str = r#"
+            - table: users
+              fields: [password_hash, api_token]
+            - table: accounts
+              fields: [balance]
+              default: 0
+            - table: customers
+              fields: [notes]
+              type_default: true
+              where: [{ field: status, equals: closed }]
+        "#;
+
+        let nulls: Vec<NullFieldConfig> = serde_yaml::from_str(config_str).unwrap();
+        assert_eq!(nulls[0].fields, vec!["password_hash", "api_token"]);
+        assert_eq!(nulls[0].default, None);
+        assert!(!nulls[0].type_default);
+        assert_eq!(nulls[1].default.as_deref(), Some("0"));
+        assert!(nulls[2].type_default);
+        assert_eq!(nulls[2].where_clause.len(), 1);
+
+        let drops: Vec<DropColumnsConfig> = serde_yaml::from_str("[{ table: users, fields: [legacy_notes] }]").unwrap();
+        assert_eq!(drops[0].fields, vec!["legacy_notes"]);
+    }
+}
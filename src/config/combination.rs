@@ -1,16 +1,68 @@
 use serde::Deserialize;
 
+use crate::config::Condition;
+
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct CombinationFieldConfig {
     pub table: String,
+    #[serde(default)]
     pub fields: Vec<SourceField>,
+    #[serde(default)]
     pub separator: String,
+    // Template such as "{owner_name}/{repo_name}"; when set it replaces joining with the separator.
+    // Placeholders name a source field's alias or source_field, or any other column of the table
+    #[serde(default)]
+    pub format: Option<String>,
     pub target_field: String,
+    // Apply only to rows matching every condition, tested on the exported values
+    #[serde(default, rename = "where")]
+    pub where_clause: Vec<Condition>,
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 pub struct SourceField {
-    pub source_field: String,
+    // Exactly one of source_field or literal must be set
+    #[serde(default)]
+    pub source_field: Option<String>,
+    #[serde(default)]
+    pub literal: Option<String>,
+    // Name to use for this source in a format template
+    #[serde(default)]
+    pub alias: Option<String>,
+    // Replace the value with a random name of this style, seeded by the value
+    #[serde(default)]
+    pub random_style: Option<String>,
+    #[serde(default)]
+    pub stage: SourceStage,
+    // Read source_field from a related row of another table instead of the current row
+    #[serde(default)]
+    pub lookup: Option<LookupConfig>,
+}
+
+// Join from the current table to a parent table, e.g. repositories.project_id -> projects.id
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct LookupConfig {
+    pub table: String,
+    // Column of the current table holding the parent's key
+    pub join_field: String,
+    // Column of the parent table the join field refers to
+    pub key: String,
+}
+
+// Whether a source field reads the row as exported or after the scrubbing rules ran
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceStage {
+    BeforeScrub,
+    #[default]
+    AfterScrub,
+}
+
+impl SourceField {
+    // The name a format template uses for this source
+    pub fn name(&self) -> Option<&str> {
+        self.alias.as_deref().or(self.source_field.as_deref())
+    }
 }
 
 #[cfg(test)]
@@ -18,7 +70,8 @@ mod tests {
     use super::*;
     use std::collections::HashMap;
     use crate::error::Error;
-    use crate::config::{Config, StandardizeConfig};
+    use crate::config::Config;
+    use crate::processor::random::WordLists;
     use crate::processor::sql::TableInfo;
     use crate::processor::unl::{UnlProcessor, UnlRow};
     use serde_yaml;
@@ -84,6 +137,7 @@ mod tests {
                     "name2".to_string(),
                     "combo_name".to_string(),
                 ],
+                ..Default::default()
             },
         );
         table_info.insert(
@@ -97,6 +151,7 @@ mod tests {
                     "repo_name".to_string(),
                     "full_path".to_string(),
                 ],
+                ..Default::default()
             },
         );
     
@@ -106,7 +161,7 @@ mod tests {
             table_info,
             vec!["test".to_string()],
             vec!["test".to_string()],
-        );
+        )?;
     
         // Test projects table combination
         let mut project_row = UnlRow {
@@ -201,6 +256,7 @@ mod tests {
                     "actual_field2".to_string(),
                     "combo_name".to_string(),
                 ],
+                ..Default::default()
             },
         );
     
@@ -209,7 +265,7 @@ mod tests {
             table_info,
             vec!["test".to_string()],
             vec!["test".to_string()],
-        );
+        )?;
     
         let mut row = UnlRow {
             fields: vec![
@@ -245,6 +301,215 @@ mod tests {
         assert_eq!(config.fields.len(), 2);
         assert_eq!(config.separator, " -&- ");
         assert_eq!(config.target_field, "combo_name");
+        assert_eq!(config.format, None);
+        assert_eq!(config.fields[0].stage, SourceStage::AfterScrub);
+    }
+
+    #[test]
+    fn test_combination_source_options_parsing() {
+        let config_str = r#"
+            table: repositories
+            fields:
+                - source_field: owner_name
+                  stage: before_scrub
+                  alias: original_owner
+                - source_field: repo_name
+                  random_style: github
+                - literal: "repo-"
+            format: "{original_owner}/{repo_name}"
+            target_field: full_path
+        "#;
+
+        let config: CombinationFieldConfig = serde_yaml::from_str(config_str).unwrap();
+        assert_eq!(config.separator, "");
+        assert_eq!(config.format.as_deref(), Some("{original_owner}/{repo_name}"));
+        assert_eq!(config.fields[0].stage, SourceStage::BeforeScrub);
+        assert_eq!(config.fields[0].name(), Some("original_owner"));
+        assert_eq!(config.fields[1].random_style.as_deref(), Some("github"));
+        assert_eq!(config.fields[1].name(), Some("repo_name"));
+        assert_eq!(config.fields[2].literal.as_deref(), Some("repo-"));
+        assert_eq!(config.fields[2].name(), None);
+        assert_eq!(config.fields[0].lookup, None);
+    }
+
+    #[test]
+    fn test_combination_lookup_parsing() {
+        let config_str = r#"
+            table: repositories
+            fields:
+                - source_field: project_name
+                  lookup:
+                    table: projects
+                    join_field: project_id
+                    key: id
+                - source_field: repo_name
+            separator: "/"
+            target_field: full_path
+        "#;
+
+        let config: CombinationFieldConfig = serde_yaml::from_str(config_str).unwrap();
+        assert_eq!(config.fields[0].lookup, Some(LookupConfig {
+            table: "projects".to_string(),
+            join_field: "project_id".to_string(),
+            key: "id".to_string(),
+        }));
+        assert_eq!(config.fields[0].stage, SourceStage::AfterScrub);
+    }
+
+    #[test]
+    fn test_combination_before_and_after_scrub() -> Result<(), Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.combination_fields = serde_yaml::from_str(r#"
+            - table: repositories
+              fields:
+                - source_field: repo_name
+                  stage: before_scrub
+                  alias: original
+                - literal: "archived"
+              format: "{owner_name}/{repo_name} (was {original}, {id})"
+              target_field: full_path
+            - table: repositories
+              fields:
+                - literal: "copy-of"
+                - source_field: repo_name
+                  random_style: github
+              separator: ":"
+              target_field: owner_name
+        "#)?;
+
+        let mut table_info = HashMap::new();
+        table_info.insert(
+            "repositories".to_string(),
+            TableInfo {
+                unl_file: "repos00103.unl".to_string(),
+                fields: vec![
+                    "id".to_string(),
+                    "project_id".to_string(),
+                    "owner_name".to_string(),
+                    "repo_name".to_string(),
+                    "full_path".to_string(),
+                ],
+                ..Default::default()
+            },
+        );
+        let processor = UnlProcessor::new(
+            config,
+            table_info,
+            vec!["happy".to_string()],
+            vec!["fox".to_string()],
+        )?;
+
+        let mut row = UnlRow::from_line("7|1|OwnerA|SecretRepo||")?;
+        processor.process_row("repositories", &mut row)?;
+
+        // repo_name is scrubbed by random_names in the test config, and the template's
+        // owner_name comes from the second combination, which therefore runs first
+        assert_eq!(row.get_field(3), Some("happy-fox"));
+        assert_eq!(row.get_field(4), Some("copy-of:happy-fox/happy-fox (was SecretRepo, 7)"));
+        assert_eq!(row.get_field(2), Some("copy-of:happy-fox"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_combination_random_style_matches_random_names() -> Result<(), Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.combination_fields = serde_yaml::from_str(r#"
+            - table: repositories
+              fields:
+                - source_field: repo_name
+                  stage: before_scrub
+                  random_style: github
+              target_field: full_path
+        "#)?;
+
+        let mut table_info = HashMap::new();
+        table_info.insert("repositories".to_string(), TableInfo {
+            unl_file: "repos00103.unl".to_string(),
+            fields: vec!["id".to_string(), "repo_name".to_string(), "full_path".to_string()],
+            ..Default::default()
+        });
+        let words = |prefix: &str| (0..50).map(|n| format!("{}{}", prefix, n)).collect::<Vec<_>>();
+        let processor = UnlProcessor::new(config, table_info, words("adj"), words("noun"))?;
+
+        // The pseudonym combined from the original name is the one random_names wrote
+        let mut row = UnlRow::from_line("7|SecretRepo|x|")?;
+        processor.process_row("repositories", &mut row)?;
+        assert_ne!(row.get_field(1), Some("SecretRepo"));
+        assert_eq!(row.get_field(2), row.get_field(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_combination_random_style_uses_random_names_rule() -> Result<(), Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.scrubbing.random_names = serde_yaml::from_str(r#"
+            - table: repositories
+              fields: [repo_name]
+              style: github
+              dictionaries: [colors, animals]
+              unique: true
+        "#)?;
+        config.combination_fields = serde_yaml::from_str(r#"
+            - table: repositories
+              fields:
+                - source_field: repo_name
+                  stage: before_scrub
+                  random_style: github
+              target_field: full_path
+        "#)?;
+
+        let mut table_info = HashMap::new();
+        table_info.insert("repositories".to_string(), TableInfo {
+            unl_file: "repos00103.unl".to_string(),
+            fields: vec!["id".to_string(), "repo_name".to_string(), "full_path".to_string()],
+            ..Default::default()
+        });
+        let words = |prefix: &str| (0..20).map(|n| format!("{}{}", prefix, n)).collect::<Vec<_>>();
+        let mut word_lists = WordLists::default();
+        word_lists.dictionaries.insert("colors".to_string(), words("color"));
+        word_lists.dictionaries.insert("animals".to_string(), words("animal"));
+        let processor = UnlProcessor::new(config, table_info, vec!["happy".to_string()], vec!["fox".to_string()])?
+            .with_word_lists(word_lists);
+
+        // Drawn from the rule's dictionaries rather than the global adjectives and nouns
+        let mut row = UnlRow::from_line("7|SecretRepo|x|")?;
+        processor.process_row("repositories", &mut row)?;
+        assert!(row.get_field(1).unwrap().starts_with("color"), "{:?}", row);
+        assert_eq!(row.get_field(2), row.get_field(1));
+
+        // A unique rule redraws a repeated name, which the combination can't know about: it
+        // still gets the first draw
+        let mut again = UnlRow::from_line("8|SecretRepo|x|")?;
+        processor.process_row("repositories", &mut again)?;
+        assert_ne!(again.get_field(1), row.get_field(1));
+        assert_eq!(again.get_field(2), row.get_field(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_combination_source_needs_field_or_literal() -> Result<(), Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.standardize.email.fields.clear();
+        config.combination_fields = serde_yaml::from_str(r#"
+            - table: customers
+              fields:
+                - stage: before_scrub
+              target_field: email
+        "#)?;
+        let mut table_info = HashMap::new();
+        table_info.insert(
+            "customers".to_string(),
+            TableInfo {
+                unl_file: "custo00100.unl".to_string(),
+                fields: vec!["id".to_string(), "email".to_string()],
+                ..Default::default()
+            },
+        );
+        let processor = UnlProcessor::new(config, table_info, vec!["a".to_string()], vec!["b".to_string()])?;
+
+        let mut row = UnlRow::from_line("1|a@example.com|")?;
+        assert!(matches!(processor.process_row("customers", &mut row), Err(Error::Config(_))));
+        Ok(())
     }
 
     
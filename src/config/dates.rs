@@ -0,0 +1,67 @@
+use serde::Deserialize;
+
+use crate::config::Condition;
+
+// Shift or coarsen date and datetime columns such as birth dates, hire dates and event times
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct DateRuleConfig {
+    pub table: String,
+    pub fields: Vec<String>,
+    #[serde(default)]
+    pub method: DateMethod,
+    // Shift: columns identifying the entity whose dates move together; defaults to the primary key
+    #[serde(default)]
+    pub entity_fields: Vec<String>,
+    // Shift: shared by rules whose entity_fields hold the same key, e.g. `customer` for both
+    // customers.id and orders.customer_id, so they shift alike. Defaults to the table name
+    #[serde(default)]
+    pub entity: Option<String>,
+    // Shift: largest offset in days, in either direction
+    #[serde(default = "default_max_shift_days")]
+    pub max_shift_days: u32,
+    // Apply only to rows matching every condition, tested on the exported values
+    #[serde(default, rename = "where")]
+    pub where_clause: Vec<Condition>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DateMethod {
+    // Move every date of an entity by the same seeded number of days
+    #[default]
+    Shift,
+    // Keep only the year (January 1st) or the month (the 1st)
+    Year,
+    Month,
+}
+
+fn default_max_shift_days() -> u32 {
+    365
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_rule_parsing() {
+        let config_str = r#"
+            - table: customers
+              fields: [birth_date]
+              method: year
+            - table: orders
+              fields: [ordered_at, shipped_at]
+              entity: customer
+              entity_fields: [customer_id]
+              max_shift_days: 30
+        "#;
+
+        let rules: Vec<DateRuleConfig> = serde_yaml::from_str(config_str).unwrap();
+        assert_eq!(rules[0].method, DateMethod::Year);
+        assert_eq!(rules[0].max_shift_days, 365);
+        assert!(rules[0].entity_fields.is_empty());
+        assert_eq!(rules[1].method, DateMethod::Shift);
+        assert_eq!(rules[1].entity.as_deref(), Some("customer"));
+        assert_eq!(rules[1].max_shift_days, 30);
+    }
+}
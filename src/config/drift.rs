@@ -0,0 +1,62 @@
+use serde::Deserialize;
+
+// Compare each export's schema with the one the last successful run saw
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct SchemaDriftConfig {
+    // Where the last run's schema fingerprint is kept; drift is only checked when this is set
+    #[serde(default)]
+    pub fingerprint_file: Option<String>,
+    // Case-insensitive regexes for column names that suggest personal data. The defaults
+    // match whole words of a name split on "_", so "city" is found in home_city but not capacity
+    #[serde(default = "default_pii_patterns")]
+    pub pii_patterns: Vec<String>,
+    // table.column names that match a pattern but need no rule
+    #[serde(default)]
+    pub allow: Vec<String>,
+    // Log new unruled columns that look like personal data instead of failing
+    #[serde(default)]
+    pub warn_only: bool,
+}
+
+fn default_pii_patterns() -> Vec<String> {
+    [
+        r"(^|_)e_?mail(_?addr(ess)?)?($|_)",
+        r"(^|_)(tele)?(phone|mobile|fax|cell)(_?phone)?(_?(no|num|number))?\d*($|_)",
+        r"(^|_)(first|last|sur|full|maiden|given|family)_?name($|_)",
+        r"(^|_)(birth(_?(date|day))?|date_?of_?birth|dob)($|_)",
+        r"(^|_)(address|street|city|postal_?code|post_?code|zip(_?code)?)\d*($|_)",
+        r"(^|_)(ssn|social_?sec(urity)?(_?(no|num|number))?|national_?id|passport(_?(no|num|number))?|licen[cs]e_?(no|num|number))($|_)",
+        r"(^|_)(tax_?id|iban|(account|card|routing)_?(no|num|number))($|_)",
+        r"(^|_)ip_?addr(ess)?($|_)",
+        // Only as the name's last word, so token_count or secret_question pass
+        r"(^|_)(password|passwd|secret|token)(_?hash)?$",
+    ].iter().map(|pattern| pattern.to_string()).collect()
+}
+
+impl Default for SchemaDriftConfig {
+    fn default() -> Self {
+        Self {
+            fingerprint_file: None,
+            pii_patterns: default_pii_patterns(),
+            allow: Vec::new(),
+            warn_only: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drift_parsing() {
+        let config: SchemaDriftConfig = serde_yaml::from_str(r#"
+            fingerprint_file: schema.fingerprint
+            allow: [customers.email_opt_in]
+        "#).unwrap();
+        assert_eq!(config.fingerprint_file.as_deref(), Some("schema.fingerprint"));
+        assert_eq!(config.pii_patterns, default_pii_patterns());
+        assert!(!config.warn_only);
+        assert_eq!(SchemaDriftConfig::default().pii_patterns.len(), 9);
+    }
+}
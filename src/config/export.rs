@@ -3,4 +3,15 @@ use serde::Deserialize;
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct ExportConfig {
     pub random_seed: u64,
+    // DBDATE the export was unloaded with; sets how DATE values are read and written
+    #[serde(default = "default_dbdate")]
+    pub dbdate: String,
+    // Day `older_than_years` conditions count back from, as YYYY-MM-DD; defaults to the
+    // day the run starts. Set it to make filtered rows repeatable across days
+    #[serde(default)]
+    pub reference_date: Option<String>,
+}
+
+fn default_dbdate() -> String {
+    "MDY4/".to_string()
 }
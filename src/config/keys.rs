@@ -0,0 +1,46 @@
+use serde::Deserialize;
+
+// Renumber primary keys that are identifying in themselves, rewriting the foreign keys
+// that reference them
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct KeyRemappingConfig {
+    // Private CSV of old and new keys for support staff, written after a successful run.
+    // Required when any table is remapped, and must be outside the target directory.
+    #[serde(default)]
+    pub mapping_file: Option<String>,
+    #[serde(default)]
+    pub tables: Vec<RemapTableConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct RemapTableConfig {
+    pub table: String,
+    // New keys run from here in a seeded shuffled order
+    #[serde(default = "default_start")]
+    pub start: i64,
+}
+
+fn default_start() -> i64 {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_remapping_parsing() {
+        let config_str = r#"
+            mapping_file: /secure/key_map.csv
+            tables:
+              - table: customers
+                start: 50000
+              - table: projects
+        "#;
+
+        let config: KeyRemappingConfig = serde_yaml::from_str(config_str).unwrap();
+        assert_eq!(config.mapping_file.as_deref(), Some("/secure/key_map.csv"));
+        assert_eq!(config.tables[0].start, 50000);
+        assert_eq!(config.tables[1].start, 1);
+    }
+}
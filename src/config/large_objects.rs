@@ -0,0 +1,41 @@
+use serde::Deserialize;
+
+// What happens to BLOB, CLOB, TEXT and BYTE values that dbexport wrote to separate files.
+// Large-object columns without a rule keep their files.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct LargeObjectConfig {
+    pub table: String,
+    pub fields: Vec<String>,
+    #[serde(default)]
+    pub action: LargeObjectAction,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LargeObjectAction {
+    // Ship the files with the export, references unchanged
+    #[default]
+    Copy,
+    // Write NULL instead of the reference and leave out files nothing else references
+    Drop,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_object_parsing() {
+        let config_str = r#"
+            - table: documents
+              fields: [scan]
+              action: drop
+            - table: documents
+              fields: [thumbnail]
+        "#;
+
+        let config: Vec<LargeObjectConfig> = serde_yaml::from_str(config_str).unwrap();
+        assert_eq!(config[0].action, LargeObjectAction::Drop);
+        assert_eq!(config[1].action, LargeObjectAction::Copy);
+    }
+}
@@ -0,0 +1,37 @@
+use serde::Deserialize;
+
+use crate::config::Condition;
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct MaskingConfig {
+    pub table: String,
+    pub fields: Vec<String>,
+    #[serde(default)]
+    pub keep_last: usize,
+    // Apply only to rows matching every condition, tested on the exported values
+    #[serde(default, rename = "where")]
+    pub where_clause: Vec<Condition>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_masking_config_parsing() {
+        let config_str = r#"
+            - table: customers
+              fields: [phone]
+              keep_last: 4
+            - table: employees
+              fields: [email, phone]
+        "#;
+
+        let config: Vec<MaskingConfig> = serde_yaml::from_str(config_str).unwrap();
+        assert_eq!(config.len(), 2);
+        assert_eq!(config[0].table, "customers");
+        assert_eq!(config[0].fields, vec!["phone"]);
+        assert_eq!(config[0].keep_last, 4);
+        assert_eq!(config[1].keep_last, 0);
+    }
+}
@@ -3,17 +3,43 @@ mod export;
 mod verification;
 mod scrubbing;
 mod combination;
+mod masking;
+mod word_lists;
+mod predicate;
+mod row_filter;
+mod columns;
+mod dates;
+mod numbers;
+mod redaction;
+mod tokenize;
+mod keys;
+mod synthetic;
+mod large_objects;
+mod drift;
 
 use serde::Deserialize;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use crate::error::Error;
 
 pub use self::database::DatabaseConfig;
 pub use self::export::ExportConfig;
 pub use self::verification::VerificationConfig;
-pub use self::scrubbing::{RandomNameConfig, ScrubbingConfig, StandardizeConfig};
-pub use self::combination::CombinationFieldConfig;
+pub use self::scrubbing::{GeneratorConfig, GeneratorKind, NamePart, RandomNameConfig, ScrubbingConfig, StandardizeConfig, StandardizeField};
+pub use self::combination::{CombinationFieldConfig, LookupConfig, SourceField, SourceStage};
+pub use self::masking::MaskingConfig;
+pub use self::word_lists::WordListConfig;
+pub use self::predicate::Condition;
+pub use self::row_filter::RowFilterConfig;
+pub use self::columns::{DropColumnsConfig, NullFieldConfig};
+pub use self::dates::{DateMethod, DateRuleConfig};
+pub use self::numbers::{NumericMethod, NumericRuleConfig};
+pub use self::redaction::{RedactionConfig, RedactionPattern, Replacement};
+pub use self::tokenize::{TokenAlphabet, TokenSecretConfig, TokenizeConfig};
+pub use self::keys::{KeyRemappingConfig, RemapTableConfig};
+pub use self::synthetic::{SyntheticColumn, SyntheticRowsConfig};
+pub use self::large_objects::{LargeObjectAction, LargeObjectConfig};
+pub use self::drift::SchemaDriftConfig;
 
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct Config {
@@ -24,17 +50,88 @@ pub struct Config {
     pub scrubbing: ScrubbingConfig,
     pub standardize: StandardizeConfig,
     pub combination_fields: Vec<CombinationFieldConfig>,
+    #[serde(default)]
+    pub masking: Vec<MaskingConfig>,
+    #[serde(default)]
+    pub word_lists: WordListConfig,
+    #[serde(default)]
+    pub row_filters: Vec<RowFilterConfig>,
+    #[serde(default)]
+    pub dates: Vec<DateRuleConfig>,
+    #[serde(default)]
+    pub numbers: Vec<NumericRuleConfig>,
+    #[serde(default)]
+    pub redaction: Vec<RedactionConfig>,
+    #[serde(default)]
+    pub tokenize: Vec<TokenizeConfig>,
+    #[serde(default)]
+    pub token_secret: TokenSecretConfig,
+    #[serde(default)]
+    pub key_remapping: KeyRemappingConfig,
+    #[serde(default)]
+    pub null_fields: Vec<NullFieldConfig>,
+    #[serde(default)]
+    pub drop_columns: Vec<DropColumnsConfig>,
+    #[serde(default)]
+    pub synthetic_rows: Vec<SyntheticRowsConfig>,
+    #[serde(default)]
+    pub large_objects: Vec<LargeObjectConfig>,
+    #[serde(default)]
+    pub schema_drift: SchemaDriftConfig,
+    // Directory of the config file; relative paths in the config resolve against it
+    #[serde(skip)]
+    pub base_dir: Option<PathBuf>,
 }
 
 impl Config {
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
-        let content = fs::read_to_string(path)?;
-        Self::from_str(&content)
+        let content = fs::read_to_string(path.as_ref())?;
+        let mut config = Self::from_str(&content)?;
+        config.base_dir = path.as_ref().parent().map(Path::to_path_buf);
+        Ok(config)
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(content: &str) -> Result<Self, Error> {
         serde_yaml::from_str(content).map_err(Error::from)
     }
+
+    // Every table name the rules refer to, so selectors can be resolved against the schema
+    pub fn table_names_mut(&mut self) -> Vec<&mut String> {
+        let mut names: Vec<&mut String> = Vec::new();
+        names.extend(self.excluded_tables.iter_mut());
+        names.extend(self.verification.record_counts.sample_tables.iter_mut());
+        names.extend(self.scrubbing.random_names.iter_mut().map(|rule| &mut rule.table));
+        names.extend(self.scrubbing.generators.iter_mut().map(|rule| &mut rule.table));
+        for standardize in [&mut self.standardize.address, &mut self.standardize.phone, &mut self.standardize.email] {
+            names.extend(standardize.fields.iter_mut().map(|field| &mut field.table));
+        }
+        for rule in &mut self.combination_fields {
+            names.push(&mut rule.table);
+            names.extend(rule.fields.iter_mut().filter_map(|field| field.lookup.as_mut()).map(|lookup| &mut lookup.table));
+        }
+        names.extend(self.masking.iter_mut().map(|rule| &mut rule.table));
+        names.extend(self.row_filters.iter_mut().map(|rule| &mut rule.table));
+        names.extend(self.dates.iter_mut().map(|rule| &mut rule.table));
+        names.extend(self.numbers.iter_mut().map(|rule| &mut rule.table));
+        names.extend(self.redaction.iter_mut().map(|rule| &mut rule.table));
+        names.extend(self.tokenize.iter_mut().map(|rule| &mut rule.table));
+        names.extend(self.key_remapping.tables.iter_mut().map(|rule| &mut rule.table));
+        names.extend(self.null_fields.iter_mut().map(|rule| &mut rule.table));
+        names.extend(self.drop_columns.iter_mut().map(|rule| &mut rule.table));
+        names.extend(self.synthetic_rows.iter_mut().map(|rule| &mut rule.table));
+        names.extend(self.large_objects.iter_mut().map(|rule| &mut rule.table));
+        names
+    }
+
+    // Resolve a path from the config relative to the config file's directory
+    pub fn resolve_path(&self, path: &str) -> PathBuf {
+        let path = Path::new(path);
+        match &self.base_dir {
+            Some(base) if path.is_relative() => base.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -53,6 +150,7 @@ mod tests {
 
         // Test export config
         assert_eq!(config.export.random_seed, 42);
+        assert_eq!(config.export.dbdate, "MDY4/");
 
         // Test excluded tables
         assert!(config.excluded_tables.contains(&"training_config".to_string()));
@@ -94,6 +192,23 @@ mod tests {
         assert_eq!(repos.target_field, "full_path");
     }
 
+    #[test]
+    fn test_paths_resolve_relative_to_config_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.yml");
+        fs::write(&config_path, include_str!("../../test_data/config.yml")).unwrap();
+
+        let config = Config::from_file(&config_path).unwrap();
+        assert_eq!(config.base_dir.as_deref(), Some(temp_dir.path()));
+        assert_eq!(config.resolve_path("adjectives.txt"), temp_dir.path().join("adjectives.txt"));
+        assert_eq!(config.resolve_path("/abs/nouns.txt"), PathBuf::from("/abs/nouns.txt"));
+
+        // Configs parsed from strings resolve against the working directory
+        let config = Config::from_str(include_str!("../../test_data/config.yml")).unwrap();
+        assert_eq!(config.resolve_path("nouns.txt"), PathBuf::from("nouns.txt"));
+        assert_eq!(config.word_lists, WordListConfig::default());
+    }
+
     #[test]
     fn test_missing_file() {
         let result = Config::from_file("nonexistent.yml");
@@ -0,0 +1,64 @@
+use serde::Deserialize;
+
+use crate::config::Condition;
+
+// Perturb amounts and counts such as salaries, balances and invoice totals
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct NumericRuleConfig {
+    pub table: String,
+    pub fields: Vec<String>,
+    #[serde(default)]
+    pub method: NumericMethod,
+    // Noise: largest relative change, e.g. 0.1 for up to 10% either way
+    #[serde(default = "default_noise")]
+    pub noise: f64,
+    // Bucket: values round down to a multiple of this
+    #[serde(default)]
+    pub bucket_size: Option<f64>,
+    // Noise: adjust the noisy values so each column's total over the table stays as exported
+    #[serde(default)]
+    pub preserve_sum: bool,
+    // Apply only to rows matching every condition, tested on the exported values
+    #[serde(default, rename = "where")]
+    pub where_clause: Vec<Condition>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NumericMethod {
+    // Seeded random change bounded by `noise`
+    #[default]
+    Noise,
+    Bucket,
+}
+
+fn default_noise() -> f64 {
+    0.1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numeric_rule_parsing() {
+        let config_str = r#"
+            - table: employees
+              fields: [salary]
+              noise: 0.05
+              preserve_sum: true
+            - table: customers
+              fields: [credit_limit]
+              method: bucket
+              bucket_size: 1000
+        "#;
+
+        let rules: Vec<NumericRuleConfig> = serde_yaml::from_str(config_str).unwrap();
+        assert_eq!(rules[0].method, NumericMethod::Noise);
+        assert_eq!(rules[0].noise, 0.05);
+        assert!(rules[0].preserve_sum);
+        assert_eq!(rules[1].method, NumericMethod::Bucket);
+        assert_eq!(rules[1].bucket_size, Some(1000.0));
+        assert_eq!(rules[1].noise, 0.1);
+    }
+}
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Deserializer};
+use serde::de::Error as _;
+
+// One test on a column of the row as exported. Every operator given must hold;
+// a rule's `where` list holds when all of its conditions do.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Condition {
+    pub field: String,
+    #[serde(default, deserialize_with = "optional_scalar")]
+    pub equals: Option<String>,
+    #[serde(default, deserialize_with = "optional_scalar")]
+    pub not_equals: Option<String>,
+    #[serde(default, rename = "in", deserialize_with = "optional_scalars")]
+    pub in_list: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "optional_scalars")]
+    pub not_in: Option<Vec<String>>,
+    #[serde(default)]
+    pub regex: Option<String>,
+    // Inclusive numeric bounds; non-numeric and NULL values never match
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+    // Informix NULLs are empty fields in the UNL file
+    #[serde(default)]
+    pub is_null: Option<bool>,
+    // Dates more than this many years before export.reference_date, or the day the run starts;
+    // NULL and unparseable values never match
+    #[serde(default)]
+    pub older_than_years: Option<u32>,
+}
+
+// Accept ids written as YAML numbers as well as strings, e.g. `in: [1001, 1002]`
+fn scalar_to_string<E: serde::de::Error>(value: serde_yaml::Value) -> Result<String, E> {
+    match value {
+        serde_yaml::Value::String(s) => Ok(s),
+        serde_yaml::Value::Number(n) => Ok(n.to_string()),
+        serde_yaml::Value::Bool(b) => Ok(b.to_string()),
+        other => Err(E::custom(format!("expected a string or number, found {:?}", other))),
+    }
+}
+
+pub(super) fn optional_scalar<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Option::<serde_yaml::Value>::deserialize(deserializer)?
+        .map(scalar_to_string)
+        .transpose()
+}
+
+pub(super) fn optional_scalars<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<String>>, D::Error> {
+    match Option::<serde_yaml::Value>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(serde_yaml::Value::Sequence(values)) => values.into_iter()
+            .map(scalar_to_string)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some),
+        Some(other) => Err(D::Error::custom(format!("expected a list, found {:?}", other))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_condition_parsing() {
+        let config_str = r#"
+            - field: id
+              not_in: [1001, "1002"]
+            - field: email
+              regex: "@internal\\.test$"
+            - field: balance
+              min: 0
+              max: 99.5
+            - field: deleted_at
+              is_null: true
+            - field: status
+              equals: 1
+              older_than_years: 7
+        "#;
+
+        let conditions: Vec<Condition> = serde_yaml::from_str(config_str).unwrap();
+        assert_eq!(conditions[0].not_in, Some(vec!["1001".to_string(), "1002".to_string()]));
+        assert_eq!(conditions[1].regex.as_deref(), Some("@internal\\.test$"));
+        assert_eq!(conditions[2].min, Some(0.0));
+        assert_eq!(conditions[2].max, Some(99.5));
+        assert_eq!(conditions[3].is_null, Some(true));
+        assert_eq!(conditions[4].equals.as_deref(), Some("1"));
+        assert_eq!(conditions[4].in_list, None);
+        assert_eq!(conditions[4].older_than_years, Some(7));
+
+        assert!(serde_yaml::from_str::<Condition>("{ field: id, in: 5 }").is_err());
+    }
+}
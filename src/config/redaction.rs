@@ -0,0 +1,69 @@
+use serde::Deserialize;
+
+use crate::config::Condition;
+
+// Scrub identifying text embedded in notes and comments columns
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct RedactionConfig {
+    pub table: String,
+    pub fields: Vec<String>,
+    // What to look for in the text; defaults to all of them
+    #[serde(default = "default_patterns")]
+    pub patterns: Vec<RedactionPattern>,
+    // Columns of the same row whose exported values are redacted wherever the text mentions them
+    #[serde(default)]
+    pub known_fields: Vec<String>,
+    #[serde(default)]
+    pub replacement: Replacement,
+    // Apply only to rows matching every condition, tested on the exported values
+    #[serde(default, rename = "where")]
+    pub where_clause: Vec<Condition>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactionPattern {
+    Email,
+    Phone,
+    Url,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Replacement {
+    // [EMAIL], [PHONE], [URL], or the known column's name such as [FIRST_NAME]
+    #[default]
+    Placeholder,
+    // Seeded fake values, the same for the same text everywhere; known values become the
+    // row's scrubbed value of their column
+    Pseudonym,
+}
+
+fn default_patterns() -> Vec<RedactionPattern> {
+    vec![RedactionPattern::Email, RedactionPattern::Phone, RedactionPattern::Url]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redaction_parsing() {
+        let config_str = r#"
+            - table: customers
+              fields: [notes]
+              known_fields: [first_name, last_name]
+              replacement: pseudonym
+            - table: tickets
+              fields: [body]
+              patterns: [email]
+        "#;
+
+        let rules: Vec<RedactionConfig> = serde_yaml::from_str(config_str).unwrap();
+        assert_eq!(rules[0].patterns.len(), 3);
+        assert_eq!(rules[0].known_fields, vec!["first_name", "last_name"]);
+        assert_eq!(rules[0].replacement, Replacement::Pseudonym);
+        assert_eq!(rules[1].patterns, vec![RedactionPattern::Email]);
+        assert_eq!(rules[1].replacement, Replacement::Placeholder);
+    }
+}
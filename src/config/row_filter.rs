@@ -0,0 +1,44 @@
+use serde::Deserialize;
+
+use crate::config::Condition;
+
+// Rows of a table to leave out of the refreshed export
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct RowFilterConfig {
+    pub table: String,
+    // Rows matching every condition are removed
+    #[serde(rename = "where")]
+    pub where_clause: Vec<Condition>,
+    // Also remove rows of other tables that reference a removed row through a foreign key
+    #[serde(default)]
+    pub cascade: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_filter_parsing() {
+        let config_str = r#"
+            - table: customers
+              where:
+                - field: deleted_at
+                  is_null: false
+              cascade: true
+            - table: projects
+              where:
+                - field: tenant_id
+                  in: [7, 9]
+        "#;
+
+        let filters: Vec<RowFilterConfig> = serde_yaml::from_str(config_str).unwrap();
+        assert_eq!(filters.len(), 2);
+        assert_eq!(filters[0].where_clause[0].is_null, Some(false));
+        assert!(filters[0].cascade);
+        assert!(!filters[1].cascade);
+
+        // A filter without conditions would remove every row; that is what excluded_tables is for
+        assert!(serde_yaml::from_str::<RowFilterConfig>("table: customers").is_err());
+    }
+}
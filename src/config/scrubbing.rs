@@ -1,15 +1,85 @@
 use serde::Deserialize;
 
+use crate::config::Condition;
+
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct ScrubbingConfig {
     pub random_names: Vec<RandomNameConfig>,
+    #[serde(default)]
+    pub generators: Vec<GeneratorConfig>,
+    // Also keep scrubbed values unique within the schema's primary keys, unique constraints and unique indexes
+    #[serde(default)]
+    pub unique_from_schema: bool,
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 pub struct RandomNameConfig {
     pub table: String,
     pub fields: Vec<String>,
     pub style: String,
+    // Person styles: column holding gender codes (M/F) used to pick first names
+    #[serde(default)]
+    pub gender_field: Option<String>,
+    // Person styles: keep the gender implied by the original first name
+    #[serde(default)]
+    pub keep_gender: bool,
+    // Person style: which part of one generated name goes into each field, in order.
+    // Defaults to [full] for one field, [first, last] for two, [first, middle, last] for three
+    #[serde(default)]
+    pub parts: Vec<NamePart>,
+    // Github style: word list files overriding the global adjectives/nouns for this rule
+    #[serde(default)]
+    pub adjectives: Option<String>,
+    #[serde(default)]
+    pub nouns: Option<String>,
+    // Github style: named dictionaries from word_lists.dictionaries, one word from each, joined by '-'
+    #[serde(default)]
+    pub dictionaries: Vec<String>,
+    // Never write the same value twice to these fields within the table
+    #[serde(default)]
+    pub unique: bool,
+    // Apply only to rows matching every condition, tested on the exported values
+    #[serde(default, rename = "where")]
+    pub where_clause: Vec<Condition>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NamePart {
+    First,
+    Middle,
+    MiddleInitial,
+    Last,
+    Full,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct GeneratorConfig {
+    pub table: String,
+    pub fields: Vec<String>,
+    pub generator: GeneratorKind,
+    // Email only: fields of the same row (after name scrubbing) that form the local part
+    #[serde(default)]
+    pub name_fields: Vec<String>,
+    // Email only: must be a reserved domain; defaults to a random one of example.com/.net/.org
+    #[serde(default)]
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub unique: bool,
+    // Apply only to rows matching every condition, tested on the exported values
+    #[serde(default, rename = "where")]
+    pub where_clause: Vec<Condition>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GeneratorKind {
+    Email,
+    Phone,
+    StreetAddress,
+    City,
+    PostalCode,
+    Company,
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq)]
@@ -29,6 +99,9 @@ pub struct StandardizeField {
 pub struct TableField {
     pub table: String,
     pub field: String,
+    // Apply only to rows matching every condition, tested on the exported values
+    #[serde(default, rename = "where")]
+    pub where_clause: Vec<Condition>,
 }
 
 #[cfg(test)]
@@ -52,6 +125,73 @@ mod tests {
         assert_eq!(config.random_names[0].table, "customers");
         assert_eq!(config.random_names[0].fields, vec!["first_name", "last_name"]);
         assert_eq!(config.random_names[0].style, "github");
+        assert!(config.generators.is_empty());
+        assert!(config.random_names[0].parts.is_empty());
+        assert!(!config.random_names[0].keep_gender);
+    }
+
+    #[test]
+    fn test_person_name_config_parsing() {
+        let config_str = r#"
+            table: customers
+            fields: [last_name, first_name]
+            style: person
+            gender_field: gender
+            keep_gender: true
+            parts: [last, first]
+        "#;
+
+        let config: RandomNameConfig = serde_yaml::from_str(config_str).unwrap();
+        assert_eq!(config.style, "person");
+        assert_eq!(config.gender_field.as_deref(), Some("gender"));
+        assert!(config.keep_gender);
+        assert_eq!(config.parts, vec![NamePart::Last, NamePart::First]);
+    }
+
+    #[test]
+    fn test_word_list_overrides_parsing() {
+        let config_str = r#"
+            - table: projects
+              fields: [project_name]
+              style: github
+              adjectives: words/project_adjectives.txt
+            - table: repositories
+              fields: [repo_name]
+              style: github
+              dictionaries: [colors, animals]
+        "#;
+
+        let config: Vec<RandomNameConfig> = serde_yaml::from_str(config_str).unwrap();
+        assert_eq!(config[0].adjectives.as_deref(), Some("words/project_adjectives.txt"));
+        assert_eq!(config[0].nouns, None);
+        assert_eq!(config[1].dictionaries, vec!["colors", "animals"]);
+    }
+
+    #[test]
+    fn test_generator_config_parsing() {
+        let config_str = r#"
+            random_names: []
+            generators:
+                - table: customers
+                  fields: [email]
+                  generator: email
+                  name_fields: [first_name, last_name]
+                  domain: example.org
+                - table: customers
+                  fields: [address]
+                  generator: street_address
+        "#;
+
+        let config: ScrubbingConfig = serde_yaml::from_str(config_str).unwrap();
+        assert_eq!(config.generators.len(), 2);
+        assert_eq!(config.generators[0].generator, GeneratorKind::Email);
+        assert_eq!(config.generators[0].name_fields, vec!["first_name", "last_name"]);
+        assert_eq!(config.generators[0].domain.as_deref(), Some("example.org"));
+        assert_eq!(config.generators[1].generator, GeneratorKind::StreetAddress);
+        assert!(config.generators[1].name_fields.is_empty());
+
+        let invalid = "random_names: []\ngenerators:\n  - { table: t, fields: [f], generator: ssn }";
+        assert!(serde_yaml::from_str::<ScrubbingConfig>(invalid).is_err());
     }
 
     #[test]
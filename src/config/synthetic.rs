@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::config::GeneratorKind;
+use crate::config::predicate::{optional_scalar, optional_scalars};
+
+// Append made-up rows to a table that is empty or too small for training
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct SyntheticRowsConfig {
+    pub table: String,
+    pub count: usize,
+    // How to fill particular columns; others get a value suited to their declared type,
+    // keys continue after the largest exported key and foreign keys pick an exported parent row
+    #[serde(default)]
+    pub columns: BTreeMap<String, SyntheticColumn>,
+}
+
+// Exactly one of the options
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct SyntheticColumn {
+    #[serde(default)]
+    pub generator: Option<GeneratorKind>,
+    // Picked at random for each row
+    #[serde(default, deserialize_with = "optional_scalars")]
+    pub values: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "optional_scalar")]
+    pub value: Option<String>,
+    #[serde(default)]
+    pub null: bool,
+}
+
+impl SyntheticColumn {
+    pub fn options_set(&self) -> usize {
+        [self.generator.is_some(), self.values.is_some(), self.value.is_some(), self.null]
+            .iter()
+            .filter(|set| **set)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synthetic_rows_parsing() {
+        let config_str = r#"
+            - table: support_tickets
+              count: 25
+              columns:
+                subject: { generator: company }
+                status: { values: [open, closed, 3] }
+                priority: { value: 1 }
+                notes: { null: true }
+        "#;
+
+        let config: Vec<SyntheticRowsConfig> = serde_yaml::from_str(config_str).unwrap();
+        let columns = &config[0].columns;
+        assert_eq!(config[0].count, 25);
+        assert_eq!(columns["subject"].generator, Some(GeneratorKind::Company));
+        assert_eq!(columns["status"].values.as_ref().map(Vec::len), Some(3));
+        assert_eq!(columns["priority"].value.as_deref(), Some("1"));
+        assert!(columns["notes"].null);
+        assert!(columns.values().all(|column| column.options_set() == 1));
+    }
+}
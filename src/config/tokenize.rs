@@ -0,0 +1,72 @@
+use serde::Deserialize;
+
+use crate::config::Condition;
+
+// Replace identifiers used as join keys downstream with stable keyed-hash tokens
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct TokenizeConfig {
+    pub table: String,
+    pub fields: Vec<String>,
+    #[serde(default)]
+    pub prefix: String,
+    // Token length including the prefix; defaults to 16 characters after the prefix
+    #[serde(default)]
+    pub length: Option<usize>,
+    #[serde(default)]
+    pub alphabet: TokenAlphabet,
+    // Apply only to rows matching every condition, tested on the exported values
+    #[serde(default, rename = "where")]
+    pub where_clause: Vec<Condition>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenAlphabet {
+    // 0-9 and a-f
+    #[default]
+    Hex,
+    // 0-9 and A-Z
+    Alphanumeric,
+    Numeric,
+}
+
+// Where the tokenization key comes from. Without either, tokens are keyed by
+// export.random_seed, which anyone with the config can reproduce.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct TokenSecretConfig {
+    // Environment variable holding the secret
+    #[serde(default)]
+    pub env: Option<String>,
+    // File holding the secret, relative to the config file
+    #[serde(default)]
+    pub file: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_parsing() {
+        let config_str = r#"
+            - table: customers
+              fields: [customer_number]
+              prefix: "C-"
+              length: 12
+              alphabet: alphanumeric
+            - table: users
+              fields: [username]
+        "#;
+
+        let rules: Vec<TokenizeConfig> = serde_yaml::from_str(config_str).unwrap();
+        assert_eq!(rules[0].prefix, "C-");
+        assert_eq!(rules[0].length, Some(12));
+        assert_eq!(rules[0].alphabet, TokenAlphabet::Alphanumeric);
+        assert_eq!(rules[1].prefix, "");
+        assert_eq!(rules[1].alphabet, TokenAlphabet::Hex);
+
+        let secret: TokenSecretConfig = serde_yaml::from_str("env: TOKEN_SECRET").unwrap();
+        assert_eq!(secret.env.as_deref(), Some("TOKEN_SECRET"));
+        assert_eq!(secret.file, None);
+    }
+}
@@ -11,6 +11,16 @@ pub struct VerificationConfig {
 pub struct LoggingConfig {
     pub directory: String,
     pub prefix: String,
+    // Write a per-table trace of which rule produced each column's final value
+    #[serde(default)]
+    pub trace_rules: bool,
+    // Number of rows traced per table
+    #[serde(default = "default_trace_rows")]
+    pub trace_rows: usize,
+}
+
+fn default_trace_rows() -> usize {
+    10
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq)]
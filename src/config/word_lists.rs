@@ -0,0 +1,58 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+// Word list files, resolved relative to the config file's directory
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct WordListConfig {
+    #[serde(default = "default_adjectives")]
+    pub adjectives: String,
+    #[serde(default = "default_nouns")]
+    pub nouns: String,
+    // Use a tiny built-in list instead of failing when a word list is missing
+    #[serde(default)]
+    pub allow_fallback: bool,
+    // Named dictionaries that rules can reference, name -> file
+    #[serde(default)]
+    pub dictionaries: BTreeMap<String, String>,
+}
+
+fn default_adjectives() -> String {
+    "adjectives.txt".to_string()
+}
+
+fn default_nouns() -> String {
+    "nouns.txt".to_string()
+}
+
+impl Default for WordListConfig {
+    fn default() -> Self {
+        Self {
+            adjectives: default_adjectives(),
+            nouns: default_nouns(),
+            allow_fallback: false,
+            dictionaries: BTreeMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_list_config_parsing() {
+        let config_str = r#"
+            adjectives: words/adjectives.txt
+            dictionaries:
+                colors: words/colors.txt
+                animals: /usr/share/words/animals.txt
+        "#;
+
+        let config: WordListConfig = serde_yaml::from_str(config_str).unwrap();
+        assert_eq!(config.adjectives, "words/adjectives.txt");
+        assert_eq!(config.nouns, "nouns.txt");
+        assert!(!config.allow_fallback);
+        assert_eq!(config.dictionaries.len(), 2);
+        assert_eq!(config.dictionaries["colors"], "words/colors.txt");
+    }
+}
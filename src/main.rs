@@ -1,6 +1,6 @@
 use std::path::{Path, PathBuf};
 use std::env;
-use ifx_train_data_refresh::{Config, DbExportProcessor, Error};
+use ifx_train_data_refresh::{processor, Config, DbExportProcessor, Error};
 
 fn main() {
     if let Err(e) = run() {
@@ -11,11 +11,43 @@ fn main() {
 
 fn run() -> Result<(), Error> {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 4 {
-        eprintln!("Usage: {} <config.yml> <source_dir> <target_dir>", args[0]);
-        return Ok(());
+    match args.get(1).map(String::as_str) {
+        Some("plan") if args.len() == 4 => {
+            let config = Config::from_file(&args[2])?;
+            print!("{}", processor::plan(config, Path::new(&args[3]))?);
+            Ok(())
+        }
+        Some("diff") if args.len() == 4 => {
+            print!("{}", processor::diff(Path::new(&args[2]), Path::new(&args[3]))?);
+            Ok(())
+        }
+        Some("inspect") if args.len() > 2 => inspect(&args),
+        _ if args.len() == 4 => refresh(&args),
+        _ => usage(&args[0]),
     }
+}
+
+fn usage(program: &str) -> Result<(), Error> {
+    eprintln!("Usage: {} <config.yml> <source_dir> <target_dir>", program);
+    eprintln!("       {} plan <config.yml> <source_dir>", program);
+    eprintln!("       {} diff <left_dir> <right_dir>", program);
+    eprintln!("       {} inspect [--json] <source_dir> [config.yml]", program);
+    Ok(())
+}
 
+fn inspect(args: &[String]) -> Result<(), Error> {
+    let json = args.iter().any(|arg| arg == "--json");
+    let paths: Vec<&String> = args[2..].iter().filter(|arg| *arg != "--json").collect();
+    let (source_path, config) = match paths.as_slice() {
+        [source] => (source, None),
+        [source, config] => (source, Some(Config::from_file(config)?)),
+        _ => return usage(&args[0]),
+    };
+    print!("{}", processor::inspect(Path::new(source_path), config, json)?);
+    Ok(())
+}
+
+fn refresh(args: &[String]) -> Result<(), Error> {
     let config = Config::from_file(&args[1])?;
     let source_path = PathBuf::from(&args[2]);
     let target_path = PathBuf::from(&args[3]);
@@ -28,5 +60,6 @@ fn run() -> Result<(), Error> {
 
     processor.process()?;
     println!("Processing completed successfully");
+    println!("Log written to {}", processor.log_path().display());
     Ok(())
-}
\ No newline at end of file
+}
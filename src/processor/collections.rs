@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::error::Error;
+use crate::processor::plan::{RulePlan, RuleRef};
+use crate::processor::sql::{ColumnInfo, TableInfo};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollectionKind {
+    Set,
+    Multiset,
+    List,
+}
+
+impl CollectionKind {
+    // The kind of a column's base type, e.g. "set" for set(varchar(20) not null)
+    pub fn from_type(base_type: &str) -> Option<Self> {
+        match base_type.to_ascii_lowercase().as_str() {
+            "set" => Some(CollectionKind::Set),
+            "multiset" => Some(CollectionKind::Multiset),
+            "list" => Some(CollectionKind::List),
+            _ => None,
+        }
+    }
+
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            CollectionKind::Set => "SET",
+            CollectionKind::Multiset => "MULTISET",
+            CollectionKind::List => "LIST",
+        }
+    }
+
+    // The collection with no elements, which is not NULL
+    pub fn empty_literal(&self) -> String {
+        format!("{}{{}}", self.keyword())
+    }
+}
+
+// One element of an unloaded collection
+#[derive(Clone, Debug, PartialEq)]
+pub enum Element {
+    // A character value, written in single quotes
+    Quoted(String),
+    // Numbers, dates written bare, nested collections and ROW(...) values
+    Bare(String),
+}
+
+// A collection value as unloaded, e.g. SET{'red','blue'} or LIST{1,2,3}
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collection {
+    pub kind: CollectionKind,
+    pub elements: Vec<Element>,
+}
+
+impl Collection {
+    // None for NULLs and values that aren't collection literals
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (keyword, rest) = value.split_once('{')?;
+        let kind = CollectionKind::from_type(keyword.trim())?;
+        let inner = rest.strip_suffix('}')?;
+
+        let mut elements = Vec::new();
+        if !inner.trim().is_empty() {
+            for element in split_elements(inner)? {
+                let element = element.trim();
+                let quoted = element.strip_prefix('\'').and_then(|e| e.strip_suffix('\''));
+                elements.push(match quoted {
+                    Some(text) => Element::Quoted(text.replace("''", "'")),
+                    None => Element::Bare(element.to_string()),
+                });
+            }
+        }
+        Some(Collection { kind, elements })
+    }
+
+    // Change each scalar element, including those of nested collections; ROW values are kept
+    pub fn map_elements(&mut self, change: &mut impl FnMut(&str) -> Result<String, Error>) -> Result<(), Error> {
+        for element in &mut self.elements {
+            match element {
+                Element::Quoted(text) => *text = change(text)?,
+                Element::Bare(text) => match Collection::parse(text) {
+                    Some(mut nested) => {
+                        nested.map_elements(change)?;
+                        *text = nested.to_string();
+                    }
+                    None if is_row(text) => {}
+                    None => *text = change(text)?,
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Collection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{{", self.kind.keyword())?;
+        for (i, element) in self.elements.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            match element {
+                Element::Quoted(text) => write!(f, "'{}'", text.replace('\'', "''"))?,
+                Element::Bare(text) => write!(f, "{}", text)?,
+            }
+        }
+        write!(f, "}}")
+    }
+}
+
+// Apply a value rule to a column value, element by element when the column is a collection
+pub fn map_value(
+    column: Option<&ColumnInfo>,
+    value: &str,
+    mut change: impl FnMut(&str) -> Result<String, Error>,
+) -> Result<String, Error> {
+    let is_collection = column.is_some_and(|c| CollectionKind::from_type(&c.base_type()).is_some());
+    match Collection::parse(value).filter(|_| is_collection) {
+        Some(mut collection) => {
+            collection.map_elements(&mut change)?;
+            Ok(collection.to_string())
+        }
+        None => change(value),
+    }
+}
+
+// Rules that replace a whole value would leave a SET, MULTISET, LIST or ROW column holding
+// something that isn't one; only null_fields and in-place transforms may write them
+pub fn check_rule_writes(plan: &RulePlan, table_info: &HashMap<String, TableInfo>) -> Result<(), Error> {
+    for (table, info) in table_info {
+        for step in plan.steps(table) {
+            if matches!(step.rule, RuleRef::NullFields(_)) {
+                continue;
+            }
+            for field in step.writes.iter().filter(|field| !step.transforms(field)) {
+                let column = info.columns.iter().find(|c| c.name.eq_ignore_ascii_case(field));
+                if let Some(column) = column.filter(|c| is_composite(c)) {
+                    return Err(Error::Config(format!(
+                        "{} would replace {}.{}, a {} column, with a plain value",
+                        step.rule.describe(),
+                        table,
+                        field,
+                        column.data_type
+                    )));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn is_composite(column: &ColumnInfo) -> bool {
+    let base_type = column.base_type();
+    base_type == "row" || CollectionKind::from_type(&base_type).is_some()
+}
+
+fn is_row(text: &str) -> bool {
+    text.get(..4).is_some_and(|start| start.eq_ignore_ascii_case("row("))
+}
+
+// Split on commas outside quotes, braces and parentheses; None if they don't balance
+fn split_elements(text: &str) -> Option<Vec<&str>> {
+    let mut elements = Vec::new();
+    let (mut quoted, mut depth, mut start) = (false, 0usize, 0);
+    for (i, c) in text.char_indices() {
+        match c {
+            '\'' => quoted = !quoted,
+            _ if quoted => {}
+            '{' | '(' => depth += 1,
+            '}' | ')' => depth = depth.checked_sub(1)?,
+            ',' if depth == 0 => {
+                elements.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if quoted || depth != 0 {
+        return None;
+    }
+    elements.push(&text[start..]);
+    Some(elements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format() {
+        let set = Collection::parse("SET{'red','it''s',''}").unwrap();
+        assert_eq!(set.kind, CollectionKind::Set);
+        assert_eq!(set.elements, vec![
+            Element::Quoted("red".to_string()),
+            Element::Quoted("it's".to_string()),
+            Element::Quoted(String::new()),
+        ]);
+        assert_eq!(set.to_string(), "SET{'red','it''s',''}");
+
+        let nested = Collection::parse("LIST{SET{1,2},ROW(3,'a,b')}").unwrap();
+        assert_eq!(nested.elements, vec![
+            Element::Bare("SET{1,2}".to_string()),
+            Element::Bare("ROW(3,'a,b')".to_string()),
+        ]);
+        assert_eq!(Collection::parse("MULTISET{}").unwrap().elements, vec![]);
+        assert_eq!(CollectionKind::List.empty_literal(), "LIST{}");
+
+        assert_eq!(Collection::parse("plain text"), None);
+        assert_eq!(Collection::parse("SET{'open}"), None);
+        assert_eq!(Collection::parse("BAG{1}"), None);
+    }
+
+    #[test]
+    fn test_map_value() -> Result<(), Error> {
+        let column = |data_type: &str| ColumnInfo { data_type: data_type.to_string(), ..Default::default() };
+        let upper = |value: &str| Ok(value.to_uppercase());
+        let tags = column("set(varchar(20) not null)");
+        assert_eq!(map_value(Some(&tags), "SET{'a','b'}", upper)?, "SET{'A','B'}");
+        let nested = column("list(set(varchar(5) not null) not null)");
+        assert_eq!(map_value(Some(&nested), "LIST{SET{'x'},ROW('y')}", upper)?, "LIST{SET{'X'},ROW('y')}");
+        // Text that only looks like a collection is a plain value
+        assert_eq!(map_value(Some(&column("varchar(20)")), "SET{'a'}", upper)?, "SET{'A'}");
+        assert_eq!(map_value(None, "plain", upper)?, "PLAIN");
+        Ok(())
+    }
+}
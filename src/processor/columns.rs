@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::processor::collections::CollectionKind;
+use crate::processor::dates::DateFormat;
+use crate::processor::sql::{ColumnInfo, TableInfo};
+use crate::processor::unl::UnlRow;
+
+// Datetime qualifier fields and how each extends a value starting at year
+const DATETIME_FIELDS: [(&str, &str); 7] = [
+    ("year", "1900"),
+    ("month", "-01"),
+    ("day", "-01"),
+    ("hour", " 00"),
+    ("minute", ":00"),
+    ("second", ":00"),
+    ("fraction", ".0"),
+];
+
+// A harmless value of the column's type, for `not null` columns that can't be emptied.
+// Dates are written in the export's DBDATE format.
+pub fn type_default(column: &ColumnInfo, date_format: &DateFormat) -> Option<String> {
+    let value = match column.base_type().as_str() {
+        "serial" | "serial8" | "bigserial" | "integer" | "int" | "int8" | "bigint" | "smallint"
+        | "decimal" | "dec" | "numeric" | "money" | "float" | "smallfloat" | "real" | "double" => "0",
+        // A zero-length character value unloads as a single blank; an empty field would be NULL
+        "char" | "character" | "nchar" | "varchar" | "nvarchar" | "lvarchar" => " ",
+        "date" => return NaiveDate::from_ymd_opt(1899, 12, 31).map(|date| date_format.format(date)),
+        "boolean" => "f",
+        "datetime" => return datetime_default(&column.data_type),
+        base_type => return CollectionKind::from_type(base_type).map(|kind| kind.empty_literal()),
+    };
+    Some(value.to_string())
+}
+
+// Midnight on 1900-01-01 in a "datetime year to <last>" column's literal format
+fn datetime_default(data_type: &str) -> Option<String> {
+    let words: Vec<String> = data_type.split(|c: char| c.is_whitespace() || c == '(')
+        .map(str::to_ascii_lowercase)
+        .collect();
+    if words.get(1).map(String::as_str) != Some("year") {
+        return None;
+    }
+    let last = words.get(3).map(String::as_str).unwrap_or("second");
+    let end = DATETIME_FIELDS.iter().position(|(name, _)| *name == last)?;
+    Some(DATETIME_FIELDS[..=end].iter().map(|(_, part)| *part).collect())
+}
+
+// Values written by null_fields rules and the columns left after drop_columns, checked
+// against the parsed schema before any data is written
+#[derive(Debug, Default)]
+pub struct ColumnRules {
+    // (field, value) for each null_fields rule, by rule index
+    null_values: HashMap<usize, Vec<(String, String)>>,
+    // Indices of the kept fields of tables that drop columns
+    kept: HashMap<String, Vec<usize>>,
+}
+
+impl ColumnRules {
+    pub fn new(config: &Config, table_info: &HashMap<String, TableInfo>) -> Result<Self, Error> {
+        let date_format = DateFormat::parse(&config.export.dbdate)?;
+        let mut null_values = HashMap::new();
+        for (i, rule) in config.null_fields.iter().enumerate() {
+            let info = match table_info.get(&rule.table) {
+                Some(info) => info,
+                None => continue,
+            };
+            let mut values = Vec::new();
+            for field in &rule.fields {
+                let column = find_column(info, &rule.table, field)?;
+                let value = match (&rule.default, rule.type_default) {
+                    (Some(default), _) => default.clone(),
+                    (None, true) => type_default(column, &date_format).unwrap_or_default(),
+                    (None, false) => String::new(),
+                };
+                if value.is_empty() && column.not_null {
+                    return Err(Error::Config(format!(
+                        "null_fields[{}] cannot null {}.{}: the column is declared not null; set a default{}",
+                        i,
+                        rule.table,
+                        field,
+                        if rule.type_default { format!(" (type {} has none)", column.data_type) } else { " or type_default".to_string() }
+                    )));
+                }
+                values.push((column.name.clone(), value));
+            }
+            null_values.insert(i, values);
+        }
+
+        let mut dropped: HashMap<&str, Vec<&String>> = HashMap::new();
+        for rule in &config.drop_columns {
+            if config.excluded_tables.contains(&rule.table) {
+                continue;
+            }
+            let info = table_info.get(&rule.table).ok_or_else(|| Error::Config(format!(
+                "drop_columns names table '{}', which is not in the export",
+                rule.table
+            )))?;
+            for field in &rule.fields {
+                find_column(info, &rule.table, field)?;
+                if let Some(key) = key_using(table_info, &rule.table, field) {
+                    return Err(Error::Config(format!(
+                        "Cannot drop {}.{}: it is part of {}",
+                        rule.table,
+                        field,
+                        key
+                    )));
+                }
+                dropped.entry(rule.table.as_str()).or_default().push(field);
+            }
+        }
+
+        let mut kept = HashMap::new();
+        for (table, fields) in dropped {
+            let info = &table_info[table];
+            let indices: Vec<usize> = (0..info.fields.len())
+                .filter(|&idx| !fields.iter().any(|f| f.eq_ignore_ascii_case(&info.fields[idx])))
+                .collect();
+            if indices.is_empty() {
+                return Err(Error::Config(format!(
+                    "drop_columns removes every column of table '{}'; use excluded_tables instead",
+                    table
+                )));
+            }
+            kept.insert(table.to_string(), indices);
+        }
+
+        Ok(ColumnRules { null_values, kept })
+    }
+
+    pub fn null_values(&self, rule: usize) -> &[(String, String)] {
+        self.null_values.get(&rule).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    // The table's field names once dropped columns are removed, if it drops any
+    pub fn kept_fields(&self, table_name: &str, info: &TableInfo) -> Option<Vec<String>> {
+        self.kept.get(table_name)
+            .map(|indices| indices.iter().map(|&idx| info.fields[idx].clone()).collect())
+    }
+
+    // Whether drop_columns leaves the field in the written rows
+    pub fn keeps(&self, table_name: &str, idx: usize) -> bool {
+        self.kept.get(table_name).is_none_or(|indices| indices.contains(&idx))
+    }
+
+    pub fn drop_fields(&self, table_name: &str, row: UnlRow) -> UnlRow {
+        match self.kept.get(table_name) {
+            Some(indices) => UnlRow {
+                fields: indices.iter().filter_map(|&idx| row.fields.get(idx).cloned()).collect(),
+            },
+            None => row,
+        }
+    }
+}
+
+fn find_column<'a>(info: &'a TableInfo, table_name: &str, field: &str) -> Result<&'a ColumnInfo, Error> {
+    info.columns.iter()
+        .find(|c| c.name.eq_ignore_ascii_case(field))
+        .ok_or_else(|| Error::Config(format!("Column '{}' not found in table '{}'", field, table_name)))
+}
+
+// The key of the table, or a foreign key pointing at it, that includes the column
+fn key_using(table_info: &HashMap<String, TableInfo>, table_name: &str, field: &str) -> Option<String> {
+    let info = &table_info[table_name];
+    let contains = |columns: &[String]| columns.iter().any(|c| c.eq_ignore_ascii_case(field));
+
+    if contains(&info.primary_key) {
+        return Some("the primary key".to_string());
+    }
+    if info.unique_keys.iter().any(|key| contains(key)) {
+        return Some("a unique key".to_string());
+    }
+    if let Some(fk) = info.foreign_keys.iter().find(|fk| contains(&fk.columns)) {
+        return Some(format!("a foreign key to {}", fk.parent_table));
+    }
+    let mut children: Vec<&String> = table_info.iter()
+        .filter(|(_, child)| child.foreign_keys.iter().any(|fk| fk.parent_table == table_name && contains(&fk.parent_columns)))
+        .map(|(child, _)| child)
+        .collect();
+    children.sort();
+    children.first().map(|child| format!("the key {} references", child))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::sql::parse_sql_file;
+
+    const SQL: &str = r#"
+        { TABLE "informix".users row size = 300 number of columns = 6 index size = 9 }
+        { unload file name = users00100.unl number of rows = 2 }
+        create table "informix".users 
+        (
+            id serial not null,
+            password_hash char(64) not null,
+            api_token varchar(100),
+            balance money(12,2) not null,
+            last_login datetime year to minute not null,
+            photo byte not null,
+            primary key (id)
+        ) extent size 16 next size 16 lock mode row;
+    "#;
+
+    fn rules(null_fields: &str, drop_columns: &str) -> Result<ColumnRules, Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.null_fields = serde_yaml::from_str(null_fields)?;
+        config.drop_columns = serde_yaml::from_str(drop_columns)?;
+        ColumnRules::new(&config, &parse_sql_file(SQL)?)
+    }
+
+    #[test]
+    fn test_type_defaults() -> Result<(), Error> {
+        let rules = rules(r#"
+            - table: users
+              fields: [api_token]
+            - table: users
+              fields: [password_hash, balance, last_login]
+              type_default: true
+            - table: users
+              fields: [password_hash]
+              default: REDACTED
+        "#, "[]")?;
+        assert_eq!(rules.null_values(0), &[("api_token".to_string(), String::new())]);
+        assert_eq!(rules.null_values(1), &[
+            ("password_hash".to_string(), " ".to_string()),
+            ("balance".to_string(), "0".to_string()),
+            ("last_login".to_string(), "1900-01-01 00:00".to_string()),
+        ]);
+        assert_eq!(rules.null_values(2)[0].1, "REDACTED");
+        Ok(())
+    }
+
+    #[test]
+    fn test_not_null_columns_need_a_default() {
+        let err = rules("[{ table: users, fields: [password_hash] }]", "[]").unwrap_err();
+        assert!(matches!(err, Error::Config(ref msg) if msg.contains("users.password_hash")));
+
+        // BYTE has no type default
+        let err = rules("[{ table: users, fields: [photo], type_default: true }]", "[]").unwrap_err();
+        assert!(matches!(err, Error::Config(ref msg) if msg.contains("byte")));
+
+        assert!(matches!(rules("[{ table: users, fields: [missing] }]", "[]"), Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn test_drop_columns() -> Result<(), Error> {
+        let rules_with_drop = rules("[]", "[{ table: users, fields: [password_hash, photo] }]")?;
+        let row = UnlRow::from_line("1|abc|tok|1.00|2024-01-01 10:00|2f2f|")?;
+        assert_eq!(rules_with_drop.drop_fields("users", row).to_line(), "1|tok|1.00|2024-01-01 10:00|");
+
+        let info = &parse_sql_file(SQL)?["users"];
+        assert_eq!(
+            rules_with_drop.kept_fields("users", info),
+            Some(vec!["id".to_string(), "api_token".to_string(), "balance".to_string(), "last_login".to_string()])
+        );
+
+        let err = rules("[]", "[{ table: users, fields: [id] }]").unwrap_err();
+        assert!(matches!(err, Error::Config(ref msg) if msg.contains("primary key")));
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+use crate::error::Error;
+
+// How DATE values are unloaded, from an Informix DBDATE setting: the order of month, day
+// and 2- or 4-digit year followed by the separator ("/", "-", "." or "0" for none),
+// e.g. "MDY4/" (the default) or "Y4MD-"
+#[derive(Clone, Debug, PartialEq)]
+pub struct DateFormat {
+    pattern: String,
+}
+
+impl DateFormat {
+    pub fn parse(dbdate: &str) -> Result<Self, Error> {
+        let invalid = || Error::Config(format!("Invalid DBDATE format '{}'", dbdate));
+        let spec = dbdate.trim().to_ascii_uppercase();
+        let mut chars = spec.chars().peekable();
+        let mut fields = Vec::new();
+        let mut separator = "/";
+
+        while let Some(c) = chars.next() {
+            match c {
+                'M' => fields.push("%m"),
+                'D' => fields.push("%d"),
+                'Y' => match chars.next() {
+                    Some('2') => fields.push("%y"),
+                    Some('4') => fields.push("%Y"),
+                    _ => return Err(invalid()),
+                },
+                '/' | '-' | '.' if chars.peek().is_none() => separator = &spec[spec.len() - 1..],
+                '0' if chars.peek().is_none() => separator = "",
+                _ => return Err(invalid()),
+            }
+        }
+
+        let mut sorted = fields.clone();
+        sorted.sort_unstable();
+        sorted.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        if fields.len() != 3 || sorted.len() != 3 {
+            return Err(invalid());
+        }
+        Ok(DateFormat { pattern: fields.join(separator) })
+    }
+
+    pub fn parse_date(&self, value: &str) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(value.trim(), &self.pattern).ok()
+    }
+
+    pub fn format(&self, date: NaiveDate) -> String {
+        date.format(&self.pattern).to_string()
+    }
+}
+
+impl Default for DateFormat {
+    fn default() -> Self {
+        DateFormat { pattern: "%m/%d/%Y".to_string() }
+    }
+}
+
+// An unloaded DATETIME value, e.g. "2021-03-04 10:11:12.000", keeping the fields its
+// column's qualifier starts at year and ends with
+#[derive(Clone, Debug, PartialEq)]
+pub struct DatetimeLiteral {
+    pub value: NaiveDateTime,
+    // Fields present, from 1 (year) to 6 (second)
+    fields: usize,
+    fraction_digits: usize,
+}
+
+impl DatetimeLiteral {
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (date, time) = match value.split_once(' ') {
+            Some((date, time)) => (date, Some(time.trim_start())),
+            None => (value, None),
+        };
+
+        let date_parts = numbers(date, '-')?;
+        if date_parts.is_empty() || date_parts.len() > 3 || date.split('-').next()?.len() != 4 {
+            return None;
+        }
+        let (time_parts, fraction) = match time {
+            Some(_) if date_parts.len() < 3 => return None,
+            Some(time) => {
+                let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+                let parts = numbers(time, ':')?;
+                if parts.len() > 3 || (!fraction.is_empty() && parts.len() < 3) {
+                    return None;
+                }
+                (parts, fraction)
+            }
+            None => (Vec::new(), ""),
+        };
+        if fraction.len() > 9 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let part = |parts: &[u32], i: usize, default: u32| parts.get(i).copied().unwrap_or(default);
+        let date = NaiveDate::from_ymd_opt(date_parts[0] as i32, part(&date_parts, 1, 1), part(&date_parts, 2, 1))?;
+        let nanos = format!("{:0<9}", fraction).parse().ok()?;
+        let time = NaiveTime::from_hms_nano_opt(part(&time_parts, 0, 0), part(&time_parts, 1, 0), part(&time_parts, 2, 0), nanos)?;
+
+        Some(DatetimeLiteral {
+            value: date.and_time(time),
+            fields: date_parts.len() + time_parts.len(),
+            fraction_digits: fraction.len(),
+        })
+    }
+
+    pub fn with_value(&self, value: NaiveDateTime) -> Self {
+        DatetimeLiteral { value, ..self.clone() }
+    }
+}
+
+impl std::fmt::Display for DatetimeLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let v = &self.value;
+        let parts = [v.month(), v.day(), v.hour(), v.minute(), v.second()];
+        let separators = ["-", "-", " ", ":", ":"];
+
+        write!(f, "{:04}", v.year())?;
+        for (part, separator) in parts.iter().zip(separators).take(self.fields.saturating_sub(1)) {
+            write!(f, "{}{:02}", separator, part)?;
+        }
+        if self.fraction_digits > 0 {
+            let nanos = format!("{:09}", v.nanosecond());
+            write!(f, ".{}", &nanos[..self.fraction_digits])?;
+        }
+        Ok(())
+    }
+}
+
+fn numbers(text: &str, separator: char) -> Option<Vec<u32>> {
+    text.split(separator)
+        .map(|part| {
+            if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            part.parse().ok()
+        })
+        .collect()
+}
+
+// Apply `change` to a UNL DATE (in the DBDATE format) or DATETIME value, writing the
+// result back in the same format. None if the value is neither.
+pub fn map_value(
+    value: &str,
+    format: &DateFormat,
+    change: impl FnOnce(NaiveDateTime) -> Option<NaiveDateTime>,
+) -> Option<String> {
+    if let Some(date) = format.parse_date(value) {
+        let changed = change(date.and_time(NaiveTime::MIN))?;
+        return Some(format.format(changed.date()));
+    }
+    let literal = DatetimeLiteral::parse(value)?;
+    let changed = change(literal.value)?;
+    Some(literal.with_value(changed).to_string())
+}
+
+// Move a value by whole days, keeping its time of day
+pub fn shift_value(value: &str, format: &DateFormat, days: i64) -> Option<String> {
+    map_value(value, format, |datetime| datetime.checked_add_signed(chrono::Duration::days(days)))
+}
+
+// Truncate a value to the first day of its year, or of its month
+pub fn generalize_value(value: &str, format: &DateFormat, keep_month: bool) -> Option<String> {
+    map_value(value, format, |datetime| {
+        let month = if keep_month { datetime.month() } else { 1 };
+        NaiveDate::from_ymd_opt(datetime.year(), month, 1).map(|date| date.and_time(NaiveTime::MIN))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dbdate_formats() -> Result<(), Error> {
+        let date = NaiveDate::from_ymd_opt(2021, 3, 4).unwrap();
+        for (dbdate, text) in [("MDY4/", "03/04/2021"), ("DMY4.", "04.03.2021"), ("Y4MD-", "2021-03-04"), ("MDY2", "03/04/21"), ("Y4MD0", "20210304")] {
+            let format = DateFormat::parse(dbdate)?;
+            assert_eq!(format.parse_date(text), Some(date), "{}", dbdate);
+            assert_eq!(format.format(date), text);
+        }
+        assert_eq!(DateFormat::default(), DateFormat::parse("mdy4/")?);
+        for invalid in ["MDY", "MMY4/", "MDY4/x", "Y3MD"] {
+            assert!(matches!(DateFormat::parse(invalid), Err(Error::Config(_))), "{}", invalid);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_datetime_literals_keep_their_fields() {
+        for literal in ["2021-03-04 10:11:12.12345", "2021-03-04 10:11", "2021-03-04", "2021-03", "2021"] {
+            assert_eq!(DatetimeLiteral::parse(literal).unwrap().to_string(), literal);
+        }
+        assert_eq!(DatetimeLiteral::parse("10:11:12"), None);
+        assert_eq!(DatetimeLiteral::parse("2021-02-30"), None);
+    }
+
+    #[test]
+    fn test_shift_and_generalize() {
+        let format = DateFormat::default();
+        assert_eq!(shift_value("12/30/2020", &format, 3).as_deref(), Some("01/02/2021"));
+        assert_eq!(shift_value("2021-03-04 10:11:12.000", &format, -4).as_deref(), Some("2021-02-28 10:11:12.000"));
+        assert_eq!(generalize_value("07/19/1984", &format, false).as_deref(), Some("01/01/1984"));
+        assert_eq!(generalize_value("1984-07-19 10:11", &format, true).as_deref(), Some("1984-07-01 00:00"));
+        assert_eq!(shift_value("n/a", &format, 1), None);
+    }
+}
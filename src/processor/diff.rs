@@ -0,0 +1,292 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufReader, ErrorKind};
+use std::path::Path;
+
+use crate::error::Error;
+use crate::processor::sql::{self, TableInfo};
+use crate::processor::unl::{UnlReader, UnlRow};
+
+// Changed rows shown for each table
+const SAMPLE_ROWS: usize = 3;
+// Longer values are cut short in samples
+const SAMPLE_WIDTH: usize = 40;
+
+// Table by table differences between two export directories, e.g. source and target
+pub fn diff_exports(left: &Path, right: &Path) -> Result<String, Error> {
+    let left_tables = sql::parse_sql_file(&fs::read_to_string(sql::schema_path(left)?)?)?;
+    let right_tables = sql::parse_sql_file(&fs::read_to_string(sql::schema_path(right)?)?)?;
+    let tables: BTreeSet<&String> = left_tables.keys().chain(right_tables.keys()).collect();
+
+    let mut out = format!("left:  {}\nright: {}\n", left.display(), right.display());
+    for table in tables {
+        match (left_tables.get(table), right_tables.get(table)) {
+            (Some(l), Some(r)) => out.push_str(&diff_table(table, read_rows(left, l)?, read_rows(right, r)?, l, r)),
+            (Some(l), None) => out.push_str(&format!("{}: only in left, {}\n", table, rows(&read_rows(left, l)?))),
+            (None, Some(r)) => out.push_str(&format!("{}: only in right, {}\n", table, rows(&read_rows(right, r)?))),
+            (None, None) => {}
+        }
+    }
+    Ok(out)
+}
+
+// None when the export has no data file for the table, as for excluded tables
+fn read_rows(dir: &Path, info: &TableInfo) -> Result<Option<Vec<UnlRow>>, Error> {
+    let file = match File::open(dir.join(&info.unl_file)) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    UnlReader::new(BufReader::new(file)).collect::<Result<_, _>>().map(Some)
+}
+
+fn rows(rows: &Option<Vec<UnlRow>>) -> String {
+    match rows {
+        Some(rows) => format!("{} rows", rows.len()),
+        None => "no data file".to_string(),
+    }
+}
+
+fn diff_table(
+    table: &str,
+    left_rows: Option<Vec<UnlRow>>,
+    right_rows: Option<Vec<UnlRow>>,
+    left: &TableInfo,
+    right: &TableInfo,
+) -> String {
+    let mut details = Vec::new();
+    for column in left.columns.iter().filter(|c| !right.fields.contains(&c.name)) {
+        details.push(format!("column removed: {}", column.name));
+    }
+    for column in right.columns.iter().filter(|c| !left.fields.contains(&c.name)) {
+        details.push(format!("column added: {} ({})", column.name, column.data_type));
+    }
+    for column in &left.columns {
+        if let Some(other) = right.columns.iter().find(|c| c.name == column.name && c.data_type != column.data_type) {
+            details.push(format!("column type changed: {} {} -> {}", column.name, column.data_type, other.data_type));
+        }
+    }
+
+    let (left_rows, right_rows) = match (left_rows, right_rows) {
+        (Some(l), Some(r)) => (l, r),
+        (l, r) => return summary(format!("{}: {} -> {}", table, rows(&l), rows(&r)), details),
+    };
+    let heading = if left_rows.len() == right_rows.len() {
+        format!("{}: {} rows", table, left_rows.len())
+    } else {
+        let delta = right_rows.len() as i64 - left_rows.len() as i64;
+        format!("{}: {} -> {} rows ({:+})", table, left_rows.len(), right_rows.len(), delta)
+    };
+
+    // Columns present on both sides, with their index on each
+    let common: Vec<(&str, usize, usize)> = left.fields.iter().enumerate()
+        .filter_map(|(i, name)| right.fields.iter().position(|f| f == name).map(|j| (name.as_str(), i, j)))
+        .collect();
+    let matched = match_rows(&left_rows, &right_rows, left, right);
+
+    let mut changed_rows = 0;
+    let mut changed_columns: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut samples = Vec::new();
+    for (label, l, r) in &matched.pairs {
+        let changed: Vec<&(&str, usize, usize)> = common.iter()
+            .filter(|(_, i, j)| l.get_field(*i) != r.get_field(*j))
+            .collect();
+        if changed.is_empty() {
+            continue;
+        }
+        changed_rows += 1;
+        for (name, _, _) in &changed {
+            *changed_columns.entry(name).or_default() += 1;
+        }
+        if samples.len() < SAMPLE_ROWS {
+            let mut sample = format!("{}:", label);
+            for (name, i, j) in changed {
+                let value = |row: &UnlRow, index: usize| shown(row.get_field(index).unwrap_or_default());
+                sample.push_str(&format!("\n    {}: {} | {}", name, value(l, *i), value(r, *j)));
+            }
+            samples.push(sample);
+        }
+    }
+
+    if details.is_empty() && matched.only_left == 0 && matched.only_right == 0 && changed_rows == 0 {
+        return format!("{}, unchanged\n", heading);
+    }
+    if let Some(key) = &matched.key {
+        details.push(format!("matched by primary key ({})", key));
+    } else if !left_rows.is_empty() && !right_rows.is_empty() {
+        details.push("matched by position, no primary key on both sides".to_string());
+    }
+    if matched.only_left > 0 || matched.only_right > 0 || changed_rows > 0 {
+        details.push(format!(
+            "{} of {} matched rows changed, {} only in left, {} only in right",
+            changed_rows,
+            matched.pairs.len(),
+            matched.only_left,
+            matched.only_right
+        ));
+    }
+    if !changed_columns.is_empty() {
+        let counts: Vec<String> = changed_columns.iter().map(|(name, count)| format!("{} {}", name, count)).collect();
+        details.push(format!("changed values: {}", counts.join(", ")));
+    }
+    details.extend(samples);
+    summary(heading, details)
+}
+
+fn summary(heading: String, details: Vec<String>) -> String {
+    let mut out = heading + "\n";
+    for line in details {
+        out.push_str(&format!("  {}\n", line));
+    }
+    out
+}
+
+struct Matched<'a> {
+    // Key columns when rows were matched by primary key, comma separated
+    key: Option<String>,
+    // Row label, e.g. "id=1001" or "row 3", with the left and right row
+    pairs: Vec<(String, &'a UnlRow, &'a UnlRow)>,
+    only_left: usize,
+    only_right: usize,
+}
+
+// Pair up rows by primary key when both sides share one, and by position otherwise
+fn match_rows<'a>(left_rows: &'a [UnlRow], right_rows: &'a [UnlRow], left: &TableInfo, right: &TableInfo) -> Matched<'a> {
+    let position = |info: &TableInfo, column: &String| info.fields.iter().position(|f| f == column);
+    let key_columns: Option<Vec<(&String, usize, usize)>> = (!left.primary_key.is_empty() && left.primary_key == right.primary_key)
+        .then(|| left.primary_key.iter().map(|c| Some((c, position(left, c)?, position(right, c)?))).collect())
+        .flatten();
+
+    let key_columns = match key_columns {
+        Some(key_columns) => key_columns,
+        None => {
+            let pairs = left_rows.iter().zip(right_rows)
+                .enumerate()
+                .map(|(n, (l, r))| (format!("row {}", n + 1), l, r))
+                .collect();
+            return Matched {
+                key: None,
+                pairs,
+                only_left: left_rows.len().saturating_sub(right_rows.len()),
+                only_right: right_rows.len().saturating_sub(left_rows.len()),
+            };
+        }
+    };
+
+    let key = |row: &'a UnlRow, right_side: bool| -> Vec<&'a str> {
+        key_columns.iter()
+            .map(|(_, i, j)| row.get_field(if right_side { *j } else { *i }).unwrap_or_default())
+            .collect()
+    };
+    let by_key: HashMap<Vec<&str>, &UnlRow> = right_rows.iter().map(|row| (key(row, true), row)).collect();
+    let mut seen = HashSet::new();
+    let mut pairs = Vec::new();
+    let mut only_left = 0;
+    for row in left_rows {
+        let values = key(row, false);
+        match by_key.get(&values) {
+            Some(other) => {
+                let label: Vec<String> = key_columns.iter().zip(&values).map(|((c, _, _), v)| format!("{}={}", c, v)).collect();
+                pairs.push((label.join(","), row, *other));
+                seen.insert(values);
+            }
+            None => only_left += 1,
+        }
+    }
+    Matched {
+        key: Some(key_columns.iter().map(|(c, _, _)| c.as_str()).collect::<Vec<_>>().join(", ")),
+        pairs,
+        only_left,
+        only_right: by_key.len() - seen.len(),
+    }
+}
+
+fn shown(value: &str) -> String {
+    if value.chars().count() <= SAMPLE_WIDTH {
+        return value.to_string();
+    }
+    let cut: String = value.chars().take(SAMPLE_WIDTH - 3).collect();
+    format!("{}...", cut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn export(dir: &Path, schema_file: &str, sql: &str, files: &[(&str, &str)]) -> Result<(), Error> {
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(schema_file), sql)?;
+        for (name, content) in files {
+            fs::write(dir.join(name), content)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_exports() -> Result<(), Error> {
+        let temp_dir = TempDir::new()?;
+        let (left, right) = (temp_dir.path().join("left.exp"), temp_dir.path().join("right"));
+        let schema = r#"
+            { TABLE "informix".customers row size = 100 number of columns = 3 index size = 0 }
+            { unload file name = custo00100.unl number of rows = 3 }
+            create table "informix".customers (id serial not null, email varchar(100), phone varchar(20), primary key (id));
+            { TABLE "informix".notes row size = 100 number of columns = 1 index size = 0 }
+            { unload file name = notes00101.unl number of rows = 2 }
+            create table "informix".notes (body varchar(100));
+            { TABLE "informix".states row size = 10 number of columns = 1 index size = 0 }
+            { unload file name = state00102.unl number of rows = 1 }
+            create table "informix".states (code char(2));
+        "#;
+        export(&left, "left.sql", schema, &[
+            ("custo00100.unl", "1|a@example.com|555-1234|\n2|b@example.com|555-5678|\n3|c@example.com|555-0000|\n"),
+            ("notes00101.unl", "first|\nsecond|\n"),
+            ("state00102.unl", "WA|\n"),
+        ])?;
+        let right_schema = schema
+            .replace("phone varchar(20)", "phone varchar(32)")
+            .replace("{ TABLE \"informix\".states", "{ TABLE \"informix\".regions")
+            .replace("create table \"informix\".states", "create table \"informix\".regions");
+        export(&right, "test_live.sql", &right_schema, &[
+            ("custo00100.unl", "2|happy.fox@example.com|555-5678|\n1|quick.dog@example.com|555-0000|\n"),
+            ("notes00101.unl", "first|\nsecond|\n"),
+        ])?;
+
+        let diff = diff_exports(&left, &right)?;
+        assert!(diff.contains(&format!("left:  {}\n", left.display())));
+        assert!(diff.contains(&[
+            "customers: 3 -> 2 rows (-1)",
+            "  column type changed: phone varchar(20) -> varchar(32)",
+            "  matched by primary key (id)",
+            "  2 of 2 matched rows changed, 1 only in left, 0 only in right",
+            "  changed values: email 2, phone 1",
+            "  id=1:",
+            "    email: a@example.com | quick.dog@example.com",
+            "    phone: 555-1234 | 555-0000",
+            "  id=2:",
+            "    email: b@example.com | happy.fox@example.com",
+        ].join("\n")), "{}", diff);
+        assert!(diff.contains("notes: 2 rows, unchanged\n"), "{}", diff);
+        assert!(diff.contains("regions: only in right, no data file\n"), "{}", diff);
+        assert!(diff.contains("states: only in left, 1 rows\n"), "{}", diff);
+
+        let empty = temp_dir.path().join("empty.exp");
+        fs::create_dir(&empty)?;
+        let err = diff_exports(&left, &empty).unwrap_err().to_string();
+        assert!(err.contains(&empty.join("empty.sql").display().to_string()), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_match_rows_by_position_without_key() {
+        let info = TableInfo { fields: vec!["body".to_string()], ..Default::default() };
+        let left = vec![UnlRow::from_line("a|").unwrap(), UnlRow::from_line("b|").unwrap()];
+        let right = vec![UnlRow::from_line("a|").unwrap()];
+        let matched = match_rows(&left, &right, &info, &info);
+        assert_eq!(matched.key, None);
+        assert_eq!(matched.pairs.len(), 1);
+        assert_eq!(matched.pairs[0].0, "row 1");
+        assert_eq!((matched.only_left, matched.only_right), (1, 0));
+        assert_eq!(shown(&"x".repeat(50)), format!("{}...", "x".repeat(37)));
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use regex::RegexBuilder;
+use sha2::{Digest, Sha256};
+
+use crate::config::{Config, LargeObjectAction};
+use crate::error::Error;
+use crate::processor::plan::RulePlan;
+use crate::processor::sql::TableInfo;
+
+// Every exported table's columns and declared types, in schema order
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Fingerprint {
+    tables: BTreeMap<String, Vec<(String, String)>>,
+}
+
+impl Fingerprint {
+    pub fn new(table_info: &HashMap<String, TableInfo>) -> Self {
+        let tables = table_info.iter()
+            .map(|(table, info)| {
+                let columns = info.columns.iter().map(|c| (c.name.clone(), c.data_type.clone())).collect();
+                (table.clone(), columns)
+            })
+            .collect();
+        Fingerprint { tables }
+    }
+
+    // None when there is no fingerprint yet
+    pub fn read(path: &Path) -> Result<Option<Self>, Error> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut tables: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+        for (n, line) in content.lines().enumerate() {
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            let mut parts = line.splitn(3, '\t');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(table), Some(column), Some(data_type)) => {
+                    tables.entry(table.to_string()).or_default().push((column.to_string(), data_type.to_string()));
+                }
+                _ => return Err(Error::Config(format!(
+                    "Schema fingerprint {} line {} is not table, column and type separated by tabs",
+                    path.display(),
+                    n + 1
+                ))),
+            }
+        }
+        Ok(Some(Fingerprint { tables }))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let content = format!(
+            "# Schema fingerprint {}; each export is compared with it\n{}",
+            self.digest(),
+            self.lines()
+        );
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    // Short hash of the schema, to tell at a glance whether two runs saw the same one
+    pub fn digest(&self) -> String {
+        Sha256::digest(self.lines().as_bytes())
+            .iter()
+            .take(6)
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    fn lines(&self) -> String {
+        let mut lines = String::new();
+        for (table, columns) in &self.tables {
+            for (column, data_type) in columns {
+                lines.push_str(&format!("{}\t{}\t{}\n", table, column, data_type));
+            }
+        }
+        lines
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    TableAdded(String),
+    TableRemoved(String),
+    TableRenamed(String, String),
+    ColumnAdded(String, String, String),
+    ColumnRemoved(String, String),
+    ColumnRenamed(String, String, String),
+    TypeChanged(String, String, String, String),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::TableAdded(table) => write!(f, "table added: {}", table),
+            Change::TableRemoved(table) => write!(f, "table removed: {}", table),
+            Change::TableRenamed(from, to) => write!(f, "table renamed: {} -> {}", from, to),
+            Change::ColumnAdded(table, column, data_type) => write!(f, "column added: {}.{} ({})", table, column, data_type),
+            Change::ColumnRemoved(table, column) => write!(f, "column removed: {}.{}", table, column),
+            Change::ColumnRenamed(table, from, to) => write!(f, "column renamed: {}.{} -> {}.{}", table, from, table, to),
+            Change::TypeChanged(table, column, from, to) => {
+                write!(f, "column type changed: {}.{} {} -> {}", table, column, from, to)
+            }
+        }
+    }
+}
+
+// Changes from the previous schema to the current one. A removed table and an added one with
+// the same columns are a rename, as are a removed and an added column of the same type at the
+// same position.
+pub fn compare(previous: &Fingerprint, current: &Fingerprint) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut added: Vec<&String> = current.tables.keys().filter(|t| !previous.tables.contains_key(*t)).collect();
+
+    for (table, columns) in &previous.tables {
+        let current_columns = match current.tables.get(table) {
+            Some(current_columns) => current_columns,
+            None => {
+                match added.iter().position(|t| current.tables[*t] == *columns) {
+                    Some(i) => changes.push(Change::TableRenamed(table.clone(), added.remove(i).clone())),
+                    None => changes.push(Change::TableRemoved(table.clone())),
+                }
+                continue;
+            }
+        };
+
+        let find = |columns: &[(String, String)], name: &str| columns.iter().position(|(c, _)| c == name);
+        let mut renamed_to = Vec::new();
+        for (i, (column, data_type)) in columns.iter().enumerate() {
+            match find(current_columns, column) {
+                Some(j) if current_columns[j].1 != *data_type => changes.push(Change::TypeChanged(
+                    table.clone(),
+                    column.clone(),
+                    data_type.clone(),
+                    current_columns[j].1.clone(),
+                )),
+                Some(_) => {}
+                None => match current_columns.get(i) {
+                    Some((new, new_type)) if new_type == data_type && find(columns, new).is_none() => {
+                        changes.push(Change::ColumnRenamed(table.clone(), column.clone(), new.clone()));
+                        renamed_to.push(new);
+                    }
+                    _ => changes.push(Change::ColumnRemoved(table.clone(), column.clone())),
+                },
+            }
+        }
+        for (column, data_type) in current_columns {
+            if find(columns, column).is_none() && !renamed_to.contains(&column) {
+                changes.push(Change::ColumnAdded(table.clone(), column.clone(), data_type.clone()));
+            }
+        }
+    }
+
+    changes.extend(added.into_iter().map(|table| Change::TableAdded(table.clone())));
+    changes
+}
+
+// New columns, as table.column, whose names look like personal data and that no rule
+// scrubs, nulls or drops
+pub fn unruled_pii(changes: &[Change], current: &Fingerprint, config: &Config, plan: &RulePlan) -> Result<Vec<String>, Error> {
+    let patterns = config.schema_drift.pii_patterns.iter()
+        .map(|pattern| RegexBuilder::new(pattern).case_insensitive(true).build()
+            .map_err(|e| Error::Config(format!("Invalid schema_drift pii pattern '{}': {}", pattern, e))))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let mut new_columns: Vec<(&str, &str)> = Vec::new();
+    for change in changes {
+        match change {
+            // Rules name tables, so those of the old name no longer apply to any column
+            Change::TableAdded(table) | Change::TableRenamed(_, table) => {
+                let columns = current.tables.get(table).map(Vec::as_slice).unwrap_or_default();
+                new_columns.extend(columns.iter().map(|(column, _)| (table.as_str(), column.as_str())));
+            }
+            Change::ColumnAdded(table, column, _) | Change::ColumnRenamed(table, _, column) => {
+                new_columns.push((table, column));
+            }
+            _ => {}
+        }
+    }
+
+    let same = |a: &str, b: &str| a.eq_ignore_ascii_case(b);
+    let covered = |table: &str, column: &str| {
+        config.excluded_tables.iter().any(|t| t == table)
+            || config.schema_drift.allow.iter().any(|name| same(name, &format!("{}.{}", table, column)))
+            || plan.steps(table).iter().any(|step| step.writes.iter().any(|c| same(c, column)))
+            || config.drop_columns.iter().any(|r| r.table == table && r.fields.iter().any(|c| same(c, column)))
+            || config.large_objects.iter().any(|r| {
+                r.table == table && r.action == LargeObjectAction::Drop && r.fields.iter().any(|c| same(c, column))
+            })
+    };
+
+    Ok(new_columns.into_iter()
+        .filter(|(_, column)| patterns.iter().any(|pattern| pattern.is_match(column)))
+        .filter(|(table, column)| !covered(table, column))
+        .map(|(table, column)| format!("{}.{}", table, column))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn fingerprint(tables: &[(&str, &[(&str, &str)])]) -> Fingerprint {
+        let tables = tables.iter()
+            .map(|(table, columns)| {
+                (table.to_string(), columns.iter().map(|(c, t)| (c.to_string(), t.to_string())).collect())
+            })
+            .collect();
+        Fingerprint { tables }
+    }
+
+    #[test]
+    fn test_compare_schemas() -> Result<(), Error> {
+        let previous = fingerprint(&[
+            ("customers", &[("id", "serial"), ("phone", "varchar(20)"), ("notes", "text")]),
+            ("audit", &[("id", "serial"), ("at", "datetime year to second")]),
+            ("legacy", &[("id", "integer")]),
+        ]);
+        let current = fingerprint(&[
+            ("customers", &[("id", "serial8"), ("mobile_phone", "varchar(20)"), ("email", "varchar(100)")]),
+            ("audit_log", &[("id", "serial"), ("at", "datetime year to second")]),
+            ("contacts", &[("id", "serial"), ("home_address", "varchar(200)")]),
+        ]);
+
+        let changes: Vec<String> = compare(&previous, &current).iter().map(Change::to_string).collect();
+        assert_eq!(changes, vec![
+            "table renamed: audit -> audit_log",
+            "column type changed: customers.id serial -> serial8",
+            "column renamed: customers.phone -> customers.mobile_phone",
+            "column removed: customers.notes",
+            "column added: customers.email (varchar(100))",
+            "table removed: legacy",
+            "table added: contacts",
+        ]);
+        assert!(compare(&current, &current).is_empty());
+
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("drift/schema.fingerprint");
+        assert_eq!(Fingerprint::read(&path)?, None);
+        current.write(&path)?;
+        assert_eq!(Fingerprint::read(&path)?, Some(current.clone()));
+        assert!(fs::read_to_string(&path)?.starts_with(&format!("# Schema fingerprint {}", current.digest())));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unruled_pii_columns() -> Result<(), Error> {
+        let previous = fingerprint(&[("customers", &[("id", "serial")])]);
+        let current = fingerprint(&[
+            ("customers", &[("id", "serial"), ("Email", "varchar(100)"), ("DOB", "date"), ("score", "integer")]),
+            ("contacts", &[("id", "serial"), ("home_address", "varchar(200)")]),
+        ]);
+        let changes = compare(&previous, &current);
+
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.masking = serde_yaml::from_str("[{ table: customers, fields: [email] }]")?;
+        let plan = RulePlan::build(&config)?;
+        assert_eq!(unruled_pii(&changes, &current, &config, &plan)?, vec!["customers.DOB", "contacts.home_address"]);
+
+        config.schema_drift.allow = vec!["customers.dob".to_string()];
+        config.excluded_tables.push("contacts".to_string());
+        assert!(unruled_pii(&changes, &current, &config, &plan)?.is_empty());
+
+        // A renamed table's columns are checked again, as rules for the old name no longer apply
+        let renamed = fingerprint(&[("clients", &[("id", "serial"), ("Email", "varchar(100)"), ("DOB", "date"), ("score", "integer")])]);
+        let changes = compare(&current, &renamed);
+        assert_eq!(changes, vec![Change::TableRemoved("contacts".to_string()), Change::TableRenamed("customers".to_string(), "clients".to_string())]);
+        config.schema_drift.allow = vec!["clients.dob".to_string()];
+        assert_eq!(unruled_pii(&changes, &renamed, &config, &plan)?, vec!["clients.Email"]);
+
+        // Names are matched word by word
+        let columns = [
+            "email_address", "mobile_phone", "phone2", "lastname", "birth_date", "home_city", "zip_code", "ssn",
+            "ip_address", "card_number", "api_token", "password_hash",
+            "cancelled", "capacity", "ethnicity", "token_count", "secret_question", "zipper", "emailed_at", "tokens",
+        ];
+        let current = fingerprint(&[("misc", &columns.map(|column| (column, "varchar(10)")))]);
+        let changes = compare(&previous, &current);
+        let unruled = unruled_pii(&changes, &current, &config, &plan)?;
+        assert_eq!(unruled, columns[..12].iter().map(|column| format!("misc.{}", column)).collect::<Vec<_>>());
+
+        config.schema_drift.pii_patterns = vec!["(".to_string()];
+        assert!(matches!(unruled_pii(&changes, &current, &config, &plan), Err(Error::Config(_))));
+        Ok(())
+    }
+}
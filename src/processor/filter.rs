@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::processor::predicate::{DateContext, RowPredicate};
+use crate::processor::sql::TableInfo;
+use crate::processor::unl::{find_field_index, UnlRow};
+
+// Row counts for one processed UNL file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FileStats {
+    pub rows_read: usize,
+    // Removed by one of the table's row filters
+    pub rows_filtered: usize,
+    // Removed because they referenced a removed row of a parent table
+    pub rows_cascaded: usize,
+    // Appended by a synthetic_rows rule
+    pub rows_generated: usize,
+}
+
+impl FileStats {
+    pub fn rows_removed(&self) -> usize {
+        self.rows_filtered + self.rows_cascaded
+    }
+
+    pub fn rows_written(&self) -> usize {
+        self.rows_read - self.rows_removed() + self.rows_generated
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Removal {
+    Filtered { cascade: bool },
+    Cascaded,
+}
+
+impl Removal {
+    // Whether child rows referencing this row must go as well
+    pub fn cascades(&self) -> bool {
+        matches!(self, Removal::Filtered { cascade: true } | Removal::Cascaded)
+    }
+}
+
+// Removed key values per parent table and referenced column list
+type RemovedKeys = HashMap<(String, Vec<String>), HashSet<Vec<String>>>;
+
+// Per-table row filters, plus the removed keys needed to cascade them through foreign keys
+#[derive(Debug, Default)]
+pub struct RowFilters {
+    filters: HashMap<String, Vec<(RowPredicate, bool)>>,
+    // Tables whose removed rows cascade, with the key columns their children reference
+    tracked: HashMap<String, Vec<Vec<String>>>,
+    removed: Mutex<RemovedKeys>,
+}
+
+impl RowFilters {
+    pub fn new(config: &Config, table_info: &HashMap<String, TableInfo>, dates: &DateContext) -> Result<Self, Error> {
+        let mut filters: HashMap<String, Vec<(RowPredicate, bool)>> = HashMap::new();
+        for filter in &config.row_filters {
+            if filter.where_clause.is_empty() {
+                return Err(Error::Config(format!(
+                    "Row filter for table '{}' has no conditions; use excluded_tables to drop a whole table",
+                    filter.table
+                )));
+            }
+            filters.entry(filter.table.clone())
+                .or_default()
+                .push((RowPredicate::compile(&filter.table, &filter.where_clause, table_info, dates)?, filter.cascade));
+        }
+
+        // Follow foreign keys from every cascading filter down through all descendant tables.
+        // Self-references are not followed.
+        let exported = |table: &str| table_info.contains_key(table) && !config.excluded_tables.iter().any(|t| t == table);
+        let mut cascading: HashSet<String> = config.row_filters.iter()
+            .filter(|f| f.cascade && exported(&f.table))
+            .map(|f| f.table.clone())
+            .collect();
+        let mut queue: VecDeque<String> = cascading.iter().cloned().collect();
+        let mut tracked: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+
+        while let Some(parent) = queue.pop_front() {
+            let mut children: Vec<_> = table_info.iter()
+                .filter(|(child, _)| **child != parent && exported(child))
+                .collect();
+            children.sort_by_key(|(child, _)| *child);
+
+            for (child, info) in children {
+                for fk in info.foreign_keys.iter().filter(|fk| fk.parent_table == parent) {
+                    let keys = tracked.entry(parent.clone()).or_default();
+                    if !keys.contains(&fk.parent_columns) {
+                        keys.push(fk.parent_columns.clone());
+                    }
+                    if cascading.insert(child.clone()) {
+                        queue.push_back(child.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(RowFilters { filters, tracked, removed: Mutex::default() })
+    }
+
+    // (child, parent) pairs: the parent must be processed first so its removals are known
+    pub fn dependencies(&self, table_info: &HashMap<String, TableInfo>) -> Vec<(String, String)> {
+        let mut dependencies = Vec::new();
+        for (child, info) in table_info {
+            for fk in &info.foreign_keys {
+                if fk.parent_table != *child && self.tracked.contains_key(&fk.parent_table) {
+                    dependencies.push((child.clone(), fk.parent_table.clone()));
+                }
+            }
+        }
+        dependencies
+    }
+
+    // Forget a table's removed keys, e.g. before a retry processes it again
+    pub fn reset(&self, table_name: &str) -> Result<(), Error> {
+        self.lock()?.retain(|(table, _), _| table != table_name);
+        Ok(())
+    }
+
+    // Whether, and why, a row is removed. Filters test the row as exported.
+    pub fn check(&self, table_name: &str, row: &UnlRow, table_info: &HashMap<String, TableInfo>) -> Result<Option<Removal>, Error> {
+        for (predicate, cascade) in self.filters.get(table_name).map(Vec::as_slice).unwrap_or_default() {
+            if predicate.matches(row)? {
+                return Ok(Some(Removal::Filtered { cascade: *cascade }));
+            }
+        }
+
+        let info = match table_info.get(table_name) {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+        let removed = self.lock()?;
+        for fk in &info.foreign_keys {
+            if fk.parent_table == table_name || !self.tracked.contains_key(&fk.parent_table) {
+                continue;
+            }
+            let values = match key_values(&fk.columns, &info.fields, row) {
+                Some(values) => values,
+                None => continue,
+            };
+            let parent_removed = removed.get(&(fk.parent_table.clone(), fk.parent_columns.clone()));
+            if parent_removed.is_some_and(|keys| keys.contains(&values)) {
+                return Ok(Some(Removal::Cascaded));
+            }
+        }
+        Ok(None)
+    }
+
+    // Remember a removed row's keys for the child tables that reference them
+    pub fn record_removed(&self, table_name: &str, row: &UnlRow, table_info: &HashMap<String, TableInfo>) -> Result<(), Error> {
+        let (keys, info) = match (self.tracked.get(table_name), table_info.get(table_name)) {
+            (Some(keys), Some(info)) => (keys, info),
+            _ => return Ok(()),
+        };
+
+        let mut removed = self.lock()?;
+        for columns in keys {
+            if let Some(values) = key_values(columns, &info.fields, row) {
+                removed.entry((table_name.to_string(), columns.clone())).or_default().insert(values);
+            }
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, RemovedKeys>, Error> {
+        self.removed.lock()
+            .map_err(|e| Error::Processing(format!("Failed to lock removed row keys: {}", e)))
+    }
+}
+
+// The row's values for the columns, or None if any is NULL or missing
+fn key_values(columns: &[String], fields: &[String], row: &UnlRow) -> Option<Vec<String>> {
+    columns.iter()
+        .map(|column| find_field_index(column, fields)
+            .and_then(|idx| row.get_field(idx))
+            .filter(|value| !value.is_empty())
+            .map(String::from))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::sql::ForeignKey;
+
+    fn table_info() -> HashMap<String, TableInfo> {
+        let fk = |column: &str, parent: &str| ForeignKey {
+            columns: vec![column.to_string()],
+            parent_table: parent.to_string(),
+            parent_columns: vec!["id".to_string()],
+        };
+        let table = |fields: &[&str], foreign_keys: Vec<ForeignKey>| TableInfo {
+            fields: fields.iter().map(|f| f.to_string()).collect(),
+            primary_key: vec!["id".to_string()],
+            foreign_keys,
+            ..Default::default()
+        };
+
+        let mut tables = HashMap::new();
+        tables.insert("customers".to_string(), table(&["id", "deleted"], vec![]));
+        tables.insert("projects".to_string(), table(&["id", "customer_id"], vec![fk("customer_id", "customers")]));
+        tables.insert("repositories".to_string(), table(&["id", "project_id"], vec![fk("project_id", "projects")]));
+        tables.insert("employees".to_string(), table(&["id", "manager_id"], vec![fk("manager_id", "employees")]));
+        tables
+    }
+
+    fn build_filters(config_str: &str) -> Result<RowFilters, Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.excluded_tables.clear();
+        config.row_filters = serde_yaml::from_str(config_str)?;
+        RowFilters::new(&config, &table_info(), &DateContext::default())
+    }
+
+    fn row(line: &str) -> UnlRow {
+        UnlRow::from_line(line).unwrap()
+    }
+
+    #[test]
+    fn test_cascade_through_descendants() -> Result<(), Error> {
+        let tables = table_info();
+        let filters = build_filters(r#"
+            - table: customers
+              where: [{ field: deleted, equals: Y }]
+              cascade: true
+        "#)?;
+
+        let mut dependencies = filters.dependencies(&tables);
+        dependencies.sort();
+        assert_eq!(dependencies, vec![
+            ("projects".to_string(), "customers".to_string()),
+            ("repositories".to_string(), "projects".to_string()),
+        ]);
+
+        let deleted = row("1|Y|");
+        let removal = filters.check("customers", &deleted, &tables)?;
+        assert_eq!(removal, Some(Removal::Filtered { cascade: true }));
+        filters.record_removed("customers", &deleted, &tables)?;
+        assert_eq!(filters.check("customers", &row("2|N|"), &tables)?, None);
+
+        let orphan = row("10|1|");
+        assert_eq!(filters.check("projects", &orphan, &tables)?, Some(Removal::Cascaded));
+        filters.record_removed("projects", &orphan, &tables)?;
+        assert_eq!(filters.check("projects", &row("11|2|"), &tables)?, None);
+        assert_eq!(filters.check("projects", &row("12||"), &tables)?, None);
+
+        assert_eq!(filters.check("repositories", &row("100|10|"), &tables)?, Some(Removal::Cascaded));
+        assert_eq!(filters.check("repositories", &row("101|11|"), &tables)?, None);
+
+        filters.reset("projects")?;
+        assert_eq!(filters.check("repositories", &row("100|10|"), &tables)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filters_without_cascade() -> Result<(), Error> {
+        let tables = table_info();
+        let filters = build_filters(r#"
+            - table: customers
+              where: [{ field: deleted, equals: Y }]
+        "#)?;
+        assert!(filters.dependencies(&tables).is_empty());
+        assert_eq!(filters.check("customers", &row("1|Y|"), &tables)?, Some(Removal::Filtered { cascade: false }));
+        assert!(!Removal::Filtered { cascade: false }.cascades());
+
+        assert!(matches!(build_filters("[{ table: customers, where: [] }]"), Err(Error::Config(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::config::{GeneratorConfig, GeneratorKind};
+use crate::error::Error;
+
+// Domains reserved for documentation by RFC 2606, so generated addresses can never reach anyone
+pub const RESERVED_EMAIL_DOMAINS: &[&str] = &["example.com", "example.net", "example.org"];
+const RESERVED_TLDS: &[&str] = &[".example", ".test", ".invalid", ".localhost"];
+
+const AREA_CODES: &[&str] = &[
+    "202", "212", "303", "312", "404", "415", "503", "512", "602", "617",
+    "702", "713", "808", "816", "904", "919",
+];
+
+const STREET_NAMES: &[&str] = &[
+    "Oak", "Maple", "Cedar", "Pine", "Elm", "Willow", "Birch", "Spruce", "Hickory", "Aspen",
+    "Lake", "Hill", "River", "Meadow", "Forest", "Sunset", "Park", "Spring", "Highland", "Valley",
+    "Washington", "Lincoln", "Jefferson", "Madison", "Franklin", "Jackson", "Adams", "Monroe",
+];
+
+const STREET_SUFFIXES: &[&str] = &[
+    "St", "Ave", "Rd", "Blvd", "Ln", "Dr", "Ct", "Way", "Pl", "Ter",
+];
+
+const CITIES: &[&str] = &[
+    "Springfield", "Riverside", "Fairview", "Franklin", "Greenville", "Bristol", "Clinton",
+    "Madison", "Georgetown", "Salem", "Arlington", "Ashland", "Burlington", "Dover", "Milton",
+    "Newport", "Oxford", "Kingston", "Lakewood", "Centerville", "Marion", "Oakland", "Jackson",
+    "Hudson",
+];
+
+const COMPANY_PREFIXES: &[&str] = &[
+    "Blue", "Northern", "Summit", "Silver", "Harbor", "Granite", "Evergreen", "Pioneer",
+    "Keystone", "Redwood", "Atlas", "Beacon", "Cascade", "Liberty", "Prairie", "Horizon",
+];
+
+const COMPANY_CORES: &[&str] = &[
+    "Logistics", "Systems", "Holdings", "Consulting", "Supply", "Foods", "Analytics",
+    "Manufacturing", "Partners", "Services", "Industries", "Software", "Energy", "Media",
+];
+
+const COMPANY_SUFFIXES: &[&str] = &["Inc", "LLC", "Co", "Group", "Corp", "Ltd"];
+
+// True for the RFC 2606 / RFC 6761 domains that are guaranteed never to resolve to a real mailbox
+pub fn is_reserved_domain(domain: &str) -> bool {
+    let domain = domain.to_ascii_lowercase();
+    RESERVED_EMAIL_DOMAINS.iter().any(|d| domain == *d || domain.ends_with(&format!(".{}", d)))
+        || RESERVED_TLDS.iter().any(|tld| domain.ends_with(tld))
+}
+
+// Fail on a configured email domain that could reach a real mailbox, before any file is written
+pub fn check_domains(generators: &[GeneratorConfig]) -> Result<(), Error> {
+    for rule in generators {
+        if let Some(domain) = rule.domain.as_deref().filter(|domain| !is_reserved_domain(domain)) {
+            return Err(Error::Config(format!(
+                "Email domain '{}' for table '{}' is not a reserved domain such as example.com",
+                domain,
+                rule.table
+            )));
+        }
+    }
+    Ok(())
+}
+
+// Generate a value for every kind except email, which needs the row's name fields
+pub fn generate<R: Rng>(kind: GeneratorKind, rng: &mut R) -> String {
+    match kind {
+        GeneratorKind::Email => email(&[], None, rng),
+        GeneratorKind::Phone => phone(rng),
+        GeneratorKind::StreetAddress => street_address(rng),
+        GeneratorKind::City => pick(CITIES, rng).to_string(),
+        GeneratorKind::PostalCode => format!("{:05}", rng.gen_range(10000..99999)),
+        GeneratorKind::Company => company(rng),
+    }
+}
+
+// Number of distinct values a generator can produce
+pub fn space(kind: GeneratorKind) -> u128 {
+    let len = |items: &[&str]| items.len() as u128;
+    match kind {
+        // Depends on the row's names; collisions fall back to a numeric suffix
+        GeneratorKind::Email => u128::MAX,
+        GeneratorKind::Phone => len(AREA_CODES) * 100,
+        GeneratorKind::StreetAddress => 9900 * len(STREET_NAMES) * len(STREET_SUFFIXES),
+        GeneratorKind::City => len(CITIES),
+        GeneratorKind::PostalCode => 89999,
+        GeneratorKind::Company => len(COMPANY_PREFIXES) * len(COMPANY_CORES) * len(COMPANY_SUFFIXES),
+    }
+}
+
+// Build a plausible address from the given name parts, e.g. ["Jane", "Smith"] -> jane.smith42@example.com
+pub fn email<R: Rng>(name_parts: &[&str], domain: Option<&str>, rng: &mut R) -> String {
+    let parts: Vec<String> = name_parts.iter()
+        .map(|part| part.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase())
+        .filter(|part| !part.is_empty())
+        .collect();
+
+    let mut local = if parts.is_empty() {
+        format!("user{}", rng.gen_range(1000..10000))
+    } else {
+        parts.join(".")
+    };
+    if rng.gen_bool(0.5) {
+        local.push_str(&rng.gen_range(1..100).to_string());
+    }
+
+    let domain = domain.unwrap_or_else(|| pick(RESERVED_EMAIL_DOMAINS, rng));
+    format!("{}@{}", local, domain)
+}
+
+// NANP reserves 555-0100 through 555-0199 for fictional use
+pub fn phone<R: Rng>(rng: &mut R) -> String {
+    format!("{}-555-01{:02}", pick(AREA_CODES, rng), rng.gen_range(0..100))
+}
+
+pub fn street_address<R: Rng>(rng: &mut R) -> String {
+    format!(
+        "{} {} {}",
+        rng.gen_range(100..10000),
+        pick(STREET_NAMES, rng),
+        pick(STREET_SUFFIXES, rng)
+    )
+}
+
+pub fn company<R: Rng>(rng: &mut R) -> String {
+    format!(
+        "{} {} {}",
+        pick(COMPANY_PREFIXES, rng),
+        pick(COMPANY_CORES, rng),
+        pick(COMPANY_SUFFIXES, rng)
+    )
+}
+
+fn pick<'a, R: Rng>(items: &[&'a str], rng: &mut R) -> &'a str {
+    items.choose(rng).copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::random::seeded_rng;
+    use regex::Regex;
+
+    #[test]
+    fn test_email_from_name_parts() {
+        let value = email(&["Mary-Jo", "O'Brien"], Some("example.org"), &mut seeded_rng(42, &["email"]));
+        let re = Regex::new(r"^maryjo\.obrien\d{0,2}@example\.org$").unwrap();
+        assert!(re.is_match(&value), "unexpected email {}", value);
+
+        let value = email(&[], None, &mut seeded_rng(42, &["email"]));
+        assert!(value.starts_with("user"));
+        let domain = value.split('@').nth(1).unwrap();
+        assert!(is_reserved_domain(domain));
+    }
+
+    #[test]
+    fn test_phone_is_fictional() {
+        let re = Regex::new(r"^\d{3}-555-01\d{2}$").unwrap();
+        for i in 0..50 {
+            let value = phone(&mut seeded_rng(i, &["phone"]));
+            assert!(re.is_match(&value), "unexpected phone {}", value);
+        }
+    }
+
+    #[test]
+    fn test_generators_are_deterministic() {
+        for kind in [
+            GeneratorKind::Email,
+            GeneratorKind::Phone,
+            GeneratorKind::StreetAddress,
+            GeneratorKind::City,
+            GeneratorKind::PostalCode,
+            GeneratorKind::Company,
+        ] {
+            let a = generate(kind, &mut seeded_rng(7, &["value"]));
+            let b = generate(kind, &mut seeded_rng(7, &["value"]));
+            assert!(!a.is_empty());
+            assert_eq!(a, b);
+        }
+        assert_eq!(generate(GeneratorKind::PostalCode, &mut seeded_rng(1, &[])).len(), 5);
+    }
+
+    #[test]
+    fn test_reserved_domains() {
+        assert!(is_reserved_domain("example.com"));
+        assert!(is_reserved_domain("mail.EXAMPLE.net"));
+        assert!(is_reserved_domain("corp.test"));
+        assert!(!is_reserved_domain("gmail.com"));
+        assert!(!is_reserved_domain("notexample.com"));
+    }
+
+    #[test]
+    fn test_check_domains() {
+        let rules: Vec<GeneratorConfig> = serde_yaml::from_str(r#"
+            - { table: customers, fields: [email], generator: email, domain: mail.example.org }
+            - { table: customers, fields: [phone], generator: phone }
+        "#).unwrap();
+        assert!(check_domains(&rules).is_ok());
+
+        let rules: Vec<GeneratorConfig> = serde_yaml::from_str(r#"
+            - { table: customers, fields: [email], generator: email }
+            - { table: employees, fields: [email], generator: email, domain: gmail.com }
+        "#).unwrap();
+        match check_domains(&rules) {
+            Err(Error::Config(msg)) => assert!(msg.contains("'gmail.com' for table 'employees'"), "{}", msg),
+            other => panic!("Expected Config error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, ErrorKind};
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::processor::plan::RulePlan;
+use crate::processor::sql::TableInfo;
+use crate::processor::unl::UnlReader;
+
+// What an export holds for one table
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TableInventory {
+    pub table: String,
+    pub owner: Option<String>,
+    pub unl_file: String,
+    pub columns: Vec<ColumnInventory>,
+    pub declared_rows: usize,
+    // None when the export has no data file for the table
+    pub actual_rows: Option<usize>,
+    pub file_size: Option<u64>,
+    // Config rules naming the table, e.g. "masking[0]"; empty without a config
+    pub rules: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ColumnInventory {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub data_type: String,
+    pub not_null: bool,
+}
+
+// Every table of the export in name order, with the rules of a config already resolved
+// against it
+pub fn inventory(
+    source_path: &Path,
+    table_info: &HashMap<String, TableInfo>,
+    rules: Option<(&Config, &RulePlan)>,
+) -> Result<Vec<TableInventory>, Error> {
+    let mut tables: Vec<(&String, &TableInfo)> = table_info.iter().collect();
+    tables.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut inventory = Vec::new();
+    for (table, info) in tables {
+        let path = source_path.join(&info.unl_file);
+        let (actual_rows, file_size) = match File::open(&path) {
+            Ok(file) => (Some(count_records(file)?), Some(fs::metadata(&path)?.len())),
+            Err(e) if e.kind() == ErrorKind::NotFound => (None, None),
+            Err(e) => return Err(e.into()),
+        };
+        let columns = info.columns.iter()
+            .map(|c| ColumnInventory { name: c.name.clone(), data_type: c.data_type.clone(), not_null: c.not_null })
+            .collect();
+        inventory.push(TableInventory {
+            table: table.clone(),
+            owner: info.owner.clone(),
+            unl_file: info.unl_file.clone(),
+            columns,
+            declared_rows: info.declared_rows,
+            actual_rows,
+            file_size,
+            rules: rules.map(|(config, plan)| rules_for(config, plan, table)).unwrap_or_default(),
+        });
+    }
+    Ok(inventory)
+}
+
+// Records rather than lines, as escaped newlines continue a record
+fn count_records(file: File) -> Result<usize, Error> {
+    let mut records = 0;
+    for row in UnlReader::new(BufReader::new(file)) {
+        row?;
+        records += 1;
+    }
+    Ok(records)
+}
+
+// Rules in rule order, then the sections that keep, drop or add rows and columns
+fn rules_for(config: &Config, plan: &RulePlan, table: &str) -> Vec<String> {
+    let mut rules: Vec<String> = plan.steps(table).iter().map(|step| step.rule.describe()).collect();
+    let mut section = |name: &str, tables: Vec<&String>| {
+        for (i, _) in tables.iter().enumerate().filter(|(_, t)| t.as_str() == table) {
+            rules.push(format!("{}[{}]", name, i));
+        }
+    };
+    section("row_filters", config.row_filters.iter().map(|r| &r.table).collect());
+    section("key_remapping.tables", config.key_remapping.tables.iter().map(|r| &r.table).collect());
+    section("drop_columns", config.drop_columns.iter().map(|r| &r.table).collect());
+    section("large_objects", config.large_objects.iter().map(|r| &r.table).collect());
+    section("synthetic_rows", config.synthetic_rows.iter().map(|r| &r.table).collect());
+    if config.excluded_tables.iter().any(|t| t == table) {
+        rules.push("excluded_tables".to_string());
+    }
+    rules
+}
+
+// One line per table, then each table's columns
+pub fn describe(inventory: &[TableInventory]) -> String {
+    let header = ["TABLE", "FILE", "COLUMNS", "DECLARED", "ACTUAL", "SIZE", "RULES"].map(String::from);
+    let mut rows = vec![header.to_vec()];
+    for table in inventory {
+        let missing = || "-".to_string();
+        // Flag files whose record count disagrees with the unload header
+        let actual = match table.actual_rows {
+            Some(rows) if rows != table.declared_rows => format!("{}!", rows),
+            Some(rows) => rows.to_string(),
+            None => missing(),
+        };
+        rows.push(vec![
+            table.table.clone(),
+            table.unl_file.clone(),
+            table.columns.len().to_string(),
+            table.declared_rows.to_string(),
+            actual,
+            table.file_size.map(|size| size.to_string()).unwrap_or_else(missing),
+            if table.rules.is_empty() { missing() } else { table.rules.join(", ") },
+        ]);
+    }
+
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|i| rows.iter().map(|row| row[i].chars().count()).max().unwrap_or_default())
+        .collect();
+    let mut out = String::new();
+    for row in &rows {
+        let cells: Vec<String> = row.iter().zip(&widths).map(|(cell, width)| format!("{:width$}", cell, width = width)).collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    for table in inventory {
+        let columns: Vec<String> = table.columns.iter()
+            .map(|c| format!("{} {}{}", c.name, c.data_type, if c.not_null { " not null" } else { "" }))
+            .collect();
+        out.push_str(&format!("\n{}:\n  {}\n", table.table, columns.join("\n  ")));
+    }
+    out
+}
+
+pub fn to_json(inventory: &[TableInventory]) -> Result<String, Error> {
+    serde_json::to_string_pretty(inventory)
+        .map(|json| json + "\n")
+        .map_err(|e| Error::Processing(format!("Failed to write inventory as JSON: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::sql;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_inventory() -> Result<(), Error> {
+        let temp_dir = TempDir::new()?;
+        let sql = r#"
+            { TABLE "informix".customers row size = 100 number of columns = 2 index size = 0 }
+            { unload file name = custo00100.unl number of rows = 3 }
+            create table "informix".customers (id serial not null, email varchar(100));
+            { TABLE "informix".states row size = 10 number of columns = 1 index size = 0 }
+            { unload file name = state00101.unl number of rows = 1 }
+            create table "informix".states (code char(2));
+        "#;
+        fs::write(temp_dir.path().join("custo00100.unl"), "1|a@example.com|\n2|b@example.com|\n")?;
+        let table_info = sql::parse_sql_file(sql)?;
+
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.masking = serde_yaml::from_str("[{ table: customers, fields: [email] }]")?;
+        config.excluded_tables = vec!["states".to_string()];
+        let plan = RulePlan::build(&config)?;
+        let inventory = inventory(temp_dir.path(), &table_info, Some((&config, &plan)))?;
+
+        assert_eq!(inventory[0].table, "customers");
+        assert_eq!((inventory[0].declared_rows, inventory[0].actual_rows, inventory[0].file_size), (3, Some(2), Some(34)));
+        assert!(inventory[0].rules.contains(&"masking[0]".to_string()));
+        assert_eq!((inventory[1].actual_rows, inventory[1].file_size), (None, None));
+        assert_eq!(inventory[1].rules, vec!["excluded_tables"]);
+
+        let table = describe(&inventory);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].starts_with("TABLE      FILE            COLUMNS  DECLARED  ACTUAL  SIZE  RULES"), "{}", table);
+        assert!(lines[1].starts_with("customers  custo00100.unl  2        3         2!      34    "), "{}", table);
+        assert_eq!(lines[2], "states     state00101.unl  1        1         -       -     excluded_tables");
+        assert!(table.ends_with("\nstates:\n  code char(2)\n"));
+        assert!(table.contains("\ncustomers:\n  id serial not null\n  email varchar(100)\n"));
+
+        let json = to_json(&inventory)?;
+        assert!(json.contains("\"table\": \"customers\""));
+        assert!(json.contains("\"type\": \"varchar(100)\""));
+        assert!(json.contains("\"actual_rows\": null"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_inspect_export_directory() -> Result<(), Error> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path().join("stores.exp");
+        fs::create_dir(&dir)?;
+        let err = crate::processor::inspect(&dir, None, false).unwrap_err().to_string();
+        assert!(err.contains("stores.sql"), "{}", err);
+
+        fs::write(dir.join("stores.sql"), r#"
+            { TABLE "informix".notes row size = 100 number of columns = 2 index size = 0 }
+            { unload file name = notes00100.unl number of rows = 2 }
+            create table "informix".notes (id integer, body varchar(100));
+        "#)?;
+        // Three lines, but the escaped newline continues the first record
+        fs::write(dir.join("notes00100.unl"), "1|first\\\nline|\n2|a \\| b|\n")?;
+        let table = crate::processor::inspect(&dir, None, false)?;
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[1].starts_with("notes  notes00100.unl  2        2         2       "), "{}", table);
+        Ok(())
+    }
+}
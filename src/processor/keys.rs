@@ -0,0 +1,368 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use rand::seq::SliceRandom;
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::processor::random::seeded_rng;
+use crate::processor::sql::TableInfo;
+use crate::processor::unl::{find_field_index, UnlRow};
+
+// Old to new key values per remapped table
+type KeyMappings = HashMap<String, HashMap<String, String>>;
+
+// Renumbers the primary keys of remapped tables and rewrites the foreign keys that point
+// at them. Each table's new keys are assigned before its rows are written, so children
+// (processed after their parents) and self-references can both be rewritten.
+#[derive(Debug, Default)]
+pub struct KeyRemapper {
+    // Remapped table -> (key column, first new key)
+    keys: HashMap<String, (String, i64)>,
+    // Table -> (column, parent table) for foreign keys into remapped tables
+    references: HashMap<String, Vec<(String, String)>>,
+    mappings: RwLock<KeyMappings>,
+}
+
+impl KeyRemapper {
+    pub fn new(config: &Config, table_info: &HashMap<String, TableInfo>) -> Result<Self, Error> {
+        let remapping = &config.key_remapping;
+        if !remapping.tables.is_empty() && remapping.mapping_file.is_none() {
+            return Err(Error::Config(
+                "key_remapping needs a mapping_file so support staff can trace new keys back".to_string()
+            ));
+        }
+
+        let excluded = |table: &str| config.excluded_tables.iter().any(|t| t == table);
+        let mut keys = HashMap::new();
+        for rule in &remapping.tables {
+            let info = match table_info.get(&rule.table) {
+                Some(info) if !excluded(&rule.table) => info,
+                _ => return Err(Error::Config(format!(
+                    "key_remapping table '{}' is excluded or not in the export",
+                    rule.table
+                ))),
+            };
+            let column = match info.primary_key.as_slice() {
+                [column] => column.clone(),
+                _ => return Err(Error::Config(format!(
+                    "key_remapping table '{}' needs a single-column primary key",
+                    rule.table
+                ))),
+            };
+            if let Some(info) = info.columns.iter().find(|c| c.name == column) {
+                if !is_integer_type(&info.base_type()) {
+                    return Err(Error::Config(format!(
+                        "key_remapping needs an integer key, but {}.{} is {}",
+                        rule.table,
+                        column,
+                        info.data_type
+                    )));
+                }
+            }
+            if keys.insert(rule.table.clone(), (column, rule.start)).is_some() {
+                return Err(Error::Config(format!("key_remapping lists table '{}' twice", rule.table)));
+            }
+        }
+
+        let mut references: HashMap<String, Vec<(String, String)>> = HashMap::new();
+        for (table, info) in table_info.iter().filter(|(table, _)| !excluded(table)) {
+            for fk in &info.foreign_keys {
+                let parent_key = match keys.get(&fk.parent_table) {
+                    Some((column, _)) => column,
+                    None => continue,
+                };
+                // Only references to the renumbered key change; other unique keys keep their values
+                let column = match (fk.columns.as_slice(), fk.parent_columns.as_slice()) {
+                    ([column], [parent_column]) if parent_column == parent_key => column,
+                    _ => continue,
+                };
+                if keys.get(table).is_some_and(|(key, _)| key == column) {
+                    return Err(Error::Config(format!(
+                        "{}.{} is both a remapped key and a reference to remapped table '{}'",
+                        table,
+                        column,
+                        fk.parent_table
+                    )));
+                }
+                references.entry(table.clone()).or_default().push((column.clone(), fk.parent_table.clone()));
+            }
+        }
+
+        Ok(KeyRemapper { keys, references, mappings: RwLock::default() })
+    }
+
+    // (child, parent) pairs: the parent's new keys must exist before its children are written
+    pub fn dependencies(&self) -> Vec<(String, String)> {
+        let mut dependencies = Vec::new();
+        for (child, references) in &self.references {
+            for (_, parent) in references {
+                if parent != child {
+                    dependencies.push((child.clone(), parent.clone()));
+                }
+            }
+        }
+        dependencies
+    }
+
+    pub fn key_column(&self, table_name: &str) -> Option<&str> {
+        self.keys.get(table_name).map(|(column, _)| column.as_str())
+    }
+
+    // Whether the table's key or any of its references change
+    pub fn touches(&self, table_name: &str) -> bool {
+        self.keys.contains_key(table_name) || self.references.contains_key(table_name)
+    }
+
+    // Forget a table's new keys, e.g. before a retry processes it again
+    pub fn reset(&self, table_name: &str) -> Result<(), Error> {
+        self.write()?.remove(table_name);
+        Ok(())
+    }
+
+    // Assign new keys to the exported keys of a table's written rows, in a seeded shuffled
+    // order so the new keys don't give away the old ones' order
+    pub fn prepare(&self, table_name: &str, exported: Vec<String>, seed: u64) -> Result<(), Error> {
+        let (column, start) = match self.keys.get(table_name) {
+            Some(key) => key,
+            None => return Ok(()),
+        };
+
+        let mut seen = HashSet::new();
+        for key in &exported {
+            if key.trim().parse::<i64>().is_err() {
+                return Err(Error::Processing(format!(
+                    "Cannot remap {}.{}: '{}' is not an integer",
+                    table_name,
+                    column,
+                    key
+                )));
+            }
+            if !seen.insert(key.as_str()) {
+                return Err(Error::Processing(format!(
+                    "Cannot remap {}.{}: key '{}' appears more than once",
+                    table_name,
+                    column,
+                    key
+                )));
+            }
+        }
+
+        let mut shuffled = exported;
+        shuffled.sort();
+        shuffled.shuffle(&mut seeded_rng(seed, &["keys", table_name]));
+
+        let mut mapping = HashMap::new();
+        for (i, key) in shuffled.into_iter().enumerate() {
+            let new_key = start.checked_add(i as i64).ok_or_else(|| Error::Processing(format!(
+                "New keys for {}.{} overflow from start {}",
+                table_name,
+                column,
+                start
+            )))?;
+            mapping.insert(key, new_key.to_string());
+        }
+        self.write()?.insert(table_name.to_string(), mapping);
+        Ok(())
+    }
+
+    // Rewrite the row's key and references from the values in its exported row
+    pub fn remap(&self, table_name: &str, original: &UnlRow, row: &mut UnlRow, fields: &[String]) -> Result<(), Error> {
+        let key = self.keys.get(table_name).map(|(column, _)| (column, table_name));
+        let references = self.references.get(table_name).into_iter().flatten()
+            .map(|(column, parent)| (column, parent.as_str()));
+        if !self.touches(table_name) {
+            return Ok(());
+        }
+
+        let mappings = self.read()?;
+        for (column, parent) in key.into_iter().chain(references) {
+            let idx = match find_field_index(column, fields) {
+                Some(idx) => idx,
+                None => continue,
+            };
+            let value = original.get_field(idx).unwrap_or_default();
+            if value.is_empty() {
+                continue;
+            }
+            let new_key = mappings.get(parent)
+                .and_then(|mapping| mapping.get(value.trim()))
+                .ok_or_else(|| Error::Processing(format!(
+                    "{}.{} references {} key '{}', which is not in the export; \
+                     remove the row or cascade the parent's row filter",
+                    table_name,
+                    column,
+                    parent,
+                    value
+                )))?;
+            row.set_field(idx, new_key.clone())?;
+        }
+        Ok(())
+    }
+
+    // Every table's mapping as (table, column, old key, new key), ordered by table and new key
+    pub fn mappings(&self) -> Result<Vec<(String, String, String, String)>, Error> {
+        let mut rows = Vec::new();
+        for (table, mapping) in self.read()?.iter() {
+            let column = self.key_column(table).unwrap_or_default();
+            for (old, new) in mapping {
+                rows.push((table.clone(), column.to_string(), old.clone(), new.clone()));
+            }
+        }
+        rows.sort_by(|a, b| (&a.0, a.3.parse::<i64>().ok()).cmp(&(&b.0, b.3.parse::<i64>().ok())));
+        Ok(rows)
+    }
+
+    // The mapping as CSV, readable by its owner only where the platform supports it
+    pub fn write_mapping_file(&self, path: &Path) -> Result<usize, Error> {
+        let rows = self.mappings()?;
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut writer = BufWriter::new(create_private(path)?);
+        writeln!(writer, "table,column,old_key,new_key")?;
+        for (table, column, old, new) in &rows {
+            writeln!(writer, "{},{},{},{}", table, column, old, new)?;
+        }
+        writer.flush()?;
+        Ok(rows.len())
+    }
+
+    fn read(&self) -> Result<RwLockReadGuard<'_, KeyMappings>, Error> {
+        self.mappings.read()
+            .map_err(|e| Error::Processing(format!("Failed to lock key mappings: {}", e)))
+    }
+
+    fn write(&self) -> Result<RwLockWriteGuard<'_, KeyMappings>, Error> {
+        self.mappings.write()
+            .map_err(|e| Error::Processing(format!("Failed to lock key mappings: {}", e)))
+    }
+}
+
+fn is_integer_type(base_type: &str) -> bool {
+    matches!(base_type, "smallint" | "integer" | "int" | "serial" | "int8" | "bigint" | "serial8" | "bigserial")
+}
+
+#[cfg(unix)]
+fn create_private(path: &Path) -> Result<File, Error> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    let file = OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)?;
+    // An existing file keeps its mode when truncated
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+fn create_private(path: &Path) -> Result<File, Error> {
+    Ok(OpenOptions::new().write(true).create(true).truncate(true).open(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::sql::{ColumnInfo, ForeignKey};
+
+    fn table_info() -> HashMap<String, TableInfo> {
+        let fk = |column: &str, parent: &str| ForeignKey {
+            columns: vec![column.to_string()],
+            parent_table: parent.to_string(),
+            parent_columns: vec!["id".to_string()],
+        };
+        let table = |fields: &[&str], foreign_keys: Vec<ForeignKey>| TableInfo {
+            fields: fields.iter().map(|f| f.to_string()).collect(),
+            columns: vec![ColumnInfo { name: "id".to_string(), data_type: "serial".to_string(), ..Default::default() }],
+            primary_key: vec!["id".to_string()],
+            foreign_keys,
+            ..Default::default()
+        };
+
+        let mut tables = HashMap::new();
+        tables.insert("customers".to_string(), table(&["id", "name"], vec![]));
+        tables.insert("projects".to_string(), table(&["id", "customer_id"], vec![fk("customer_id", "customers")]));
+        tables.insert("employees".to_string(), table(&["id", "manager_id"], vec![fk("manager_id", "employees")]));
+        tables
+    }
+
+    fn build(remapping: &str) -> Result<KeyRemapper, Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.excluded_tables.clear();
+        config.key_remapping = serde_yaml::from_str(remapping)?;
+        KeyRemapper::new(&config, &table_info())
+    }
+
+    fn row(line: &str) -> UnlRow {
+        UnlRow::from_line(line).unwrap()
+    }
+
+    fn fields(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_keys_follow_references() -> Result<(), Error> {
+        let remapper = build("{ mapping_file: map.csv, tables: [{ table: customers, start: 100 }, { table: employees }] }")?;
+        assert_eq!(remapper.dependencies(), vec![("projects".to_string(), "customers".to_string())]);
+
+        remapper.prepare("customers", vec!["7".to_string(), "3".to_string(), "5".to_string()], 42)?;
+        let customer = row("7|Ann|");
+        let mut remapped = customer.clone();
+        remapper.remap("customers", &customer, &mut remapped, &fields(&["id", "name"]))?;
+        let new_key = remapped.fields[0].clone();
+        assert!(["100", "101", "102"].contains(&new_key.as_str()));
+
+        let project = row("1|7|");
+        let mut remapped = project.clone();
+        remapper.remap("projects", &project, &mut remapped, &fields(&["id", "customer_id"]))?;
+        assert_eq!(remapped.fields, vec!["1".to_string(), new_key]);
+
+        let orphan = row("2|9|");
+        let mut remapped = orphan.clone();
+        assert!(matches!(
+            remapper.remap("projects", &orphan, &mut remapped, &fields(&["id", "customer_id"])),
+            Err(Error::Processing(_))
+        ));
+
+        // Self-references use the table's own new keys; NULLs stay NULL
+        remapper.prepare("employees", vec!["1".to_string(), "2".to_string()], 42)?;
+        let employee = row("2|1|");
+        let mut remapped = employee.clone();
+        remapper.remap("employees", &employee, &mut remapped, &fields(&["id", "manager_id"]))?;
+        assert_ne!(remapped.fields[0], remapped.fields[1]);
+        let boss = row("1||");
+        let mut remapped = boss.clone();
+        remapper.remap("employees", &boss, &mut remapped, &fields(&["id", "manager_id"]))?;
+        assert_eq!(remapped.fields[1], "");
+
+        assert_eq!(remapper.mappings()?.len(), 5);
+        remapper.reset("employees")?;
+        assert_eq!(remapper.mappings()?.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_prepare_is_seeded() -> Result<(), Error> {
+        let keys = || (1..=20).map(|k| k.to_string()).collect::<Vec<_>>();
+        let first = build("{ mapping_file: map.csv, tables: [{ table: customers }] }")?;
+        let second = build("{ mapping_file: map.csv, tables: [{ table: customers }] }")?;
+        first.prepare("customers", keys(), 42)?;
+        second.prepare("customers", keys().into_iter().rev().collect(), 42)?;
+        assert_eq!(first.mappings()?, second.mappings()?);
+
+        assert!(matches!(first.prepare("customers", vec!["1".to_string(), "1".to_string()], 42), Err(Error::Processing(_))));
+        assert!(matches!(first.prepare("customers", vec!["A1".to_string()], 42), Err(Error::Processing(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_remapping_errors() {
+        assert!(matches!(build("{ tables: [{ table: customers }] }"), Err(Error::Config(_))));
+        assert!(matches!(build("{ mapping_file: m.csv, tables: [{ table: missing }] }"), Err(Error::Config(_))));
+        assert!(matches!(
+            build("{ mapping_file: m.csv, tables: [{ table: customers }, { table: customers }] }"),
+            Err(Error::Config(_))
+        ));
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::config::{Config, LargeObjectAction};
+use crate::error::Error;
+use crate::processor::plan::{RulePlan, RuleRef};
+use crate::processor::sql::{ColumnInfo, TableInfo};
+use crate::processor::unl::{find_field_index, UnlRow};
+
+pub fn is_large_object(column: &ColumnInfo) -> bool {
+    matches!(column.base_type().as_str(), "blob" | "clob" | "text" | "byte")
+}
+
+// A large-object value stored outside the UNL file, unloaded as
+// "start offset,length,file" with the offset and length in hex
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LobReference {
+    pub offset: u64,
+    pub length: u64,
+    pub path: String,
+}
+
+impl LobReference {
+    // None for NULLs and values unloaded inline
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.splitn(3, ',');
+        let offset = u64::from_str_radix(parts.next()?, 16).ok()?;
+        let length = u64::from_str_radix(parts.next()?, 16).ok()?;
+        let path = parts.next().filter(|path| !path.trim().is_empty())?;
+        Some(LobReference { offset, length, path: path.to_string() })
+    }
+}
+
+// Files referenced by a table's rows, by whether the reference was written
+#[derive(Debug, Default)]
+struct TableFiles {
+    kept: HashSet<String>,
+    dropped: HashSet<String>,
+}
+
+// Large-object columns and the external files their references point at. A file can hold
+// the values of many rows, so it is left out of the export only when no written row
+// references it.
+#[derive(Debug, Default)]
+pub struct LargeObjects {
+    // Per table: large-object column indices and whether their files ship
+    columns: HashMap<String, Vec<(usize, bool)>>,
+    files: Mutex<HashMap<String, TableFiles>>,
+}
+
+impl LargeObjects {
+    pub fn new(config: &Config, table_info: &HashMap<String, TableInfo>, plan: &RulePlan) -> Result<Self, Error> {
+        let mut columns: HashMap<String, Vec<(usize, bool)>> = HashMap::new();
+        for (table, info) in table_info {
+            let excluded = config.excluded_tables.contains(table);
+            for (idx, _) in info.columns.iter().enumerate().filter(|(_, column)| is_large_object(column)) {
+                columns.entry(table.clone()).or_default().push((idx, !excluded));
+            }
+
+            // Only null_fields may write a reference; anything else would leave it pointing nowhere
+            for step in plan.steps(table) {
+                if matches!(step.rule, RuleRef::NullFields(_)) {
+                    continue;
+                }
+                if let Some(column) = step.writes.iter().find(|field| large_object_column(info, field).is_some()) {
+                    return Err(Error::Config(format!(
+                        "{} would overwrite {}.{}, a large-object reference; use large_objects or null_fields instead",
+                        step.rule.describe(),
+                        table,
+                        column
+                    )));
+                }
+            }
+        }
+
+        for rule in &config.large_objects {
+            let info = match table_info.get(&rule.table) {
+                Some(info) => info,
+                None => return Err(Error::Config(format!("large_objects table '{}' is not in the export", rule.table))),
+            };
+            for field in &rule.fields {
+                let (idx, column) = large_object_column(info, field).ok_or_else(|| Error::Config(format!(
+                    "large_objects field {}.{} is not a BLOB, CLOB, TEXT or BYTE column",
+                    rule.table,
+                    field
+                )))?;
+                if rule.action == LargeObjectAction::Drop && column.not_null {
+                    return Err(Error::Config(format!(
+                        "large_objects cannot drop {}.{}: the column is declared not null",
+                        rule.table,
+                        field
+                    )));
+                }
+                if let Some(entry) = columns.get_mut(&rule.table).and_then(|c| c.iter_mut().find(|(i, _)| *i == idx)) {
+                    entry.1 &= rule.action == LargeObjectAction::Copy;
+                }
+            }
+        }
+
+        Ok(LargeObjects { columns, files: Mutex::default() })
+    }
+
+    pub fn has_columns(&self, table_name: &str) -> bool {
+        self.columns.contains_key(table_name)
+    }
+
+    // Forget a table's references, e.g. before a retry processes it again
+    pub fn reset(&self, table_name: &str) -> Result<(), Error> {
+        self.lock()?.remove(table_name);
+        Ok(())
+    }
+
+    // Note the files an exported row references. `written` is the row about to be written,
+    // None when the row is removed; dropped references in it become NULL. `shipped` tells
+    // whether a column survives drop_columns.
+    pub fn observe(&self, table_name: &str, exported: &UnlRow, written: Option<&mut UnlRow>, shipped: impl Fn(usize) -> bool) -> Result<(), Error> {
+        let columns = match self.columns.get(table_name) {
+            Some(columns) => columns,
+            None => return Ok(()),
+        };
+
+        let mut written = written;
+        let mut files = self.lock()?;
+        let files = files.entry(table_name.to_string()).or_default();
+        for &(idx, keep) in columns {
+            let value = exported.get_field(idx).unwrap_or_default();
+            // Dropped values become NULL whether they were unloaded inline or to a file
+            let kept = match written.as_deref_mut() {
+                Some(row) if !keep => {
+                    row.set_field(idx, String::new())?;
+                    false
+                }
+                Some(row) => shipped(idx) && row.get_field(idx) == Some(value),
+                None => false,
+            };
+            let reference = match LobReference::parse(value) {
+                Some(reference) => reference,
+                None => continue,
+            };
+            check_path(table_name, &reference.path)?;
+            if kept {
+                files.kept.insert(reference.path);
+            } else {
+                files.dropped.insert(reference.path);
+            }
+        }
+        Ok(())
+    }
+
+    // Check every kept file made it into the target, and remove the files only dropped
+    // references pointed at. Returns the number of files removed.
+    pub fn finish(&self, target: &Path) -> Result<usize, Error> {
+        let files = self.lock()?;
+        let kept: HashSet<&String> = files.values().flat_map(|f| f.kept.iter()).collect();
+
+        let mut missing: Vec<&&String> = kept.iter().filter(|path| !target.join(path).is_file()).collect();
+        if !missing.is_empty() {
+            missing.sort();
+            return Err(Error::Processing(format!(
+                "Large-object files referenced by the export are missing: {}",
+                missing.iter().map(|p| p.as_str()).collect::<Vec<_>>().join(", ")
+            )));
+        }
+
+        let mut dropped: Vec<&String> = files.values()
+            .flat_map(|f| f.dropped.iter())
+            .filter(|path| !kept.contains(path))
+            .collect();
+        dropped.sort();
+        dropped.dedup();
+
+        let mut removed = 0;
+        for path in dropped {
+            let path = target.join(path);
+            if path.is_file() {
+                fs::remove_file(path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, HashMap<String, TableFiles>>, Error> {
+        self.files.lock()
+            .map_err(|e| Error::Processing(format!("Failed to lock large-object files: {}", e)))
+    }
+}
+
+fn large_object_column<'a>(info: &'a TableInfo, field: &str) -> Option<(usize, &'a ColumnInfo)> {
+    let idx = find_field_index(field, &info.fields)?;
+    info.columns.get(idx).filter(|column| is_large_object(column)).map(|column| (idx, column))
+}
+
+// References must stay inside the export directory so the copied files still match them
+fn check_path(table_name: &str, path: &str) -> Result<(), Error> {
+    let inside = Path::new(path).components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if inside {
+        return Ok(());
+    }
+    Err(Error::Processing(format!(
+        "Table '{}' references large-object file '{}' outside the export directory",
+        table_name,
+        path
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn column(name: &str, data_type: &str, not_null: bool) -> ColumnInfo {
+        ColumnInfo { name: name.to_string(), data_type: data_type.to_string(), not_null, default: None }
+    }
+
+    fn table_info() -> HashMap<String, TableInfo> {
+        let columns = vec![column("id", "serial", true), column("scan", "blob", false), column("notes", "text", false)];
+        let mut tables = HashMap::new();
+        tables.insert("documents".to_string(), TableInfo {
+            fields: columns.iter().map(|c| c.name.clone()).collect(),
+            columns,
+            ..Default::default()
+        });
+        tables
+    }
+
+    fn build(large_objects: &str, masking: &str) -> Result<LargeObjects, Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.excluded_tables.clear();
+        config.large_objects = serde_yaml::from_str(large_objects)?;
+        config.masking = serde_yaml::from_str(masking)?;
+        LargeObjects::new(&config, &table_info(), &RulePlan::build(&config)?)
+    }
+
+    fn row(line: &str) -> UnlRow {
+        UnlRow::from_line(line).unwrap()
+    }
+
+    #[test]
+    fn test_parse_reference() {
+        assert_eq!(LobReference::parse("0,1a4,blob/doc.001"), Some(LobReference {
+            offset: 0,
+            length: 0x1a4,
+            path: "blob/doc.001".to_string(),
+        }));
+        assert_eq!(LobReference::parse(""), None);
+        assert_eq!(LobReference::parse("plain text, inline"), None);
+        assert_eq!(LobReference::parse("0,10,"), None);
+    }
+
+    #[test]
+    fn test_dropped_files_leave_export() -> Result<(), Error> {
+        let target = TempDir::new()?;
+        fs::create_dir(target.path().join("blob"))?;
+        for file in ["scans.001", "notes.001", "removed.001"] {
+            fs::write(target.path().join("blob").join(file), "data")?;
+        }
+
+        let objects = build("[{ table: documents, fields: [scan], action: drop }]", "[]")?;
+        let exported = row("1|0,4,blob/scans.001|0,4,blob/notes.001|");
+        let mut written = exported.clone();
+        objects.observe("documents", &exported, Some(&mut written), |_| true)?;
+        assert_eq!(written.fields, vec!["1", "", "0,4,blob/notes.001"]);
+        objects.observe("documents", &row("2||0,4,blob/removed.001|"), None, |_| true)?;
+
+        assert_eq!(objects.finish(target.path())?, 2);
+        assert!(target.path().join("blob/notes.001").exists());
+        assert!(!target.path().join("blob/scans.001").exists());
+
+        fs::remove_file(target.path().join("blob/notes.001"))?;
+        assert!(matches!(objects.finish(target.path()), Err(Error::Processing(_))));
+
+        let outside = row("3|0,4,../secret|");
+        assert!(objects.observe("documents", &outside, None, |_| true).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rules_cannot_overwrite_references() {
+        assert!(matches!(build("[]", "[{ table: documents, fields: [scan] }]"), Err(Error::Config(_))));
+        assert!(matches!(build("[{ table: documents, fields: [id], action: drop }]", "[]"), Err(Error::Config(_))));
+    }
+}
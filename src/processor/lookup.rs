@@ -0,0 +1,251 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::config::{Config, LookupConfig, SourceStage};
+use crate::error::Error;
+
+// One parent column that some child table reads through a join
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct LookupKey {
+    pub table: String,
+    pub key: String,
+    pub field: String,
+    pub stage: SourceStage,
+}
+
+impl LookupKey {
+    pub fn new(lookup: &LookupConfig, field: &str, stage: SourceStage) -> Self {
+        LookupKey {
+            table: lookup.table.clone(),
+            key: lookup.key.clone(),
+            field: field.to_string(),
+            stage,
+        }
+    }
+}
+
+type LookupValues = HashMap<LookupKey, HashMap<String, String>>;
+
+// Values captured from parent tables while they are processed, keyed by the parent's
+// original key value so children can join on their own (original) join column
+#[derive(Debug, Default)]
+pub struct LookupIndex {
+    keys: Vec<LookupKey>,
+    values: RwLock<LookupValues>,
+}
+
+impl LookupIndex {
+    pub fn new(config: &Config) -> Self {
+        let mut keys = Vec::new();
+        for combo in &config.combination_fields {
+            for source in &combo.fields {
+                if let (Some(lookup), Some(field)) = (&source.lookup, &source.source_field) {
+                    let key = LookupKey::new(lookup, field, source.stage);
+                    if !keys.contains(&key) {
+                        keys.push(key);
+                    }
+                }
+            }
+        }
+        LookupIndex { keys, values: RwLock::default() }
+    }
+
+    // The parent columns to capture while processing this table
+    pub fn keys_for(&self, table_name: &str) -> Vec<&LookupKey> {
+        self.keys.iter().filter(|k| k.table == table_name).collect()
+    }
+
+    // Drop a parent table's values, e.g. before a retry processes it again
+    pub fn reset(&self, table_name: &str) -> Result<(), Error> {
+        self.write()?.retain(|key, _| key.table != table_name);
+        Ok(())
+    }
+
+    pub fn record(&self, key: &LookupKey, join_value: String, value: String) -> Result<(), Error> {
+        self.write()?.entry(key.clone()).or_default().insert(join_value, value);
+        Ok(())
+    }
+
+    pub fn get(&self, key: &LookupKey, join_value: &str) -> Result<Option<String>, Error> {
+        Ok(self.read()?.get(key).and_then(|values| values.get(join_value)).cloned())
+    }
+
+    fn read(&self) -> Result<RwLockReadGuard<'_, LookupValues>, Error> {
+        self.values.read()
+            .map_err(|e| Error::Processing(format!("Failed to lock lookup index: {}", e)))
+    }
+
+    fn write(&self) -> Result<RwLockWriteGuard<'_, LookupValues>, Error> {
+        self.values.write()
+            .map_err(|e| Error::Processing(format!("Failed to lock lookup index: {}", e)))
+    }
+}
+
+// Group tables into waves that can each run in parallel, with every lookup parent in an
+// earlier wave than the tables reading from it. `dependencies` adds other (child, parent)
+// orderings, such as row filters cascading through foreign keys.
+pub fn processing_waves(
+    tables: &[String],
+    config: &Config,
+    dependencies: &[(String, String)],
+) -> Result<Vec<Vec<String>>, Error> {
+    let table_set: HashSet<&str> = tables.iter().map(String::as_str).collect();
+    let mut parents: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+
+    for combo in &config.combination_fields {
+        if !table_set.contains(combo.table.as_str()) {
+            continue;
+        }
+        for lookup in combo.fields.iter().filter_map(|f| f.lookup.as_ref()) {
+            // A table's values are indexed as it is processed, so they aren't complete until it is done
+            if lookup.table == combo.table {
+                return Err(Error::Config(format!(
+                    "Table '{}' looks up values in its own rows (join_field '{}'); lookups must read another table",
+                    combo.table,
+                    lookup.join_field
+                )));
+            }
+            if !table_set.contains(lookup.table.as_str()) {
+                return Err(Error::Config(format!(
+                    "Table '{}' looks up values in '{}', which is excluded or not in the export",
+                    combo.table,
+                    lookup.table
+                )));
+            }
+            parents.entry(combo.table.as_str()).or_default().insert(lookup.table.as_str());
+        }
+    }
+    for (child, parent) in dependencies {
+        if table_set.contains(child.as_str()) && table_set.contains(parent.as_str()) {
+            parents.entry(child.as_str()).or_default().insert(parent.as_str());
+        }
+    }
+
+    let mut done: HashSet<&str> = HashSet::new();
+    let mut remaining: Vec<&str> = tables.iter().map(String::as_str).collect();
+    remaining.sort_unstable();
+    let mut waves = Vec::new();
+
+    while !remaining.is_empty() {
+        let (ready, blocked): (Vec<&str>, Vec<&str>) = remaining.iter().partition(|table| {
+            parents.get(*table).is_none_or(|p| p.iter().all(|parent| done.contains(parent)))
+        });
+        if ready.is_empty() {
+            return Err(Error::Config(format!(
+                "Lookups or cascading row filters between tables form a cycle: {}",
+                blocked.join(", ")
+            )));
+        }
+        done.extend(&ready);
+        waves.push(ready.into_iter().map(String::from).collect());
+        remaining = blocked;
+    }
+
+    Ok(waves)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_lookups(lookups: &str) -> Config {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml")).unwrap();
+        config.combination_fields = serde_yaml::from_str(lookups).unwrap();
+        config
+    }
+
+    fn tables(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parents_processed_first() -> Result<(), Error> {
+        let config = config_with_lookups(r#"
+            - table: repositories
+              fields:
+                - source_field: project_name
+                  lookup: { table: projects, join_field: project_id, key: id }
+              target_field: full_path
+            - table: projects
+              fields:
+                - source_field: first_name
+                  lookup: { table: customers, join_field: customer_id, key: id }
+              target_field: combo_name
+        "#);
+
+        let waves = processing_waves(&tables(&["repositories", "projects", "customers", "employees"]), &config, &[])?;
+        assert_eq!(waves, vec![
+            tables(&["customers", "employees"]),
+            tables(&["projects"]),
+            tables(&["repositories"]),
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_extra_dependencies() -> Result<(), Error> {
+        let config = config_with_lookups("[]");
+        let dependencies = vec![("employees".to_string(), "customers".to_string())];
+        let waves = processing_waves(&tables(&["customers", "employees"]), &config, &dependencies)?;
+        assert_eq!(waves, vec![tables(&["customers"]), tables(&["employees"])]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lookup_cycles_and_missing_parents() {
+        let config = config_with_lookups(r#"
+            - table: a
+              fields:
+                - source_field: x
+                  lookup: { table: b, join_field: b_id, key: id }
+              target_field: y
+            - table: b
+              fields:
+                - source_field: x
+                  lookup: { table: a, join_field: a_id, key: id }
+              target_field: y
+        "#);
+        assert!(matches!(processing_waves(&tables(&["a", "b"]), &config, &[]), Err(Error::Config(_))));
+
+        let config = config_with_lookups(r#"
+            - table: a
+              fields:
+                - source_field: x
+                  lookup: { table: excluded, join_field: e_id, key: id }
+              target_field: y
+        "#);
+        assert!(matches!(processing_waves(&tables(&["a"]), &config, &[]), Err(Error::Config(_))));
+
+        let config = config_with_lookups(r#"
+            - table: employees
+              fields:
+                - source_field: last_name
+                  lookup: { table: employees, join_field: manager_id, key: id }
+              target_field: manager_name
+        "#);
+        let err = processing_waves(&tables(&["employees"]), &config, &[]).unwrap_err().to_string();
+        assert!(err.contains("'employees' looks up values in its own rows (join_field 'manager_id')"), "{}", err);
+    }
+
+    #[test]
+    fn test_index_reset() -> Result<(), Error> {
+        let config = config_with_lookups(r#"
+            - table: repositories
+              fields:
+                - source_field: project_name
+                  lookup: { table: projects, join_field: project_id, key: id }
+              target_field: full_path
+        "#);
+        let index = LookupIndex::new(&config);
+        let keys = index.keys_for("projects");
+        assert_eq!(keys.len(), 1);
+        assert!(index.keys_for("repositories").is_empty());
+
+        let key = keys[0].clone();
+        index.record(&key, "1".to_string(), "happy-fox".to_string())?;
+        assert_eq!(index.get(&key, "1")?.as_deref(), Some("happy-fox"));
+        index.reset("projects")?;
+        assert_eq!(index.get(&key, "1")?, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use rand::Rng;
+
+const LOWERCASE: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+// Replace every digit with a digit and every letter with a letter of the same case,
+// leaving punctuation, whitespace and length untouched. The last `keep_last`
+// characters are copied through unchanged.
+pub fn mask_value<R: Rng>(value: &str, keep_last: usize, rng: &mut R) -> String {
+    let char_count = value.chars().count();
+    let mask_until = char_count.saturating_sub(keep_last);
+
+    value.chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if i >= mask_until {
+                c
+            } else if c.is_ascii_digit() {
+                char::from(b'0' + rng.gen_range(0..10u8))
+            } else if c.is_uppercase() {
+                char::from(UPPERCASE[rng.gen_range(0..UPPERCASE.len())])
+            } else if c.is_alphabetic() {
+                char::from(LOWERCASE[rng.gen_range(0..LOWERCASE.len())])
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::random::seeded_rng;
+
+    #[test]
+    fn test_mask_preserves_shape() {
+        let original = "(555) 123-4567 ext. A9";
+        let masked = mask_value(original, 0, &mut seeded_rng(42, &[original]));
+
+        assert_eq!(masked.chars().count(), original.chars().count());
+        for (o, m) in original.chars().zip(masked.chars()) {
+            if o.is_ascii_digit() {
+                assert!(m.is_ascii_digit(), "{} should stay a digit, got {}", o, m);
+            } else if o.is_uppercase() {
+                assert!(m.is_ascii_uppercase());
+            } else if o.is_alphabetic() {
+                assert!(m.is_ascii_lowercase());
+            } else {
+                assert_eq!(o, m);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mask_keep_last() {
+        let masked = mask_value("4111-1111-1111-1234", 4, &mut seeded_rng(42, &["card"]));
+        assert!(masked.ends_with("1234"));
+        assert_eq!(masked.len(), 19);
+        assert_eq!(&masked[4..5], "-");
+
+        // Keeping more characters than exist leaves the value unchanged
+        assert_eq!(mask_value("ab1", 10, &mut seeded_rng(42, &["x"])), "ab1");
+    }
+
+    #[test]
+    fn test_mask_is_deterministic() {
+        let a = mask_value("John.Doe@example.com", 0, &mut seeded_rng(7, &["john"]));
+        let b = mask_value("John.Doe@example.com", 0, &mut seeded_rng(7, &["john"]));
+        assert_eq!(a, b);
+        assert_eq!(mask_value("", 2, &mut seeded_rng(7, &[""])), "");
+    }
+}
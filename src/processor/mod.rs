@@ -1,12 +1,35 @@
 mod copy;
 pub(crate) mod sql;
+pub(crate) mod schema;
+pub(crate) mod selector;
+pub(crate) mod diff;
+pub(crate) mod drift;
+pub(crate) mod inspect;
 pub(crate) mod unl;
-mod random;
+pub(crate) mod random;
+pub(crate) mod mask;
+pub(crate) mod generators;
+pub(crate) mod names;
+pub(crate) mod unique;
+pub(crate) mod template;
+pub(crate) mod lookup;
+pub(crate) mod plan;
+pub(crate) mod predicate;
+pub(crate) mod dates;
+pub(crate) mod filter;
+pub(crate) mod columns;
+pub(crate) mod numbers;
+pub(crate) mod redact;
+pub(crate) mod tokens;
+pub(crate) mod keys;
+pub(crate) mod synthetic;
+pub(crate) mod large_objects;
+pub(crate) mod collections;
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::fs::{self, File, OpenOptions};
-use std::io::{self, Write};
+use std::io::Write;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use rayon::prelude::*;
@@ -14,8 +37,12 @@ use indicatif::{ProgressBar, ProgressStyle};
 
 use crate::config::Config;
 use crate::error::Error;
+use crate::processor::drift::Fingerprint;
+use crate::processor::filter::FileStats;
+use crate::processor::plan::RulePlan;
+use crate::processor::random::WordLists;
 use crate::processor::sql::{TableInfo};
-use crate::processor::unl::{UnlProcessor, UnlRow};
+use crate::processor::unl::UnlProcessor;
 
 pub struct DbExportProcessor {
     config: Config,
@@ -24,7 +51,10 @@ pub struct DbExportProcessor {
     table_info: Arc<HashMap<String, TableInfo>>,
     adjectives: Vec<String>,
     nouns: Vec<String>,
+    word_lists: WordLists,
     progress_logger: ProgressLogger,
+    // Where to record the schema once the run succeeds, when drift is checked
+    fingerprint: Option<(PathBuf, Fingerprint)>,
 }
 struct ProgressLogger {
     log_file: Arc<Mutex<File>>,
@@ -45,8 +75,7 @@ impl ProgressLogger {
         // Create the log file with append mode
         let mut log_file = OpenOptions::new()
             .create(true)
-            .write(true)
-            .append(true)  // Add append mode
+            .append(true)
             .read(true)
             .open(&log_path)
             .map_err(Error::Io)?;
@@ -57,7 +86,7 @@ impl ProgressLogger {
         
         Ok(Self {
             log_file: Arc::new(Mutex::new(log_file)),
-            log_path: log_path,
+            log_path,
             start_time: Instant::now(),
         })
     }
@@ -89,9 +118,56 @@ impl ProgressLogger {
     }
 }
 
+// The selector expansions and rule order a run would use, without processing anything
+pub fn plan(mut config: Config, source_path: &Path) -> Result<String, Error> {
+    let sql_content = fs::read_to_string(source_path.join("test_live.sql"))?;
+    let table_info = sql::parse_sql_file(&sql_content)?;
+    let expansions = selector::resolve_config(&mut config, &table_info)?;
+    let plan = RulePlan::build(&config)?;
+    Ok(describe_plan(&expansions, &plan))
+}
+
+// Every table of an export with its file, columns and row counts, and with a config the rules
+// that touch it; as an aligned table or as JSON
+pub fn inspect(source_path: &Path, config: Option<Config>, json: bool) -> Result<String, Error> {
+    let sql_content = fs::read_to_string(sql::schema_path(source_path)?)?;
+    let table_info = sql::parse_sql_file(&sql_content)?;
+    let rules = match config {
+        Some(mut config) => {
+            selector::resolve_config(&mut config, &table_info)?;
+            let plan = RulePlan::build(&config)?;
+            Some((config, plan))
+        }
+        None => None,
+    };
+    let inventory = inspect::inventory(source_path, &table_info, rules.as_ref().map(|(config, plan)| (config, plan)))?;
+    if json {
+        inspect::to_json(&inventory)
+    } else {
+        Ok(inspect::describe(&inventory))
+    }
+}
+
+// What differs between two exports, table by table
+pub fn diff(left: &Path, right: &Path) -> Result<String, Error> {
+    diff::diff_exports(left, right)
+}
+
+fn describe_plan(expansions: &[String], plan: &RulePlan) -> String {
+    let mut out = String::new();
+    if !expansions.is_empty() {
+        out.push_str("Selectors:\n");
+        for line in expansions {
+            out.push_str(&format!("  {}\n", line));
+        }
+    }
+    out.push_str(&format!("Rule order:\n{}", plan.describe()));
+    out
+}
+
 impl DbExportProcessor {
     pub fn new(
-        config: Config, 
+        mut config: Config, 
         source_path: PathBuf, 
         target_path: PathBuf
     ) -> Result<Self, Error> {
@@ -99,15 +175,33 @@ impl DbExportProcessor {
         let log_dir = PathBuf::from(&config.verification.logging.directory);
         let progress_logger = ProgressLogger::new(&log_dir)?;
         
-        // Load word lists with error recovery
-        let adjectives = Self::load_word_list("adjectives.txt")
-            .or_else(|_| Self::load_fallback_words())?;
-        let nouns = Self::load_word_list("nouns.txt")
-            .or_else(|_| Self::load_fallback_words())?;
+        // Load word lists; missing lists are an error unless fallback words are allowed
+        let adjectives = Self::load_words(&config, &config.word_lists.adjectives, &progress_logger)?;
+        let nouns = Self::load_words(&config, &config.word_lists.nouns, &progress_logger)?;
+        let word_lists = Self::load_rule_word_lists(&config, &progress_logger)?;
 
-        // Parse SQL file for table information
+        // Parse SQL file for table information, and point the rules' table names at its tables
         let sql_content = fs::read_to_string(source_path.join("test_live.sql"))?;
         let table_info = Arc::new(sql::parse_sql_file(&sql_content)?);
+        let expansions = selector::resolve_config(&mut config, &table_info)?;
+
+        // Order the rules up front so conflicting writes and cycles fail before any data is touched
+        let plan = RulePlan::build(&config)?;
+        progress_logger.log(describe_plan(&expansions, &plan).trim_end())?;
+
+        // New columns that look like personal data need a rule before any data is touched
+        let fingerprint = Self::check_schema_drift(&config, &plan, &table_info, &progress_logger)?;
+
+        // The key mapping would undo the remapping if it shipped with the export
+        if let Some(file) = &config.key_remapping.mapping_file {
+            let mapping = std::path::absolute(config.resolve_path(file))?;
+            if mapping.starts_with(std::path::absolute(&target_path)?) {
+                return Err(Error::Config(format!(
+                    "key_remapping mapping_file '{}' must be outside the target directory",
+                    mapping.display()
+                )));
+            }
+        }
 
         Ok(Self {
             config,
@@ -116,18 +210,129 @@ impl DbExportProcessor {
             table_info,
             adjectives,
             nouns,
+            word_lists,
             progress_logger,
+            fingerprint,
         })
     }
 
-    fn load_word_list(filename: &str) -> Result<Vec<String>, Error> {
+    // Compare the schema with the fingerprint of the last successful run and report what changed
+    fn check_schema_drift(
+        config: &Config,
+        plan: &RulePlan,
+        table_info: &HashMap<String, TableInfo>,
+        logger: &ProgressLogger,
+    ) -> Result<Option<(PathBuf, Fingerprint)>, Error> {
+        let path = match &config.schema_drift.fingerprint_file {
+            Some(file) => config.resolve_path(file),
+            None => return Ok(None),
+        };
+        let current = Fingerprint::new(table_info);
+
+        let previous = match Fingerprint::read(&path)? {
+            Some(previous) => previous,
+            None => {
+                logger.log(&format!("No schema fingerprint at {}; this run records the first", path.display()))?;
+                return Ok(Some((path, current)));
+            }
+        };
+        let changes = drift::compare(&previous, &current);
+        if changes.is_empty() {
+            logger.log(&format!("Schema unchanged since the last run ({})", current.digest()))?;
+        } else {
+            let lines: Vec<String> = changes.iter().map(|change| format!("  {}", change)).collect();
+            logger.log(&format!(
+                "Schema changed since the last run ({} -> {}):\n{}",
+                previous.digest(),
+                current.digest(),
+                lines.join("\n")
+            ))?;
+        }
+
+        let unruled = drift::unruled_pii(&changes, &current, config, plan)?;
+        if !unruled.is_empty() {
+            let message = format!(
+                "New columns look like personal data but no rule covers them: {}. Add a rule, drop or exclude them, or list them in schema_drift.allow",
+                unruled.join(", ")
+            );
+            if !config.schema_drift.warn_only {
+                return Err(Error::Config(message));
+            }
+            logger.log(&format!("Warning: {}", message))?;
+        }
+        Ok(Some((path, current)))
+    }
+
+    pub fn log_path(&self) -> &Path {
+        self.progress_logger.get_log_path()
+    }
+
+    fn load_words(config: &Config, path: &str, logger: &ProgressLogger) -> Result<Vec<String>, Error> {
+        let resolved = config.resolve_path(path);
+        let result = Self::load_word_list(&resolved).and_then(|words| {
+            if words.is_empty() {
+                Err(Error::Config(format!("Word list '{}' is empty", resolved.display())))
+            } else {
+                Ok(words)
+            }
+        });
+
+        match result {
+            Ok(words) => Ok(words),
+            Err(e) if config.word_lists.allow_fallback => {
+                logger.log(&format!(
+                    "Word list '{}' unavailable ({}), using fallback words",
+                    resolved.display(),
+                    e
+                ))?;
+                Self::load_fallback_words()
+            }
+            Err(e) => Err(Error::Config(format!(
+                "Failed to load word list '{}': {} (set word_lists.allow_fallback to use fallback words)",
+                resolved.display(),
+                e
+            ))),
+        }
+    }
+
+    // Named dictionaries plus any per-rule adjective/noun files
+    fn load_rule_word_lists(config: &Config, logger: &ProgressLogger) -> Result<WordLists, Error> {
+        let mut word_lists = WordLists::default();
+
+        for (name, path) in &config.word_lists.dictionaries {
+            let words = Self::load_words(config, path, logger)?;
+            word_lists.dictionaries.insert(name.clone(), words);
+        }
+
+        for rule in &config.scrubbing.random_names {
+            for path in rule.adjectives.iter().chain(rule.nouns.iter()) {
+                if !word_lists.files.contains_key(path) {
+                    let words = Self::load_words(config, path, logger)?;
+                    word_lists.files.insert(path.clone(), words);
+                }
+            }
+            for name in &rule.dictionaries {
+                if !word_lists.dictionaries.contains_key(name) {
+                    return Err(Error::Config(format!(
+                        "Rule for table '{}' references unknown dictionary '{}'",
+                        rule.table,
+                        name
+                    )));
+                }
+            }
+        }
+
+        Ok(word_lists)
+    }
+
+    fn load_word_list(filename: &Path) -> Result<Vec<String>, Error> {
         fs::read_to_string(filename)?
             .lines()
             .map(String::from)
             .collect::<Vec<_>>()
             .into_iter()
             .filter(|s| !s.is_empty())
-            .map(|s| Ok(s))
+            .map(Ok)
             .collect()
     }
 
@@ -148,34 +353,53 @@ impl DbExportProcessor {
             (*self.table_info).clone(),
             self.adjectives.clone(),
             self.nouns.clone(),
-        ));
+        )?.with_word_lists(self.word_lists.clone()));
+        self.progress_logger.log(&format!("Date conditions count back from {}", unl_processor.reference_date()))?;
+        if unl_processor.tokens_keyed_by_seed() {
+            self.progress_logger.log("No token_secret is set, so tokens are keyed by export.random_seed and anyone holding the config can reproduce them")?;
+        }
+
+        // Columns dropped from the rows are dropped from the schema as well
+        self.drop_schema_columns()?;
 
-        let tables: Vec<_> = self.table_info.keys()
+        let tables: Vec<String> = self.table_info.keys()
             .filter(|table| !self.config.excluded_tables.contains(&table.to_string()))
+            .cloned()
             .collect();
 
+        // Tables whose combinations look up a parent table, whose rows cascade from a
+        // filtered parent, or that reference a remapped key run after that parent
+        let waves = lookup::processing_waves(&tables, &self.config, &unl_processor.table_dependencies())?;
+
         let progress_bar = ProgressBar::new(tables.len() as u64);
         progress_bar.set_style(ProgressStyle::default_bar()
             .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}")
             .map_err(|e| Error::Processing(format!("Failed to set progress bar style: {}", e)))?);
 
-        let results: Vec<Result<(), Error>> = tables.par_iter()
-            .map(|table| {
-                let result = self.process_table(table, &unl_processor);
-                progress_bar.inc(1);
-                if let Err(ref e) = result {
-                    self.progress_logger.log(&format!("Error processing table {}: {}", table, e))?;
-                }
-                result
-            })
-            .collect();
+        let mut results: Vec<Result<(String, FileStats), Error>> = Vec::new();
+        for wave in &waves {
+            results.extend(wave.par_iter()
+                .map(|table| {
+                    let result = self.process_table(table, &unl_processor).map(|stats| (table.clone(), stats));
+                    progress_bar.inc(1);
+                    if let Err(ref e) = result {
+                        self.progress_logger.log(&format!("Error processing table {}: {}", table, e))?;
+                    }
+                    result
+                })
+                .collect::<Vec<_>>());
+
+            // Later waves depend on this one, so stop at the first failed wave
+            if results.iter().any(|r| r.is_err()) {
+                break;
+            }
+        }
 
         progress_bar.finish_with_message("Processing complete");
 
         // Check for any errors
-        let errors: Vec<_> = results.into_iter()
-            .filter_map(|r| r.err())
-            .collect();
+        let (written, errors): (Vec<_>, Vec<_>) = results.into_iter().partition(Result::is_ok);
+        let errors: Vec<Error> = errors.into_iter().filter_map(Result::err).collect();
 
         if !errors.is_empty() {
             self.progress_logger.log(&format!("Completed with {} errors", errors.len()))?;
@@ -186,11 +410,18 @@ impl DbExportProcessor {
         }
 
         self.progress_logger.log("Successfully completed processing")?;
-        self.generate_sql()?;
+        self.update_row_counts(written.into_iter().filter_map(Result::ok))?;
+        self.finish_large_objects(&unl_processor)?;
+        self.write_key_mapping(&unl_processor)?;
+        self.generate_sql(&unl_processor)?;
+        if let Some((path, fingerprint)) = &self.fingerprint {
+            fingerprint.write(path)?;
+            self.progress_logger.log(&format!("Recorded schema fingerprint {} in {}", fingerprint.digest(), path.display()))?;
+        }
         Ok(())
     }
 
-    fn process_table(&self, table: &str, processor: &UnlProcessor) -> Result<(), Error> {
+    fn process_table(&self, table: &str, processor: &UnlProcessor) -> Result<FileStats, Error> {
         let table_info = self.table_info.get(table)
             .ok_or_else(|| Error::Processing(format!("Table info not found for {}", table)))?;
 
@@ -204,7 +435,26 @@ impl DbExportProcessor {
 
         while attempts < max_attempts {
             match processor.process_file(table, &source_unl, &target_unl) {
-                Ok(_) => return Ok(()),
+                Ok(stats) => {
+                    if stats.rows_removed() > 0 {
+                        self.progress_logger.log(&format!(
+                            "Removed {} of {} rows from table {} ({} by row filters, {} cascaded)",
+                            stats.rows_removed(),
+                            stats.rows_read,
+                            table,
+                            stats.rows_filtered,
+                            stats.rows_cascaded
+                        ))?;
+                    }
+                    if stats.rows_generated > 0 {
+                        self.progress_logger.log(&format!(
+                            "Generated {} synthetic rows for table {}",
+                            stats.rows_generated,
+                            table
+                        ))?;
+                    }
+                    return Ok(stats);
+                }
                 Err(e) => {
                     attempts += 1;
                     last_error = Some(e);
@@ -253,23 +503,96 @@ impl DbExportProcessor {
                 }
             }
     
-            let target = self.target_path.join(path.file_name().unwrap());
-            if path.is_file() {
+            // Large-object files may sit in subdirectories; a target inside the source is skipped
+            let target = self.target_path.join(entry.file_name());
+            if path.is_dir() && path != self.target_path {
+                copy::copy_directory(&path, &target)?;
+            } else if path.is_file() {
                 fs::copy(&path, &target)?;
             }
         }
         Ok(())
     }
     
-    fn generate_sql(&self) -> Result<(), Error> {
+    fn drop_schema_columns(&self) -> Result<(), Error> {
+        let sql_path = self.target_path.join("test_live.sql");
+        let mut sql_content = fs::read_to_string(&sql_path)?;
+        let mut changed = false;
+
+        for rule in &self.config.drop_columns {
+            if self.config.excluded_tables.contains(&rule.table) {
+                continue;
+            }
+            sql_content = sql::drop_columns(&sql_content, &rule.table, &rule.fields)?;
+            changed = true;
+            self.progress_logger.log(&format!(
+                "Dropped columns {} from table {}",
+                rule.fields.join(", "),
+                rule.table
+            ))?;
+        }
+
+        if changed {
+            fs::write(sql_path, sql_content)?;
+        }
+        Ok(())
+    }
+
+    // Large-object files only excluded tables, removed rows or dropped references point at
+    // stay out of the export
+    fn finish_large_objects(&self, processor: &UnlProcessor) -> Result<(), Error> {
+        for table in &self.config.excluded_tables {
+            if let Some(info) = self.table_info.get(table) {
+                processor.record_excluded_files(table, &self.source_path.join(&info.unl_file))?;
+            }
+        }
+        let removed = processor.large_objects().finish(&self.target_path)?;
+        if removed > 0 {
+            self.progress_logger.log(&format!("Left {} unreferenced large-object files out of the export", removed))?;
+        }
+        Ok(())
+    }
+
+    // dbimport reads each table's row count from the schema, so it must match the rows written
+    fn update_row_counts(&self, written: impl Iterator<Item = (String, FileStats)>) -> Result<(), Error> {
+        let counts: Vec<(String, usize)> = written
+            .filter(|(_, stats)| stats.rows_written() != stats.rows_read)
+            .filter_map(|(table, stats)| self.table_info.get(&table).map(|info| (info.unl_file.clone(), stats.rows_written())))
+            .collect();
+        if counts.is_empty() {
+            return Ok(());
+        }
+
+        let sql_path = self.target_path.join("test_live.sql");
+        let sql_content = fs::read_to_string(&sql_path)?;
+        fs::write(sql_path, sql::set_row_counts(&sql_content, &counts)?)?;
+        Ok(())
+    }
+
+    // The old and new keys go to a private file outside the export, never into the export itself
+    fn write_key_mapping(&self, processor: &UnlProcessor) -> Result<(), Error> {
+        let path = match &self.config.key_remapping.mapping_file {
+            Some(file) if !self.config.key_remapping.tables.is_empty() => self.config.resolve_path(file),
+            _ => return Ok(()),
+        };
+        let count = processor.key_remapper().write_mapping_file(&path)?;
+        self.progress_logger.log(&format!("Wrote {} remapped keys to {}", count, path.display()))?;
+        Ok(())
+    }
+
+    fn generate_sql(&self, processor: &UnlProcessor) -> Result<(), Error> {
         let mut sql_content = String::new();
         sql_content.push_str("-- Generated SQL for loading processed data\n\n");
 
         for (table, info) in self.table_info.iter() {
             if !self.config.excluded_tables.contains(table) {
+                // Name the remaining columns when some were dropped
+                let columns = processor.kept_fields(table)
+                    .map(|fields| format!(" ({})", fields.join(", ")))
+                    .unwrap_or_default();
                 sql_content.push_str(&format!(
-                    "DELETE FROM {};\nLOAD FROM {} INSERT INTO {};\n\n",
-                    table, info.unl_file, table
+                    "DELETE FROM {};\nLOAD FROM {} INSERT INTO {}{};\n\n",
+                    table, info.unl_file, table, columns
                 ));
             }
         }
@@ -285,11 +608,9 @@ mod tests {
     use super::*;
     use std::fs;
     use tempfile::{TempDir, tempdir};
-    use std::io::Write;
 
     fn setup_test_environment() -> Result<(TempDir, DbExportProcessor), Error> {
-        let temp_dir = tempdir().map_err(|e| Error::Io(std::io::Error::new(
-            std::io::ErrorKind::Other,
+        let temp_dir = tempdir().map_err(|e| Error::Io(std::io::Error::other(
             format!("Failed to create temp dir: {}", e)
         )))?;
         
@@ -324,12 +645,12 @@ mod tests {
                           1002|Jane|Smith|jane@example.com|456 Oak St|555-5678|";
         fs::write(source_dir.join("custo00100.unl"), unl_content).map_err(Error::Io)?;
 
-        // Create test word lists
-        fs::write("adjectives.txt", "happy\nquick\nclever\n").map_err(Error::Io)?;
-        fs::write("nouns.txt", "fox\ndog\ncat\n").map_err(Error::Io)?;
+        // Create test word lists next to where the config would live
+        fs::write(temp_dir.path().join("adjectives.txt"), "happy\nquick\nclever\n").map_err(Error::Io)?;
+        fs::write(temp_dir.path().join("nouns.txt"), "fox\ndog\ncat\n").map_err(Error::Io)?;
 
         // Create test configuration with all required fields
-        let config = Config::from_str(r#"
+        let mut config = Config::from_str(r#"
             databases:
                 source:
                     name: test_live
@@ -391,6 +712,7 @@ mod tests {
                   separator: " -&- "
                   target_field: combo_name
         "#)?;
+        config.base_dir = Some(temp_dir.path().to_path_buf());
 
         let processor = DbExportProcessor::new(
             config,
@@ -465,8 +787,291 @@ mod tests {
     }
 
     #[test]
-    fn test_error_recovery() -> Result<(), Error> {
+    fn test_row_filters_cascade() -> Result<(), Error> {
+        let (_temp_dir, processor) = setup_test_environment()?;
+        let source_dir = processor.source_path.clone();
+
+        let mut sql = fs::read_to_string(source_dir.join("test_live.sql"))?;
+        sql.push_str(r#"
+            { TABLE "informix".projects row size = 362 number of columns = 3 index size = 0 }
+            { unload file name = proje00102.unl number of rows = 3 }
+            create table "informix".projects 
+            (
+                id serial not null,
+                customer_id integer,
+                project_name varchar(100)
+            ) extent size 16 next size 16 lock mode row;
+
+            alter table "informix".customers add constraint primary key (id) constraint "informix".u100_1;
+            alter table "informix".projects add constraint (foreign key (customer_id)
+                references "informix".customers  constraint "informix".r102_1);
+        "#);
+        fs::write(source_dir.join("test_live.sql"), sql)?;
+        fs::write(source_dir.join("proje00102.unl"), "1|1001|Alpha|\n2|1002|Beta|\n3||Gamma|\n")?;
+
+        let mut config = processor.config.clone();
+        config.combination_fields.clear();
+        config.row_filters = serde_yaml::from_str(r#"
+            - table: customers
+              where:
+                - field: first_name
+                  equals: Jane
+              cascade: true
+        "#)?;
+        let processor = DbExportProcessor::new(config, source_dir, processor.target_path.clone())?;
+        processor.process()?;
+
+        let customers = fs::read_to_string(processor.target_path.join("custo00100.unl"))?;
+        assert_eq!(customers.lines().count(), 1);
+        assert!(customers.starts_with("1001|"));
+
+        let projects = fs::read_to_string(processor.target_path.join("proje00102.unl"))?;
+        let ids: Vec<&str> = projects.lines().map(|line| line.split('|').next().unwrap()).collect();
+        assert_eq!(ids, vec!["1", "3"]);
+        let schema = fs::read_to_string(processor.target_path.join("test_live.sql"))?;
+        assert!(schema.contains("{ unload file name = proje00102.unl number of rows = 2 }"));
+
+        let log = fs::read_to_string(processor.log_path())?;
+        assert!(log.contains("Removed 1 of 2 rows from table customers (1 by row filters, 0 cascaded)"));
+        assert!(log.contains("Removed 1 of 3 rows from table projects (0 by row filters, 1 cascaded)"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_remapping() -> Result<(), Error> {
         let (temp_dir, processor) = setup_test_environment()?;
+        let source_dir = processor.source_path.clone();
+
+        let mut sql = fs::read_to_string(source_dir.join("test_live.sql"))?;
+        sql.push_str(r#"
+            { TABLE "informix".projects row size = 362 number of columns = 3 index size = 0 }
+            { unload file name = proje00102.unl number of rows = 3 }
+            create table "informix".projects 
+            (
+                id serial not null,
+                customer_id integer,
+                project_name varchar(100)
+            ) extent size 16 next size 16 lock mode row;
+
+            alter table "informix".customers add constraint primary key (id) constraint "informix".u100_1;
+            alter table "informix".projects add constraint (foreign key (customer_id)
+                references "informix".customers  constraint "informix".r102_1);
+        "#);
+        fs::write(source_dir.join("test_live.sql"), sql)?;
+        fs::write(source_dir.join("proje00102.unl"), "1|1001|Alpha|\n2|1002|Beta|\n3||Gamma|\n")?;
+
+        let mapping_file = temp_dir.path().join("private").join("key_map.csv");
+        let mut config = processor.config.clone();
+        config.combination_fields.clear();
+        config.key_remapping = serde_yaml::from_str(&format!(
+            "{{ mapping_file: '{}', tables: [{{ table: customers, start: 500 }}] }}",
+            mapping_file.display()
+        ))?;
+        let processor = DbExportProcessor::new(config.clone(), source_dir.clone(), processor.target_path.clone())?;
+        processor.process()?;
+
+        let mapping = fs::read_to_string(&mapping_file)?;
+        let new_keys: HashMap<&str, &str> = mapping.lines().skip(1)
+            .map(|line| {
+                let fields: Vec<&str> = line.split(',').collect();
+                assert_eq!(&fields[..2], &["customers", "id"]);
+                (fields[2], fields[3])
+            })
+            .collect();
+        assert_eq!(new_keys.len(), 2);
+
+        let customers = fs::read_to_string(processor.target_path.join("custo00100.unl"))?;
+        let ids: Vec<&str> = customers.lines().map(|line| line.split('|').next().unwrap()).collect();
+        assert_eq!(ids, vec![new_keys["1001"], new_keys["1002"]]);
+        assert!(ids.iter().all(|id| ["500", "501"].contains(id)));
+
+        let projects = fs::read_to_string(processor.target_path.join("proje00102.unl"))?;
+        let references: Vec<&str> = projects.lines().map(|line| line.split('|').nth(1).unwrap()).collect();
+        assert_eq!(references, vec![new_keys["1001"], new_keys["1002"], ""]);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&mapping_file)?.permissions().mode() & 0o777, 0o600);
+        }
+
+        // The mapping must never land in the export
+        config.key_remapping.mapping_file = Some(processor.target_path.join("key_map.csv").display().to_string());
+        let result = DbExportProcessor::new(config, source_dir, processor.target_path.clone());
+        assert!(matches!(result, Err(Error::Config(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_synthetic_rows() -> Result<(), Error> {
+        let (_temp_dir, processor) = setup_test_environment()?;
+        let source_dir = processor.source_path.clone();
+
+        let mut sql = fs::read_to_string(source_dir.join("test_live.sql"))?;
+        sql.push_str(r#"
+            { TABLE "informix".projects row size = 362 number of columns = 3 index size = 0 }
+            { unload file name = proje00102.unl number of rows = 0 }
+            create table "informix".projects 
+            (
+                id serial not null,
+                customer_id integer not null,
+                project_name varchar(100)
+            ) extent size 16 next size 16 lock mode row;
+
+            alter table "informix".customers add constraint primary key (id) constraint "informix".u100_1;
+            alter table "informix".projects add constraint primary key (id) constraint "informix".u102_1;
+            alter table "informix".projects add constraint (foreign key (customer_id)
+                references "informix".customers  constraint "informix".r102_1);
+        "#);
+        fs::write(source_dir.join("test_live.sql"), sql)?;
+        fs::write(source_dir.join("proje00102.unl"), "")?;
+
+        let mut config = processor.config.clone();
+        config.combination_fields.clear();
+        config.synthetic_rows = serde_yaml::from_str(r#"
+            - table: projects
+              count: 4
+              columns:
+                project_name: { generator: company }
+        "#)?;
+        let processor = DbExportProcessor::new(config, source_dir, processor.target_path.clone())?;
+        processor.process()?;
+
+        let projects = fs::read_to_string(processor.target_path.join("proje00102.unl"))?;
+        let rows: Vec<Vec<&str>> = projects.lines().map(|line| line.split('|').collect()).collect();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows.iter().map(|row| row[0]).collect::<Vec<_>>(), vec!["1", "2", "3", "4"]);
+        assert!(rows.iter().all(|row| ["1001", "1002"].contains(&row[1]) && !row[2].is_empty()));
+
+        let schema = fs::read_to_string(processor.target_path.join("test_live.sql"))?;
+        assert!(schema.contains("{ unload file name = proje00102.unl number of rows = 4 }"));
+        let log = fs::read_to_string(processor.log_path())?;
+        assert!(log.contains("Generated 4 synthetic rows for table projects"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_large_object_files() -> Result<(), Error> {
+        let (_temp_dir, processor) = setup_test_environment()?;
+        let source_dir = processor.source_path.clone();
+
+        let mut sql = fs::read_to_string(source_dir.join("test_live.sql"))?;
+        sql.push_str(r#"
+            { TABLE "informix".documents row size = 120 number of columns = 3 index size = 0 }
+            { unload file name = docum00103.unl number of rows = 2 }
+            create table "informix".documents 
+            (
+                id serial not null,
+                scan blob,
+                notes text
+            ) extent size 16 next size 16 lock mode row;
+        "#);
+        fs::write(source_dir.join("test_live.sql"), sql)?;
+        fs::write(
+            source_dir.join("docum00103.unl"),
+            "1|0,4,blob/scan.001|0,5,blob/notes.001|\n2|4,4,blob/scan.001|5,5,blob/notes.001|\n"
+        )?;
+        fs::create_dir_all(source_dir.join("blob"))?;
+        fs::write(source_dir.join("blob").join("scan.001"), "AAAABBBB")?;
+        fs::write(source_dir.join("blob").join("notes.001"), "firstsecnd")?;
+
+        let mut config = processor.config.clone();
+        config.large_objects = serde_yaml::from_str("[{ table: documents, fields: [scan], action: drop }]")?;
+        let processor = DbExportProcessor::new(config, source_dir, processor.target_path.clone())?;
+        processor.process()?;
+
+        let documents = fs::read_to_string(processor.target_path.join("docum00103.unl"))?;
+        assert_eq!(documents, "1||0,5,blob/notes.001|\n2||5,5,blob/notes.001|\n");
+        assert_eq!(fs::read_to_string(processor.target_path.join("blob").join("notes.001"))?, "firstsecnd");
+        assert!(!processor.target_path.join("blob").join("scan.001").exists());
+
+        let log = fs::read_to_string(processor.log_path())?;
+        assert!(log.contains("Left 1 unreferenced large-object files out of the export"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_shows_selector_expansion() -> Result<(), Error> {
+        let (_temp_dir, processor) = setup_test_environment()?;
+        let mut config = processor.config.clone();
+        config.masking = serde_yaml::from_str(r#"[{ table: "cust*", fields: ["/^(email|phone)$/"] }]"#)?;
+        config.standardize.email.fields.clear();
+        config.standardize.phone.fields.clear();
+
+        let plan = plan(config, &processor.source_path)?;
+        assert!(plan.contains("Selectors:\n  masking[0] 'cust*' -> masking[0] customers (email, phone)\n"));
+        assert!(plan.contains("Rule order:\ncustomers:\n"));
+        assert!(plan.contains("masking[0] -> email, phone"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_drift_requires_rules_for_new_pii() -> Result<(), Error> {
+        let (temp_dir, processor) = setup_test_environment()?;
+        let mut config = processor.config.clone();
+        let fingerprint_file = temp_dir.path().join("schema.fingerprint");
+        config.schema_drift.fingerprint_file = Some(fingerprint_file.display().to_string());
+
+        let first = DbExportProcessor::new(config.clone(), processor.source_path.clone(), processor.target_path.clone())?;
+        first.process()?;
+        assert!(fingerprint_file.exists());
+
+        // A mobile column appears in the live schema
+        let sql_path = processor.source_path.join("test_live.sql");
+        let sql = fs::read_to_string(&sql_path)?
+            .replace("number of columns = 6", "number of columns = 7")
+            .replace("phone varchar(20)\n", "phone varchar(20),\n                mobile varchar(20)\n");
+        fs::write(&sql_path, sql)?;
+
+        match DbExportProcessor::new(config.clone(), processor.source_path.clone(), processor.target_path.clone()) {
+            Err(Error::Config(message)) => assert!(message.contains("customers.mobile"), "{}", message),
+            Err(e) => panic!("Unexpected error: {}", e),
+            Ok(_) => panic!("New mobile column passed without a rule"),
+        }
+
+        config.schema_drift.allow = vec!["customers.mobile".to_string()];
+        DbExportProcessor::new(config.clone(), processor.source_path.clone(), processor.target_path.clone())?;
+
+        // Renaming the table leaves the rules for customers behind
+        let sql = fs::read_to_string(&sql_path)?.replace("customers", "clients");
+        fs::write(&sql_path, sql)?;
+        match DbExportProcessor::new(config, processor.source_path.clone(), processor.target_path.clone()) {
+            Err(Error::Config(message)) => assert!(message.contains("clients.email"), "{}", message),
+            Err(e) => panic!("Unexpected error: {}", e),
+            Ok(_) => panic!("Renamed table passed with rules for its old name"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_and_drop_columns() -> Result<(), Error> {
+        let (_temp_dir, processor) = setup_test_environment()?;
+        let mut config = processor.config.clone();
+        config.standardize.email.fields.clear();
+        config.null_fields = serde_yaml::from_str("[{ table: customers, fields: [email] }]")?;
+        config.drop_columns = serde_yaml::from_str("[{ table: customers, fields: [address] }]")?;
+        let processor = DbExportProcessor::new(config, processor.source_path.clone(), processor.target_path.clone())?;
+        processor.process()?;
+
+        let customers = fs::read_to_string(processor.target_path.join("custo00100.unl"))?;
+        for line in customers.lines() {
+            let fields: Vec<&str> = line.split('|').collect();
+            assert_eq!(fields.len(), 6);
+            assert_eq!(fields[3], "");
+            assert_eq!(fields[4], "555-0000");
+        }
+
+        let schema = fs::read_to_string(processor.target_path.join("test_live.sql"))?;
+        assert!(schema.contains("number of columns = 5 "));
+        assert!(!schema.contains("address"));
+        let load = fs::read_to_string(processor.target_path.join("load_data.sql"))?;
+        assert!(load.contains("INSERT INTO customers (id, first_name, last_name, email, phone);"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_recovery() -> Result<(), Error> {
+        let (_temp_dir, processor) = setup_test_environment()?;
     
         // Log initial message
         processor.progress_logger.log("Starting error recovery test")?;
@@ -560,20 +1165,59 @@ mod tests {
     }
 
     #[test]
-    fn test_fallback_word_lists() -> Result<(), Error> {
-        // Rename existing word lists to simulate missing files
-        fs::rename("adjectives.txt", "adjectives.txt.bak")?;
-        fs::rename("nouns.txt", "nouns.txt.bak")?;
+    fn test_missing_word_lists() -> Result<(), Error> {
+        let (temp_dir, processor) = setup_test_environment()?;
+        fs::remove_file(temp_dir.path().join("adjectives.txt"))?;
 
-        let (_temp_dir, processor) = setup_test_environment()?;
+        // Missing word lists are an error by default
+        let result = DbExportProcessor::new(
+            processor.config.clone(),
+            processor.source_path.clone(),
+            processor.target_path.clone(),
+        );
+        match result {
+            Err(Error::Config(msg)) => assert!(msg.contains("adjectives.txt"), "unexpected message: {}", msg),
+            _ => panic!("Expected Config error for missing word list"),
+        }
 
-        // Verify fallback words were loaded
-        assert!(!processor.adjectives.is_empty());
-        assert!(!processor.nouns.is_empty());
+        // ...unless fallback words are explicitly allowed
+        let mut config = processor.config.clone();
+        config.word_lists.allow_fallback = true;
+        let processor = DbExportProcessor::new(
+            config,
+            processor.source_path.clone(),
+            processor.target_path.clone(),
+        )?;
+        assert_eq!(processor.adjectives, DbExportProcessor::load_fallback_words()?);
+        assert_eq!(processor.nouns, vec!["fox", "dog", "cat"]);
+
+        Ok(())
+    }
 
-        // Restore word lists
-        fs::rename("adjectives.txt.bak", "adjectives.txt")?;
-        fs::rename("nouns.txt.bak", "nouns.txt")?;
+    #[test]
+    fn test_rule_word_lists() -> Result<(), Error> {
+        let (temp_dir, processor) = setup_test_environment()?;
+        fs::write(temp_dir.path().join("colors.txt"), "red\nblue\n")?;
+        fs::write(temp_dir.path().join("animals.txt"), "owl\n")?;
+
+        let mut config = processor.config.clone();
+        config.word_lists.dictionaries.insert("colors".to_string(), "colors.txt".to_string());
+        config.scrubbing.random_names[0].nouns = Some("animals.txt".to_string());
+        config.scrubbing.random_names[0].dictionaries = vec!["colors".to_string()];
+        let loaded = DbExportProcessor::new(
+            config.clone(),
+            processor.source_path.clone(),
+            processor.target_path.clone(),
+        )?;
+        assert_eq!(loaded.word_lists.dictionaries["colors"], vec!["red", "blue"]);
+        assert_eq!(loaded.word_lists.files["animals.txt"], vec!["owl"]);
+
+        // Referencing an undefined dictionary fails up front
+        config.scrubbing.random_names[0].dictionaries = vec!["shapes".to_string()];
+        assert!(matches!(
+            DbExportProcessor::new(config, processor.source_path.clone(), processor.target_path.clone()),
+            Err(Error::Config(_))
+        ));
 
         Ok(())
     }
@@ -581,8 +1225,7 @@ mod tests {
     #[test]
     fn test_excluded_tables_not_copied() -> Result<(), Error> {
         // Set up test environment
-        let temp_dir = tempdir().map_err(|e| Error::Io(std::io::Error::new(
-            std::io::ErrorKind::Other,
+        let temp_dir = tempdir().map_err(|e| Error::Io(std::io::Error::other(
             format!("Failed to create temp dir: {}", e)
         )))?;
         
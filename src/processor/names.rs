@@ -0,0 +1,144 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::config::NamePart;
+
+pub const FEMALE_FIRST_NAMES: &[&str] = &[
+    "Mary", "Patricia", "Jennifer", "Linda", "Elizabeth", "Barbara", "Susan", "Jessica",
+    "Sarah", "Karen", "Lisa", "Nancy", "Betty", "Margaret", "Sandra", "Ashley", "Kimberly",
+    "Emily", "Donna", "Michelle", "Carol", "Amanda", "Melissa", "Deborah", "Stephanie",
+    "Rebecca", "Sharon", "Laura", "Cynthia", "Kathleen", "Amy", "Angela", "Shirley", "Anna",
+    "Brenda", "Pamela", "Emma", "Nicole", "Helen", "Samantha", "Katherine", "Christine",
+    "Rachel", "Carolyn", "Janet", "Catherine", "Maria", "Heather", "Diane", "Julie", "Olivia",
+    "Sophia", "Grace", "Chloe", "Hannah", "Victoria", "Natalie", "Megan", "Alice", "Jane",
+];
+
+pub const MALE_FIRST_NAMES: &[&str] = &[
+    "James", "Robert", "John", "Michael", "David", "William", "Richard", "Joseph", "Thomas",
+    "Charles", "Christopher", "Daniel", "Matthew", "Anthony", "Mark", "Donald", "Steven",
+    "Paul", "Andrew", "Joshua", "Kenneth", "Kevin", "Brian", "George", "Timothy", "Ronald",
+    "Edward", "Jason", "Jeffrey", "Ryan", "Jacob", "Gary", "Nicholas", "Eric", "Jonathan",
+    "Stephen", "Larry", "Justin", "Scott", "Brandon", "Benjamin", "Samuel", "Gregory",
+    "Alexander", "Frank", "Patrick", "Raymond", "Jack", "Dennis", "Jerry", "Tyler", "Aaron",
+    "Henry", "Peter", "Nathan", "Adam", "Zachary", "Walter", "Ethan", "Noah",
+];
+
+pub const SURNAMES: &[&str] = &[
+    "Smith", "Johnson", "Williams", "Brown", "Jones", "Garcia", "Miller", "Davis", "Rodriguez",
+    "Martinez", "Hernandez", "Lopez", "Gonzalez", "Wilson", "Anderson", "Thomas", "Taylor",
+    "Moore", "Jackson", "Martin", "Lee", "Perez", "Thompson", "White", "Harris", "Sanchez",
+    "Clark", "Ramirez", "Lewis", "Robinson", "Walker", "Young", "Allen", "King", "Wright",
+    "Scott", "Torres", "Nguyen", "Hill", "Flores", "Green", "Adams", "Nelson", "Baker", "Hall",
+    "Rivera", "Campbell", "Mitchell", "Carter", "Roberts", "Gomez", "Phillips", "Evans",
+    "Turner", "Diaz", "Parker", "Cruz", "Edwards", "Collins", "Reyes", "Stewart", "Morris",
+    "Morales", "Murphy", "Cook", "Rogers", "Gutierrez", "Ortiz", "Morgan", "Cooper", "Peterson",
+    "Bailey", "Reed", "Kelly", "Howard", "Ramos", "Kim", "Cox", "Ward", "Richardson",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gender {
+    Female,
+    Male,
+}
+
+impl Gender {
+    // Interpret a gender column value such as "F", "female", "M" or "Male"
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim().to_ascii_lowercase().as_str() {
+            "f" | "female" | "w" | "woman" => Some(Gender::Female),
+            "m" | "male" | "man" => Some(Gender::Male),
+            _ => None,
+        }
+    }
+
+    // Guess the gender of an original first name from the bundled lists
+    pub fn from_first_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        if FEMALE_FIRST_NAMES.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+            Some(Gender::Female)
+        } else if MALE_FIRST_NAMES.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+            Some(Gender::Male)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PersonName {
+    pub first: String,
+    pub middle: String,
+    pub last: String,
+}
+
+impl PersonName {
+    pub fn generate<R: Rng>(gender: Option<Gender>, rng: &mut R) -> Self {
+        PersonName {
+            first: first_name(gender, rng),
+            middle: first_name(gender, rng),
+            last: last_name(rng),
+        }
+    }
+
+    pub fn full(&self) -> String {
+        format!("{} {}", self.first, self.last)
+    }
+
+    pub fn part(&self, part: NamePart) -> String {
+        match part {
+            NamePart::First => self.first.clone(),
+            NamePart::Middle => self.middle.clone(),
+            NamePart::MiddleInitial => self.middle.chars().take(1).collect(),
+            NamePart::Last => self.last.clone(),
+            NamePart::Full => self.full(),
+        }
+    }
+}
+
+pub fn first_name<R: Rng>(gender: Option<Gender>, rng: &mut R) -> String {
+    let gender = gender.unwrap_or_else(|| if rng.gen_bool(0.5) { Gender::Female } else { Gender::Male });
+    let names = match gender {
+        Gender::Female => FEMALE_FIRST_NAMES,
+        Gender::Male => MALE_FIRST_NAMES,
+    };
+    names.choose(rng).copied().unwrap_or_default().to_string()
+}
+
+pub fn last_name<R: Rng>(rng: &mut R) -> String {
+    SURNAMES.choose(rng).copied().unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::random::seeded_rng;
+
+    #[test]
+    fn test_gender_parsing() {
+        assert_eq!(Gender::from_code("F"), Some(Gender::Female));
+        assert_eq!(Gender::from_code(" male "), Some(Gender::Male));
+        assert_eq!(Gender::from_code("X"), None);
+        assert_eq!(Gender::from_first_name("mary"), Some(Gender::Female));
+        assert_eq!(Gender::from_first_name("John"), Some(Gender::Male));
+        assert_eq!(Gender::from_first_name("Essential1"), None);
+    }
+
+    #[test]
+    fn test_gender_consistent_first_names() {
+        for i in 0..20 {
+            let name = first_name(Some(Gender::Female), &mut seeded_rng(i, &[]));
+            assert!(FEMALE_FIRST_NAMES.contains(&name.as_str()));
+            let name = first_name(Some(Gender::Male), &mut seeded_rng(i, &[]));
+            assert!(MALE_FIRST_NAMES.contains(&name.as_str()));
+        }
+    }
+
+    #[test]
+    fn test_person_name() {
+        let a = PersonName::generate(None, &mut seeded_rng(42, &["person"]));
+        let b = PersonName::generate(None, &mut seeded_rng(42, &["person"]));
+        assert_eq!(a, b);
+        assert!(SURNAMES.contains(&a.last.as_str()));
+        assert_eq!(a.full(), format!("{} {}", a.first, a.last));
+    }
+}
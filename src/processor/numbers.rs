@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use rand::Rng;
+
+use crate::error::Error;
+use crate::processor::sql::ColumnInfo;
+
+// Decimal places and bounds a perturbed value must fit, from the column's declared type
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NumericFormat {
+    // None for floating types, which keep the value's own decimal places
+    pub scale: Option<u32>,
+    // Largest magnitude in units of the scale, e.g. 99999 for decimal(5,2); kept as an
+    // integer so int8 bounds are exact
+    pub max: Option<i128>,
+}
+
+impl NumericFormat {
+    // Floating point with no bounds, for columns the schema says nothing about
+    pub const FLOATING: NumericFormat = NumericFormat { scale: None, max: None };
+
+    // None if the column is not numeric
+    pub fn from_column(column: &ColumnInfo) -> Option<Self> {
+        let integer = |max: i128| Some(NumericFormat { scale: Some(0), max: Some(max) });
+        let (precision, scale) = type_arguments(&column.data_type);
+
+        match column.base_type().as_str() {
+            "smallint" => integer(i16::MAX as i128),
+            "integer" | "int" | "serial" => integer(i32::MAX as i128),
+            "int8" | "bigint" | "serial8" | "bigserial" => integer(i64::MAX as i128),
+            // MONEY defaults to MONEY(16,2), and MONEY(p) to MONEY(p,2)
+            "money" => fixed(precision.unwrap_or(16), scale.unwrap_or(2)),
+            // DECIMAL(p) without a scale is a floating decimal
+            "decimal" | "dec" | "numeric" => match (precision, scale) {
+                (Some(precision), Some(scale)) => fixed(precision, scale),
+                _ => Some(NumericFormat::FLOATING),
+            },
+            "float" | "smallfloat" | "real" | "double" => Some(NumericFormat::FLOATING),
+            _ => None,
+        }
+    }
+
+    // Decimal places to write a value with, given how it was exported
+    pub fn scale_for(&self, value: &str) -> u32 {
+        self.scale.unwrap_or_else(|| value.trim().split_once('.').map_or(0, |(_, decimals)| decimals.len() as u32))
+    }
+
+    // How many whole-number digits every value of that length fits in, e.g. 4 for smallint
+    // and 8 for decimal(10,2); None for floating types
+    pub fn digits(&self) -> Option<usize> {
+        let whole = (self.max? / 10i128.pow(self.scale?)).to_string();
+        Some(if whole.bytes().all(|b| b == b'9') { whole.len() } else { whole.len() - 1 })
+    }
+
+    // Keep a value, in units of the scale, within the column's bounds
+    pub fn clamp(&self, units: i128) -> i128 {
+        match self.max {
+            Some(max) => units.clamp(-max, max),
+            None => units,
+        }
+    }
+}
+
+fn fixed(precision: u32, scale: u32) -> Option<NumericFormat> {
+    Some(NumericFormat {
+        scale: Some(scale),
+        max: Some(10i128.checked_pow(precision)? - 1),
+    })
+}
+
+// Precision and scale from a type such as "decimal(10,2)"
+fn type_arguments(data_type: &str) -> (Option<u32>, Option<u32>) {
+    let arguments = data_type.split_once('(')
+        .and_then(|(_, rest)| rest.split_once(')'))
+        .map(|(arguments, _)| arguments)
+        .unwrap_or_default();
+    let mut numbers = arguments.split(',').map(|n| n.trim().parse().ok());
+    (numbers.next().flatten(), numbers.next().flatten())
+}
+
+// Change a value by a seeded factor within ±`fraction` of itself
+pub fn noise<R: Rng>(value: f64, fraction: f64, rng: &mut R) -> f64 {
+    if fraction <= 0.0 {
+        return value;
+    }
+    value * (1.0 + rng.gen_range(-fraction..=fraction))
+}
+
+// Round down to a multiple of `size`
+pub fn bucket(value: f64, size: f64) -> f64 {
+    (value / size).floor() * size
+}
+
+// A value as a whole number of its smallest unit, e.g. cents for scale 2
+pub fn to_units(value: f64, scale: u32) -> i128 {
+    (value * 10f64.powi(scale as i32)).round() as i128
+}
+
+pub fn format_units(units: i128, scale: u32) -> String {
+    if scale == 0 {
+        return units.to_string();
+    }
+    let divisor = 10i128.pow(scale);
+    let sign = if units < 0 { "-" } else { "" };
+    format!("{}{}.{:0width$}", sign, (units / divisor).abs(), (units % divisor).abs(), width = scale as usize)
+}
+
+// Running state for spreading the difference between a column's exported and noisy totals
+// back over its rows, in proportion to each row's noisy value
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct SumState {
+    difference: i128,
+    noisy_total: i128,
+    rows: i128,
+    seen_total: i128,
+    seen_rows: i128,
+    applied: i128,
+}
+
+// (table, rule index, field)
+type SumKey = (String, usize, String);
+
+// Per-column corrections that keep perturbed totals equal to the exported ones. The totals
+// come from a first pass over the table's file; the rows then take their share in order.
+#[derive(Debug, Default)]
+pub struct SumCorrections {
+    sums: Mutex<HashMap<SumKey, SumState>>,
+}
+
+impl SumCorrections {
+    // Forget a table's totals, e.g. before a retry processes it again
+    pub fn reset(&self, table_name: &str) -> Result<(), Error> {
+        self.lock()?.retain(|(table, _, _), _| table != table_name);
+        Ok(())
+    }
+
+    // Totals from the first pass, in the column's smallest unit
+    pub fn prepare(&self, table_name: &str, rule: usize, field: &str, exported: i128, noisy: i128, rows: i128) -> Result<(), Error> {
+        self.lock()?.insert((table_name.to_string(), rule, field.to_string()), SumState {
+            difference: exported - noisy,
+            noisy_total: noisy,
+            rows,
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    // The next row's noisy value with its share of the difference added, within the column's
+    // bounds. Rounding the cumulative share rather than each row's keeps the corrections adding
+    // up to the difference exactly, and a share a row can't take is carried to the rows after it.
+    pub fn next(&self, table_name: &str, rule: usize, field: &str, noisy: i128, format: &NumericFormat) -> Result<i128, Error> {
+        let mut sums = self.lock()?;
+        let state = match sums.get_mut(&(table_name.to_string(), rule, field.to_string())) {
+            Some(state) => state,
+            None => return Ok(noisy),
+        };
+        state.seen_total += noisy;
+        state.seen_rows += 1;
+
+        let target = if state.noisy_total != 0 {
+            div_round(state.difference * state.seen_total, state.noisy_total)
+        } else {
+            div_round(state.difference * state.seen_rows, state.rows.max(1))
+        };
+        let corrected = format.clamp(noisy + target - state.applied);
+        state.applied += corrected - noisy;
+        Ok(corrected)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, HashMap<SumKey, SumState>>, Error> {
+        self.sums.lock()
+            .map_err(|e| Error::Processing(format!("Failed to lock column totals: {}", e)))
+    }
+}
+
+fn div_round(numerator: i128, denominator: i128) -> i128 {
+    let (numerator, denominator) = if denominator < 0 { (-numerator, -denominator) } else { (numerator, denominator) };
+    (2 * numerator + denominator).div_euclid(2 * denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::random::seeded_rng;
+
+    fn format(data_type: &str) -> Option<NumericFormat> {
+        NumericFormat::from_column(&ColumnInfo { data_type: data_type.to_string(), ..Default::default() })
+    }
+
+    #[test]
+    fn test_formats_from_schema() {
+        assert_eq!(format("decimal(5,2)"), Some(NumericFormat { scale: Some(2), max: Some(99_999) }));
+        assert_eq!(format("money").and_then(|f| f.scale), Some(2));
+        assert_eq!(format("smallint").and_then(|f| f.max), Some(32_767));
+        assert_eq!(format("decimal(8)"), Some(NumericFormat::FLOATING));
+        assert_eq!(format("varchar(10)"), None);
+        assert_eq!(NumericFormat::FLOATING.scale_for("3.250"), 3);
+        assert_eq!(format("decimal(5,2)").unwrap().clamp(1_234_500), 99_999);
+        assert_eq!(format("smallint").and_then(|f| f.digits()), Some(4));
+        assert_eq!(format("int8").and_then(|f| f.digits()), Some(18));
+        assert_eq!(format("decimal(10,2)").and_then(|f| f.digits()), Some(8));
+        assert_eq!(NumericFormat::FLOATING.digits(), None);
+        assert_eq!(format("serial8").unwrap().clamp(to_units(1e19, 0)), 9_223_372_036_854_775_807);
+    }
+
+    #[test]
+    fn test_noise_and_buckets() {
+        let mut rng = seeded_rng(42, &["number"]);
+        for _ in 0..100 {
+            let noisy = noise(1000.0, 0.1, &mut rng);
+            assert!((900.0..=1100.0).contains(&noisy));
+        }
+        assert_eq!(bucket(52_345.0, 1000.0), 52_000.0);
+        assert_eq!(bucket(-1.5, 1.0), -2.0);
+        assert_eq!(to_units(12.345, 2), 1235);
+        assert_eq!(format_units(1235, 2), "12.35");
+        assert_eq!(format_units(-5, 2), "-0.05");
+        assert_eq!(format_units(42, 0), "42");
+    }
+
+    #[test]
+    fn test_corrections_restore_total() -> Result<(), Error> {
+        let exported = [1000, 2500, 333, 7];
+        let noisy = [1040, 2410, 350, 8];
+        let corrections = SumCorrections::default();
+        corrections.prepare("t", 0, "amount", exported.iter().sum(), noisy.iter().sum(), 4)?;
+
+        let mut total = 0;
+        for value in noisy {
+            total += corrections.next("t", 0, "amount", value, &NumericFormat::FLOATING)?;
+        }
+        assert_eq!(total, exported.iter().sum::<i128>());
+
+        // A share that would push a value past the column's bounds goes to later rows
+        let bounded = NumericFormat { scale: Some(0), max: Some(1300) };
+        corrections.prepare("t", 0, "amount", 2400, 1800, 2)?;
+        assert_eq!(corrections.next("t", 0, "amount", 1000, &bounded)?, 1300);
+        assert_eq!(corrections.next("t", 0, "amount", 800, &bounded)?, 1100);
+
+        corrections.reset("t")?;
+        assert_eq!(corrections.next("t", 0, "amount", 5, &bounded)?, 5);
+        Ok(())
+    }
+}
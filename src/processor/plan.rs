@@ -0,0 +1,469 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use crate::config::{Condition, Config, SourceStage, StandardizeConfig, StandardizeField};
+use crate::error::Error;
+use crate::processor::template;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StandardizeKind {
+    Address,
+    Phone,
+    Email,
+}
+
+impl StandardizeKind {
+    pub const ALL: [StandardizeKind; 3] = [StandardizeKind::Address, StandardizeKind::Phone, StandardizeKind::Email];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StandardizeKind::Address => "address",
+            StandardizeKind::Phone => "phone",
+            StandardizeKind::Email => "email",
+        }
+    }
+
+    pub fn field<'a>(&self, config: &'a StandardizeConfig) -> &'a StandardizeField {
+        match self {
+            StandardizeKind::Address => &config.address,
+            StandardizeKind::Phone => &config.phone,
+            StandardizeKind::Email => &config.email,
+        }
+    }
+}
+
+// A single configured rule, by its position in the config
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RuleRef {
+    RandomName(usize),
+    Generator(usize),
+    // Standardization kind and the index of the table/field entry within it
+    Standardize(StandardizeKind, usize),
+    Mask(usize),
+    Date(usize),
+    Number(usize),
+    Redact(usize),
+    Token(usize),
+    Combination(usize),
+    NullFields(usize),
+}
+
+impl RuleRef {
+    // The rule's `where` conditions
+    pub fn conditions<'a>(&self, config: &'a Config) -> &'a [Condition] {
+        match *self {
+            RuleRef::RandomName(i) => &config.scrubbing.random_names[i].where_clause,
+            RuleRef::Generator(i) => &config.scrubbing.generators[i].where_clause,
+            RuleRef::Standardize(kind, i) => &kind.field(&config.standardize).fields[i].where_clause,
+            RuleRef::Mask(i) => &config.masking[i].where_clause,
+            RuleRef::Date(i) => &config.dates[i].where_clause,
+            RuleRef::Number(i) => &config.numbers[i].where_clause,
+            RuleRef::Redact(i) => &config.redaction[i].where_clause,
+            RuleRef::Token(i) => &config.tokenize[i].where_clause,
+            RuleRef::Combination(i) => &config.combination_fields[i].where_clause,
+            RuleRef::NullFields(i) => &config.null_fields[i].where_clause,
+        }
+    }
+
+    // How the rule is named in config, e.g. "scrubbing.random_names[0]"
+    pub fn describe(&self) -> String {
+        match self {
+            RuleRef::RandomName(i) => format!("scrubbing.random_names[{}]", i),
+            RuleRef::Generator(i) => format!("scrubbing.generators[{}]", i),
+            RuleRef::Standardize(kind, i) => format!("standardize.{}.fields[{}]", kind.name(), i),
+            RuleRef::Mask(i) => format!("masking[{}]", i),
+            RuleRef::Date(i) => format!("dates[{}]", i),
+            RuleRef::Number(i) => format!("numbers[{}]", i),
+            RuleRef::Redact(i) => format!("redaction[{}]", i),
+            RuleRef::Token(i) => format!("tokenize[{}]", i),
+            RuleRef::Combination(i) => format!("combination_fields[{}]", i),
+            RuleRef::NullFields(i) => format!("null_fields[{}]", i),
+        }
+    }
+}
+
+// A rule together with the columns of its table it reads and writes. A column a rule
+// both reads and writes is transformed in place (masking, dates, numbers, redaction,
+// tokens), so it runs after whichever rule replaced that column.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleStep {
+    pub rule: RuleRef,
+    pub reads: Vec<String>,
+    pub writes: Vec<String>,
+    // Rules with a `where` clause may share a column; the later one wins where both match
+    pub conditional: bool,
+}
+
+impl RuleStep {
+    pub fn transforms(&self, column: &str) -> bool {
+        self.reads.iter().any(|c| c == column) && self.writes.iter().any(|c| c == column)
+    }
+
+    fn replaces(&self, column: &str) -> bool {
+        !self.transforms(column) && self.writes.iter().any(|c| c == column)
+    }
+}
+
+// The order rules run in for each table. Rules keep their default phase order (random
+// names, generators, standardization, masking, dates, numbers, redaction, tokens,
+// combinations, nulled fields) unless one reads a column another writes, in which case
+// the writer always runs first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RulePlan {
+    tables: HashMap<String, Vec<RuleStep>>,
+}
+
+impl RulePlan {
+    pub fn build(config: &Config) -> Result<Self, Error> {
+        let mut rules: HashMap<String, Vec<RuleStep>> = HashMap::new();
+        let mut add = |table: &str, mut step: RuleStep| {
+            step.conditional = !step.rule.conditions(config).is_empty();
+            rules.entry(table.to_string()).or_default().push(step);
+        };
+
+        for (i, rule) in config.scrubbing.random_names.iter().enumerate() {
+            add(&rule.table, RuleStep {
+                rule: RuleRef::RandomName(i),
+                reads: rule.gender_field.iter().cloned().collect(),
+                writes: rule.fields.clone(),
+                conditional: false,
+            });
+        }
+        for (i, rule) in config.scrubbing.generators.iter().enumerate() {
+            add(&rule.table, RuleStep {
+                rule: RuleRef::Generator(i),
+                reads: rule.name_fields.clone(),
+                writes: rule.fields.clone(),
+                conditional: false,
+            });
+        }
+        for kind in StandardizeKind::ALL {
+            for (i, field) in kind.field(&config.standardize).fields.iter().enumerate() {
+                add(&field.table, RuleStep {
+                    rule: RuleRef::Standardize(kind, i),
+                    reads: Vec::new(),
+                    writes: vec![field.field.clone()],
+                    conditional: false,
+                });
+            }
+        }
+        for (i, rule) in config.masking.iter().enumerate() {
+            add(&rule.table, RuleStep {
+                rule: RuleRef::Mask(i),
+                reads: rule.fields.clone(),
+                writes: rule.fields.clone(),
+                conditional: false,
+            });
+        }
+        for (i, rule) in config.dates.iter().enumerate() {
+            // Dates are transformed in place; the entity key is read as exported
+            add(&rule.table, RuleStep {
+                rule: RuleRef::Date(i),
+                reads: rule.fields.clone(),
+                writes: rule.fields.clone(),
+                conditional: false,
+            });
+        }
+        for (i, rule) in config.numbers.iter().enumerate() {
+            add(&rule.table, RuleStep {
+                rule: RuleRef::Number(i),
+                reads: rule.fields.clone(),
+                writes: rule.fields.clone(),
+                conditional: false,
+            });
+        }
+        for (i, rule) in config.redaction.iter().enumerate() {
+            // Known values are replaced by their column's scrubbed value, so those columns go first
+            add(&rule.table, RuleStep {
+                rule: RuleRef::Redact(i),
+                reads: rule.fields.iter().chain(&rule.known_fields).cloned().collect(),
+                writes: rule.fields.clone(),
+                conditional: false,
+            });
+        }
+        for (i, rule) in config.tokenize.iter().enumerate() {
+            add(&rule.table, RuleStep {
+                rule: RuleRef::Token(i),
+                reads: rule.fields.clone(),
+                writes: rule.fields.clone(),
+                conditional: false,
+            });
+        }
+        for (i, rule) in config.combination_fields.iter().enumerate() {
+            // Values read before scrubbing or from another table don't depend on this table's rules
+            let mut reads: Vec<String> = rule.fields.iter()
+                .filter(|f| f.stage == SourceStage::AfterScrub && f.lookup.is_none())
+                .filter_map(|f| f.source_field.clone())
+                .collect();
+            if let Some(format) = &rule.format {
+                template::render(format, |name| {
+                    if !rule.fields.iter().any(|f| f.name() == Some(name)) {
+                        reads.push(name.to_string());
+                    }
+                    Ok(String::new())
+                })?;
+            }
+            add(&rule.table, RuleStep {
+                rule: RuleRef::Combination(i),
+                reads,
+                writes: vec![rule.target_field.clone()],
+                conditional: false,
+            });
+        }
+
+        for (i, rule) in config.null_fields.iter().enumerate() {
+            add(&rule.table, RuleStep {
+                rule: RuleRef::NullFields(i),
+                reads: Vec::new(),
+                writes: rule.fields.clone(),
+                conditional: false,
+            });
+        }
+
+        let mut tables = HashMap::new();
+        for (table, steps) in rules {
+            let ordered = order_steps(&table, steps)?;
+            tables.insert(table, ordered);
+        }
+        Ok(RulePlan { tables })
+    }
+
+    // Every rule with the table it applies to
+    pub fn rules(&self) -> impl Iterator<Item = (&str, RuleRef)> + '_ {
+        self.tables.iter().flat_map(|(table, steps)| steps.iter().map(move |step| (table.as_str(), step.rule)))
+    }
+
+    pub fn steps(&self, table_name: &str) -> &[RuleStep] {
+        self.tables.get(table_name).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    // Human readable rule order, one table per block
+    pub fn describe(&self) -> String {
+        let mut tables: Vec<_> = self.tables.keys().collect();
+        tables.sort();
+
+        let mut out = String::new();
+        for table in tables {
+            let _ = writeln!(out, "{}:", table);
+            for (n, step) in self.tables[table].iter().enumerate() {
+                let _ = writeln!(out, "  {}. {} -> {}", n + 1, step.rule.describe(), step.writes.join(", "));
+            }
+        }
+        out
+    }
+}
+
+// Topologically sort a table's rules, falling back to config order between independent rules
+fn order_steps(table: &str, steps: Vec<RuleStep>) -> Result<Vec<RuleStep>, Error> {
+    let mut before: Vec<Vec<usize>> = vec![Vec::new(); steps.len()];
+
+    for (j, step) in steps.iter().enumerate() {
+        for column in &step.writes {
+            if !step.replaces(column) {
+                continue;
+            }
+            let earlier = steps[..j].iter()
+                .position(|other| other.replaces(column) && !(other.conditional && step.conditional));
+            if let Some(i) = earlier {
+                return Err(Error::Config(format!(
+                    "Conflicting writes to {}.{}: {} and {} both replace it",
+                    table,
+                    column,
+                    steps[i].rule.describe(),
+                    step.rule.describe()
+                )));
+            }
+        }
+
+        // Conditional replacements of the same column keep their config order
+        for column in &step.writes {
+            for (i, other) in steps[..j].iter().enumerate() {
+                if step.replaces(column) && other.replaces(column) && !before[j].contains(&i) {
+                    before[j].push(i);
+                }
+            }
+        }
+
+        for column in &step.reads {
+            for (i, other) in steps.iter().enumerate() {
+                if i == j || !other.writes.contains(column) {
+                    continue;
+                }
+                // Transforms of the same column apply in config order, after the replacement
+                let depends = if step.transforms(column) {
+                    other.replaces(column) || (other.transforms(column) && i < j)
+                } else {
+                    true
+                };
+                if depends && !before[j].contains(&i) {
+                    before[j].push(i);
+                }
+            }
+        }
+    }
+
+    let mut placed = vec![false; steps.len()];
+    let mut order = Vec::with_capacity(steps.len());
+    while order.len() < steps.len() {
+        let next = (0..steps.len())
+            .find(|&j| !placed[j] && before[j].iter().all(|&i| placed[i]));
+        match next {
+            Some(j) => {
+                placed[j] = true;
+                order.push(j);
+            }
+            None => {
+                let cycle: Vec<String> = (0..steps.len())
+                    .filter(|&j| !placed[j])
+                    .map(|j| steps[j].rule.describe())
+                    .collect();
+                return Err(Error::Config(format!(
+                    "Rules for table '{}' depend on each other in a cycle: {}",
+                    table,
+                    cycle.join(", ")
+                )));
+            }
+        }
+    }
+
+    let mut steps: Vec<Option<RuleStep>> = steps.into_iter().map(Some).collect();
+    Ok(order.into_iter().filter_map(|j| steps[j].take()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> Config {
+        Config::from_str(include_str!("../../test_data/config.yml")).unwrap()
+    }
+
+    fn rules(plan: &RulePlan, table: &str) -> Vec<RuleRef> {
+        plan.steps(table).iter().map(|step| step.rule).collect()
+    }
+
+    #[test]
+    fn test_default_phase_order() -> Result<(), Error> {
+        let plan = RulePlan::build(&test_config())?;
+        assert_eq!(rules(&plan, "customers"), vec![
+            RuleRef::RandomName(0),
+            RuleRef::Standardize(StandardizeKind::Address, 0),
+            RuleRef::Standardize(StandardizeKind::Phone, 0),
+            RuleRef::Standardize(StandardizeKind::Email, 0),
+        ]);
+        assert_eq!(rules(&plan, "repositories"), vec![RuleRef::RandomName(3), RuleRef::Combination(1)]);
+        assert!(plan.steps("unknown").is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_combination_feeds_later_mask() -> Result<(), Error> {
+        let mut config = test_config();
+        config.masking = serde_yaml::from_str(r#"
+            - table: repositories
+              fields: [full_path]
+        "#)?;
+        let plan = RulePlan::build(&config)?;
+        assert_eq!(rules(&plan, "repositories"), vec![
+            RuleRef::RandomName(3),
+            RuleRef::Combination(1),
+            RuleRef::Mask(0),
+        ]);
+        assert!(plan.describe().contains("3. masking[0] -> full_path"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_generator_reads_combination() -> Result<(), Error> {
+        let mut config = test_config();
+        config.combination_fields = serde_yaml::from_str(r#"
+            - table: customers
+              fields:
+                - source_field: first_name
+                - literal: "x"
+              target_field: nickname
+        "#)?;
+        config.scrubbing.generators = serde_yaml::from_str(r#"
+            - table: customers
+              fields: [contact_email]
+              generator: email
+              name_fields: [nickname]
+        "#)?;
+        let plan = RulePlan::build(&config)?;
+        let order = rules(&plan, "customers");
+        let combination = order.iter().position(|r| *r == RuleRef::Combination(0)).unwrap();
+        let generator = order.iter().position(|r| *r == RuleRef::Generator(0)).unwrap();
+        assert!(combination < generator);
+        Ok(())
+    }
+
+    #[test]
+    fn test_conflicting_writes() {
+        let mut config = test_config();
+        config.masking = serde_yaml::from_str(r#"
+            - table: customers
+              fields: [email]
+        "#).unwrap();
+        // Masking transforms the standardized email, which is fine
+        assert!(RulePlan::build(&config).is_ok());
+
+        config.scrubbing.generators = serde_yaml::from_str(r#"
+            - table: customers
+              fields: [email]
+              generator: email
+        "#).unwrap();
+        let err = RulePlan::build(&config).unwrap_err();
+        assert!(matches!(err, Error::Config(ref msg) if msg.contains("customers.email")));
+    }
+
+    #[test]
+    fn test_conditional_rules_share_columns() -> Result<(), Error> {
+        let mut config = test_config();
+        config.standardize.email.fields.clear();
+        config.scrubbing.generators = serde_yaml::from_str(r#"
+            - table: customers
+              fields: [email]
+              generator: email
+              where: [{ field: id, not_in: [1001, 1002] }]
+            - table: customers
+              fields: [email]
+              generator: email
+              domain: example.org
+              where: [{ field: tenant, equals: internal }]
+        "#)?;
+        let plan = RulePlan::build(&config)?;
+        let order = rules(&plan, "customers");
+        assert!(order.contains(&RuleRef::Generator(0)) && order.contains(&RuleRef::Generator(1)));
+
+        // An unconditional rule still conflicts with a conditional one
+        config.standardize.email.fields = serde_yaml::from_str("[{ table: customers, field: email }]")?;
+        assert!(matches!(RulePlan::build(&config), Err(Error::Config(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_fields_conflict_with_scrubbing() {
+        let mut config = test_config();
+        config.null_fields = serde_yaml::from_str("[{ table: customers, fields: [notes] }]").unwrap();
+        let plan = RulePlan::build(&config).unwrap();
+        assert_eq!(rules(&plan, "customers").last(), Some(&RuleRef::NullFields(0)));
+
+        // Nulling a column another rule replaces is almost certainly a config mistake
+        config.null_fields = serde_yaml::from_str("[{ table: customers, fields: [phone] }]").unwrap();
+        assert!(matches!(RulePlan::build(&config), Err(Error::Config(ref msg)) if msg.contains("null_fields[0]")));
+    }
+
+    #[test]
+    fn test_dependency_cycle() {
+        let mut config = test_config();
+        config.combination_fields = serde_yaml::from_str(r#"
+            - table: projects
+              fields: [{ source_field: b }]
+              target_field: a
+            - table: projects
+              fields: [{ source_field: a }]
+              target_field: b
+        "#).unwrap();
+        assert!(matches!(RulePlan::build(&config), Err(Error::Config(_))));
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+
+use chrono::{Local, Months, NaiveDate};
+use regex::Regex;
+
+use crate::config::{Condition, Config};
+use crate::error::Error;
+use crate::processor::dates::{DateFormat, DatetimeLiteral};
+use crate::processor::sql::TableInfo;
+use crate::processor::unl::{find_field_index, UnlRow};
+
+// How date conditions read values, and the day they count back from. Built once per run so
+// every table sees the same day.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DateContext {
+    pub format: DateFormat,
+    pub reference_date: NaiveDate,
+}
+
+impl DateContext {
+    pub fn new(config: &Config) -> Result<Self, Error> {
+        let reference_date = match &config.export.reference_date {
+            Some(date) => NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map_err(|e| Error::Config(format!(
+                "Invalid export reference_date '{}', expected YYYY-MM-DD: {}",
+                date,
+                e
+            )))?,
+            None => Local::now().date_naive(),
+        };
+        Ok(DateContext { format: DateFormat::parse(&config.export.dbdate)?, reference_date })
+    }
+
+    // A DATE in the DBDATE format or a DATETIME literal
+    fn parse(&self, value: &str) -> Option<NaiveDate> {
+        self.format.parse_date(value).or_else(|| DatetimeLiteral::parse(value).map(|literal| literal.value.date()))
+    }
+}
+
+impl Default for DateContext {
+    fn default() -> Self {
+        DateContext { format: DateFormat::default(), reference_date: Local::now().date_naive() }
+    }
+}
+
+// A rule's `where` list with its regexes compiled and its fields found once
+#[derive(Clone, Debug, Default)]
+pub struct RowPredicate {
+    conditions: Vec<CompiledCondition>,
+    dates: DateContext,
+}
+
+#[derive(Clone, Debug)]
+struct CompiledCondition {
+    condition: Condition,
+    regex: Option<Regex>,
+    // Index of the field; None for tables not in the export, whose rows are never tested
+    index: Option<usize>,
+    // Dates before this hold for `older_than_years`
+    cutoff: Option<NaiveDate>,
+}
+
+impl RowPredicate {
+    // A condition without an operator would hold for every row, so a misspelled operator
+    // is an error rather than a rule applied everywhere
+    pub fn compile(
+        table_name: &str,
+        conditions: &[Condition],
+        table_info: &HashMap<String, TableInfo>,
+        dates: &DateContext,
+    ) -> Result<Self, Error> {
+        let conditions = conditions.iter()
+            .map(|condition| {
+                if !has_operator(condition) {
+                    return Err(Error::Config(format!(
+                        "Condition on field '{}' of table '{}' has no operator; use equals, not_equals, in, not_in, regex, min, max, is_null or older_than_years",
+                        condition.field,
+                        table_name
+                    )));
+                }
+                let index = match table_info.get(table_name) {
+                    Some(info) => Some(find_field_index(&condition.field, &info.fields).ok_or_else(|| Error::Config(format!(
+                        "Condition field '{}' not found in table '{}'",
+                        condition.field,
+                        table_name
+                    )))?),
+                    None => None,
+                };
+                let regex = condition.regex.as_deref()
+                    .map(|pattern| Regex::new(pattern).map_err(|e| Error::Config(format!(
+                        "Invalid regex '{}' for field '{}': {}",
+                        pattern,
+                        condition.field,
+                        e
+                    ))))
+                    .transpose()?;
+                let cutoff = condition.older_than_years
+                    .map(|years| dates.reference_date.checked_sub_months(Months::new(years.saturating_mul(12))).ok_or_else(|| Error::Config(format!(
+                        "older_than_years {} for field '{}' reaches before the earliest date",
+                        years,
+                        condition.field
+                    ))))
+                    .transpose()?;
+                Ok(CompiledCondition { condition: condition.clone(), regex, index, cutoff })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(RowPredicate { conditions, dates: dates.clone() })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
+    }
+
+    // True when every condition holds for the row; an empty predicate matches everything
+    pub fn matches(&self, row: &UnlRow) -> Result<bool, Error> {
+        for compiled in &self.conditions {
+            let idx = compiled.index.ok_or_else(|| Error::Processing(format!(
+                "Condition field '{}' was not found when the rules were built",
+                compiled.condition.field
+            )))?;
+            let value = row.get_field(idx).unwrap_or_default();
+            if !self.holds(compiled, value)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // Whether one condition holds. A non-empty value an `older_than_years` condition cannot
+    // read as a date is an error, so rows aren't silently kept or filtered.
+    fn holds(&self, compiled: &CompiledCondition, value: &str) -> Result<bool, Error> {
+        let condition = &compiled.condition;
+        if let Some(is_null) = condition.is_null {
+            if value.is_empty() != is_null {
+                return Ok(false);
+            }
+        }
+        if condition.equals.as_deref().is_some_and(|expected| value != expected) {
+            return Ok(false);
+        }
+        if condition.not_equals.as_deref().is_some_and(|unexpected| value == unexpected) {
+            return Ok(false);
+        }
+        if condition.in_list.as_ref().is_some_and(|list| !list.iter().any(|v| v == value)) {
+            return Ok(false);
+        }
+        if condition.not_in.as_ref().is_some_and(|list| list.iter().any(|v| v == value)) {
+            return Ok(false);
+        }
+        if compiled.regex.as_ref().is_some_and(|regex| !regex.is_match(value)) {
+            return Ok(false);
+        }
+        if condition.min.is_some() || condition.max.is_some() {
+            let number = match value.trim().parse::<f64>() {
+                Ok(number) => number,
+                Err(_) => return Ok(false),
+            };
+            if condition.min.is_some_and(|min| number < min) || condition.max.is_some_and(|max| number > max) {
+                return Ok(false);
+            }
+        }
+        if let Some(cutoff) = compiled.cutoff {
+            if value.trim().is_empty() {
+                return Ok(false);
+            }
+            let date = self.dates.parse(value).ok_or_else(|| Error::Processing(format!(
+                "Value '{}' of field '{}' is not a date in the export's DBDATE format or a DATETIME",
+                value,
+                condition.field
+            )))?;
+            if date >= cutoff {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+fn has_operator(condition: &Condition) -> bool {
+    condition.equals.is_some()
+        || condition.not_equals.is_some()
+        || condition.in_list.is_some()
+        || condition.not_in.is_some()
+        || condition.regex.is_some()
+        || condition.min.is_some()
+        || condition.max.is_some()
+        || condition.is_null.is_some()
+        || condition.older_than_years.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table_info() -> HashMap<String, TableInfo> {
+        let mut table_info = HashMap::new();
+        table_info.insert("customers".to_string(), TableInfo {
+            unl_file: "custo00100.unl".to_string(),
+            fields: vec!["id".to_string(), "email".to_string(), "balance".to_string(), "deleted_at".to_string()],
+            ..Default::default()
+        });
+        table_info
+    }
+
+    fn dates() -> DateContext {
+        DateContext { format: DateFormat::default(), reference_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() }
+    }
+
+    fn matches_with(dates: &DateContext, conditions: &str, line: &str) -> Result<bool, Error> {
+        let conditions: Vec<Condition> = serde_yaml::from_str(conditions).unwrap();
+        let predicate = RowPredicate::compile("customers", &conditions, &table_info(), dates).unwrap();
+        predicate.matches(&UnlRow::from_line(line).unwrap())
+    }
+
+    fn matches(conditions: &str, line: &str) -> bool {
+        matches_with(&dates(), conditions, line).unwrap()
+    }
+
+    #[test]
+    fn test_condition_operators() {
+        let row = "1001|jo@internal.test|12.50||";
+        assert!(matches("[]", row));
+        assert!(matches("[{ field: id, equals: 1001 }]", row));
+        assert!(!matches("[{ field: id, not_equals: 1001 }]", row));
+        assert!(matches("[{ field: id, in: [1001, 1002] }]", row));
+        assert!(!matches("[{ field: id, not_in: [1001, 1002] }]", row));
+        assert!(matches(r#"[{ field: email, regex: "@internal\\.test$" }]"#, row));
+        assert!(matches("[{ field: balance, min: 10, max: 20 }]", row));
+        assert!(!matches("[{ field: balance, min: 13 }]", row));
+        assert!(matches("[{ field: deleted_at, is_null: true }]", row));
+        assert!(!matches("[{ field: email, is_null: true }]", row));
+        // Every condition must hold
+        assert!(!matches("[{ field: id, equals: 1001 }, { field: balance, max: 10 }]", row));
+    }
+
+    #[test]
+    fn test_older_than_years() {
+        assert!(matches("[{ field: deleted_at, older_than_years: 5 }]", "1|a||01/15/1990|"));
+        assert!(!matches("[{ field: deleted_at, older_than_years: 5 }]", "1|a||2999-01-15|"));
+        assert!(!matches("[{ field: deleted_at, older_than_years: 5 }]", "1|a|||"));
+        // Counted back from the reference date, not today
+        assert!(matches("[{ field: deleted_at, older_than_years: 5 }]", "1|a||12/31/2018|"));
+        assert!(!matches("[{ field: deleted_at, older_than_years: 5 }]", "1|a||01/01/2019|"));
+        assert!(matches("[{ field: deleted_at, older_than_years: 5 }]", "1|a||2018-06-01 10:30:00|"));
+        // Values are read with the export's DBDATE
+        let dmy = DateContext { format: DateFormat::parse("DMY4/").unwrap(), ..dates() };
+        let older = "[{ field: deleted_at, older_than_years: 5 }]";
+        assert!(matches_with(&dmy, older, "1|a||31/12/2018|").unwrap());
+        assert!(!matches_with(&dmy, older, "1|a||02/01/2019|").unwrap());
+        assert!(matches!(matches_with(&dates(), older, "1|a||yesterday|"), Err(Error::Processing(_))));
+    }
+
+    #[test]
+    fn test_numeric_bounds_skip_non_numbers() {
+        assert!(!matches("[{ field: balance, min: 0 }]", "1|a|||"));
+        assert!(!matches("[{ field: balance, max: 100 }]", "1|a|n/a||"));
+    }
+
+    #[test]
+    fn test_predicate_errors() {
+        let compile = |conditions: &str| {
+            let conditions: Vec<Condition> = serde_yaml::from_str(conditions).unwrap();
+            RowPredicate::compile("customers", &conditions, &table_info(), &dates())
+        };
+        assert!(matches!(compile("[{ field: email, regex: '(' }]"), Err(Error::Config(_))));
+        assert!(matches!(compile("[{ field: missing, is_null: true }]"), Err(Error::Config(_))));
+        // A misspelled operator leaves the condition without one
+        assert!(matches!(compile("[{ field: id, equls: 1001 }]"), Err(Error::Config(_))));
+        assert!(compile("[{ field: id, equals: 1001 }]").is_ok());
+    }
+}
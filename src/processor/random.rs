@@ -1,13 +1,57 @@
+use std::collections::HashMap;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
-
-pub fn generate_random_name(adjectives: &[String], nouns: &[String]) -> String {
-    let mut rng = thread_rng();
-    let noun = &"happy".to_string();
-    let adjective = &"default".to_string();
-    let adjective = adjectives.choose(&mut rng).unwrap_or(noun);
-    let noun = nouns.choose(&mut rng).unwrap_or(adjective);
-    format!("{}-{}", adjective, noun)
+use rand::{Rng, SeedableRng};
+
+// Word lists beyond the global adjectives/nouns, loaded once and shared by all rules
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WordLists {
+    // Per-rule word list files, keyed by the path as written in the config
+    pub files: HashMap<String, Vec<String>>,
+    // Named dictionaries from word_lists.dictionaries
+    pub dictionaries: HashMap<String, Vec<String>>,
+}
+
+// Build an RNG that is fully determined by the export seed and the given parts,
+// so the same input always produces the same output across runs
+pub fn seeded_rng(seed: u64, parts: &[&str]) -> StdRng {
+    StdRng::seed_from_u64(stable_hash(seed, parts))
+}
+
+// FNV-1a over the seed and parts; unlike DefaultHasher this is stable across Rust releases
+pub fn stable_hash(seed: u64, parts: &[&str]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = OFFSET;
+    for byte in seed.to_le_bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(PRIME);
+    }
+    for part in parts {
+        for byte in part.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+        // Separator so ["ab", "c"] and ["a", "bc"] hash differently
+        hash ^= 0xff;
+        hash = hash.wrapping_mul(PRIME);
+    }
+    hash
+}
+
+pub fn generate_random_name<R: Rng>(adjectives: &[String], nouns: &[String], rng: &mut R) -> Option<String> {
+    let adjective = adjectives.choose(rng)?;
+    let noun = nouns.choose(rng)?;
+    Some(format!("{}-{}", adjective, noun))
+}
+
+// Pick one word from each list and join them, e.g. ["red", "owl"] -> "red-owl"
+pub fn generate_from_dictionaries<R: Rng>(dictionaries: &[&[String]], rng: &mut R) -> Option<String> {
+    let words = dictionaries.iter()
+        .map(|words| words.choose(rng).map(String::as_str))
+        .collect::<Option<Vec<_>>>()?;
+    Some(words.join("-"))
 }
 
 #[cfg(test)]
@@ -18,18 +62,48 @@ mod tests {
     fn test_random_name_generation() {
         let adjectives = vec!["happy".to_string(), "quick".to_string()];
         let nouns = vec!["fox".to_string(), "dog".to_string()];
-        
-        let name = generate_random_name(&adjectives, &nouns);
-        
+
+        let name = generate_random_name(&adjectives, &nouns, &mut rand::thread_rng()).unwrap();
+
         // Check that the name contains a hyphen (our separator)
         assert!(name.contains('-'));
-        
+
         // Split the name and verify parts
         let parts: Vec<&str> = name.split('-').collect();
         assert_eq!(parts.len(), 2);
-        
+
         // Verify that the parts came from our input vectors
         assert!(adjectives.contains(&parts[0].to_string()));
         assert!(nouns.contains(&parts[1].to_string()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_empty_word_lists() {
+        let words = vec!["happy".to_string()];
+        assert!(generate_random_name(&[], &words, &mut rand::thread_rng()).is_none());
+        assert!(generate_random_name(&words, &[], &mut rand::thread_rng()).is_none());
+    }
+
+    #[test]
+    fn test_generate_from_dictionaries() {
+        let colors = vec!["red".to_string()];
+        let shapes = vec!["circle".to_string()];
+        let sizes = vec!["big".to_string()];
+        let name = generate_from_dictionaries(&[&colors, &shapes, &sizes], &mut rand::thread_rng());
+        assert_eq!(name.as_deref(), Some("red-circle-big"));
+        assert!(generate_from_dictionaries(&[&colors, &[]], &mut rand::thread_rng()).is_none());
+    }
+
+    #[test]
+    fn test_seeded_rng_is_deterministic() {
+        let mut a = seeded_rng(42, &["customers", "phone", "555-1234"]);
+        let mut b = seeded_rng(42, &["customers", "phone", "555-1234"]);
+        let mut c = seeded_rng(43, &["customers", "phone", "555-1234"]);
+        let a: u64 = a.gen();
+        assert_eq!(a, b.gen::<u64>());
+        assert_ne!(a, c.gen::<u64>());
+
+        // Part boundaries matter
+        assert_ne!(stable_hash(1, &["ab", "c"]), stable_hash(1, &["a", "bc"]));
+    }
+}
@@ -0,0 +1,203 @@
+use std::cmp::Reverse;
+
+use aho_corasick::{AhoCorasick, Match};
+use regex::Regex;
+
+use crate::config::RedactionPattern;
+use crate::error::Error;
+
+// What was found in the text: one of the patterns, or a known value of the named column
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Found<'a> {
+    Pattern(RedactionPattern),
+    Known(&'a str),
+}
+
+// Placeholder written in place of what was found, e.g. "[EMAIL]" or "[FIRST_NAME]"
+pub fn placeholder(found: Found<'_>) -> String {
+    match found {
+        Found::Pattern(RedactionPattern::Email) => "[EMAIL]".to_string(),
+        Found::Pattern(RedactionPattern::Phone) => "[PHONE]".to_string(),
+        Found::Pattern(RedactionPattern::Url) => "[URL]".to_string(),
+        Found::Known(field) => format!("[{}]", field.to_ascii_uppercase()),
+    }
+}
+
+// One row's values of the known columns, as (field, value), searched for in one pass.
+// Very short values such as initials would match all over ordinary prose, so are left out.
+#[derive(Clone, Debug)]
+pub struct KnownValues<'a> {
+    values: Vec<(&'a str, &'a str)>,
+    searcher: Option<AhoCorasick>,
+}
+
+impl<'a> KnownValues<'a> {
+    pub fn new(known: &[(&'a str, &'a str)]) -> Result<Self, Error> {
+        let values: Vec<(&str, &str)> = known.iter()
+            .map(|(field, value)| (*field, value.trim()))
+            .filter(|(_, value)| value.chars().count() >= 3)
+            .collect();
+        let searcher = if values.is_empty() {
+            None
+        } else {
+            let searcher = AhoCorasick::builder()
+                .ascii_case_insensitive(true)
+                .build(values.iter().map(|(_, value)| value))
+                .map_err(|e| Error::Processing(format!("Failed to build known value search: {}", e)))?;
+            Some(searcher)
+        };
+        Ok(KnownValues { values, searcher })
+    }
+
+    // The field whose value the text is, ignoring case
+    fn field_of(&self, text: &str) -> Option<&'a str> {
+        self.values.iter().find(|(_, value)| value.eq_ignore_ascii_case(text)).map(|(field, _)| *field)
+    }
+
+    // Replace values found as whole words, longest first; among equal values the first
+    // field wins
+    fn replace(&self, text: &str, replace: &mut dyn FnMut(Found<'_>, &str) -> String) -> String {
+        let searcher = match &self.searcher {
+            Some(searcher) => searcher,
+            None => return text.to_string(),
+        };
+        let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let mut found: Vec<Match> = searcher.find_overlapping_iter(text)
+            .filter(|m| !is_word(text[..m.start()].chars().next_back()) && !is_word(text[m.end()..].chars().next()))
+            .collect();
+        found.sort_by_key(|m| (Reverse(m.len()), m.start(), m.pattern()));
+        let mut chosen: Vec<Match> = Vec::new();
+        for m in found {
+            if chosen.iter().all(|c| m.end() <= c.start() || m.start() >= c.end()) {
+                chosen.push(m);
+            }
+        }
+        chosen.sort_by_key(|m| m.start());
+
+        let mut result = String::with_capacity(text.len());
+        let mut last = 0;
+        for m in chosen {
+            result.push_str(&text[last..m.start()]);
+            result.push_str(&replace(Found::Known(self.values[m.pattern().as_usize()].0), &text[m.range()]));
+            last = m.end();
+        }
+        result.push_str(&text[last..]);
+        result
+    }
+}
+
+// Finds emails, phone numbers and URLs in free text
+#[derive(Clone, Debug)]
+pub struct Redactor {
+    patterns: Regex,
+}
+
+impl Redactor {
+    pub fn new() -> Result<Self, Error> {
+        // One alternation so text replaced by one pattern is never rescanned by another
+        let patterns = Regex::new(concat!(
+            r"(?P<url>\b(?:https?://|www\.)[^\s<>]+[^\s<>.,;:!?)])",
+            r"|(?P<email>\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b)",
+            r"|(?P<phone>(?:\+?1[-.\s]?)?(?:\(\d{3}\)\s?|\b\d{3}[-.\s]?)\d{3}[-.\s]?\d{4}\b)",
+        )).map_err(|e| Error::Processing(format!("Invalid regex: {}", e)))?;
+        Ok(Redactor { patterns })
+    }
+
+    // Replace any of `patterns`, then known values as whole words ignoring case, longest
+    // first. A pattern match equal to a known value counts as that value. `replace` gives
+    // the text to write for each find.
+    pub fn redact(
+        &self,
+        text: &str,
+        patterns: &[RedactionPattern],
+        known: &KnownValues<'_>,
+        mut replace: impl FnMut(Found<'_>, &str) -> String,
+    ) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut last = 0;
+        for caps in self.patterns.captures_iter(text) {
+            let whole = caps.get(0).map(|m| m.range()).unwrap_or_default();
+            let pattern = [
+                ("url", RedactionPattern::Url),
+                ("email", RedactionPattern::Email),
+                ("phone", RedactionPattern::Phone),
+            ]
+                .into_iter()
+                .find_map(|(name, pattern)| caps.name(name).map(|_| pattern));
+            let matched = &text[whole.clone()];
+            let found = match known.field_of(matched) {
+                Some(field) => Found::Known(field),
+                None => match pattern {
+                    Some(pattern) if patterns.contains(&pattern) => Found::Pattern(pattern),
+                    // Disabled patterns are scanned for known values like any other text
+                    _ => continue,
+                },
+            };
+            result.push_str(&known.replace(&text[last..whole.start], &mut replace));
+            result.push_str(&replace(found, matched));
+            last = whole.end;
+        }
+        result.push_str(&known.replace(&text[last..], &mut replace));
+        result
+    }
+}
+
+// Cut text to at most `width` bytes without splitting a character
+pub fn truncate(text: &mut String, width: usize) {
+    if text.len() > width {
+        let end = (0..=width).rev().find(|&i| text.is_char_boundary(i)).unwrap_or(0);
+        text.truncate(end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholders(text: &str, patterns: &[RedactionPattern], known: &[(&str, &str)]) -> String {
+        Redactor::new().unwrap().redact(text, patterns, &KnownValues::new(known).unwrap(), |found, _| placeholder(found))
+    }
+
+    #[test]
+    fn test_patterns() {
+        let all = [RedactionPattern::Email, RedactionPattern::Phone, RedactionPattern::Url];
+        assert_eq!(
+            placeholders("Mail jo.doe@corp.com or call (503) 555-1234, see https://corp.com/u/jo.", &all, &[]),
+            "Mail [EMAIL] or call [PHONE], see [URL]."
+        );
+        assert_eq!(placeholders("Call 503.555.1234 or +1 503 555 1234", &all, &[]), "Call [PHONE] or [PHONE]");
+        // Only the configured patterns are replaced
+        assert_eq!(placeholders("jo@corp.com 503-555-1234", &[RedactionPattern::Phone], &[]), "jo@corp.com [PHONE]");
+        assert_eq!(placeholders("Order 12345 shipped", &all, &[]), "Order 12345 shipped");
+    }
+
+    #[test]
+    fn test_known_values() {
+        let known = [("first_name", "John"), ("last_name", "Doe"), ("middle", "J")];
+        assert_eq!(
+            placeholders("JOHN said Doe's dog Johnny is ok, J.", &[], &known),
+            "[FIRST_NAME] said [LAST_NAME]'s dog Johnny is ok, J."
+        );
+
+        let redactor = Redactor::new().unwrap();
+        let known = KnownValues::new(&known).unwrap();
+        let pseudonyms = redactor.redact("Ask John", &[], &known, |_, matched| format!("<{}>", matched.len()));
+        assert_eq!(pseudonyms, "Ask <4>");
+
+        // A longer value wins over one it contains, and values inside longer words are kept
+        let known = [("city", "Ann"), ("company", "Ann Arbor Tools")];
+        assert_eq!(
+            placeholders("ann arbor tools of Ann Arbor, Annex", &[], &known),
+            "[COMPANY] of [CITY] Arbor, Annex"
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut text = "naïve".to_string();
+        truncate(&mut text, 3);
+        assert_eq!(text, "na");
+        truncate(&mut text, 10);
+        assert_eq!(text, "na");
+    }
+}
@@ -0,0 +1,991 @@
+use std::fmt;
+
+use crate::error::Error;
+
+// Lexer and parser for the schema file dbexport writes. Statements are read whole, whatever
+// their keyword case, comments or storage clauses; the ones the processor has no use for are
+// kept as `Other`. Malformed input fails with the line and column it was found at.
+
+// Byte offsets into the schema text
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TokenKind {
+    // Identifiers and keywords, as written
+    Word(String),
+    // "Quoted" identifiers
+    Quoted(String),
+    // 'String' literals
+    Text(String),
+    Number(String),
+    Symbol(char),
+    // The text between { and }, where dbexport writes its table and unload headers
+    Comment(String),
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    span: Span,
+}
+
+// An object name with its owner when one is given, e.g. "informix".customers
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Name {
+    pub owner: Option<String>,
+    pub name: String,
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.owner {
+            Some(owner) => write!(f, "\"{}\".{}", owner, self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+// A number in a header comment and where it is, so it can be rewritten
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Count {
+    pub value: usize,
+    pub span: Span,
+}
+
+// { TABLE "informix".customers row size = 429 number of columns = 6 index size = 0 }
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableHeader {
+    pub name: Name,
+    pub columns: Count,
+    pub span: Span,
+}
+
+// { unload file name = custo00100.unl number of rows = 73 }
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnloadHeader {
+    pub file: String,
+    pub rows: Count,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Schema {
+    pub statements: Vec<Statement>,
+    pub table_headers: Vec<TableHeader>,
+    pub unload_headers: Vec<UnloadHeader>,
+}
+
+impl Schema {
+    pub fn tables(&self) -> impl Iterator<Item = &CreateTable> {
+        self.statements.iter().filter_map(|statement| match statement {
+            Statement::CreateTable(table) => Some(table),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Statement {
+    CreateTable(CreateTable),
+    CreateIndex(CreateIndex),
+    AlterTable(AlterTable),
+    CreateView(CreateView),
+    CreateSynonym(CreateSynonym),
+    CreateSequence(Name),
+    CreateTrigger(CreateTrigger),
+    CreateProcedure(CreateProcedure),
+    // Grants, update statistics and anything else, by leading keyword, e.g. "create role"
+    Other(String),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CreateTable {
+    pub name: Name,
+    pub columns: Vec<ColumnDef>,
+    // Table-level constraints; column-level ones are on their column
+    pub constraints: Vec<Constraint>,
+    // The unload header written for the table, if any
+    pub unload: Option<UnloadHeader>,
+    // Each comma-separated item of the column list, with the whitespace before it
+    pub items: Vec<Span>,
+}
+
+impl CreateTable {
+    pub fn all_constraints(&self) -> impl Iterator<Item = &Constraint> {
+        self.constraints.iter().chain(self.columns.iter().flat_map(|column| &column.constraints))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ColumnDef {
+    pub name: String,
+    // Type as declared with whitespace collapsed, e.g. "set(varchar(20) not null)"
+    pub data_type: String,
+    pub not_null: bool,
+    pub default: Option<String>,
+    pub constraints: Vec<Constraint>,
+    // The column's item in the column list
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Constraint {
+    pub name: Option<Name>,
+    pub kind: ConstraintKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConstraintKind {
+    PrimaryKey(Vec<String>),
+    // unique and distinct
+    Unique(Vec<String>),
+    // An empty parent column list means the parent's primary key
+    ForeignKey { columns: Vec<String>, parent: Name, parent_columns: Vec<String> },
+    Check,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreateIndex {
+    pub name: Name,
+    pub table: Name,
+    // Indexed columns, including those used by functional index expressions
+    pub columns: Vec<String>,
+    pub unique: bool,
+}
+
+// Only the constraints added; other changes are skipped
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlterTable {
+    pub table: Name,
+    pub constraints: Vec<Constraint>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreateView {
+    pub name: Name,
+    pub columns: Vec<String>,
+    pub query: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreateSynonym {
+    pub name: Name,
+    pub target: Name,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreateTrigger {
+    pub name: Name,
+    pub table: Name,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreateProcedure {
+    pub name: Name,
+    pub function: bool,
+}
+
+// Words that end a column's type and start its default or constraints
+const COLUMN_KEYWORDS: &[&str] = &["default", "not", "null", "constraint", "primary", "unique", "distinct", "references", "check"];
+
+// Words that start a table-level constraint rather than a column
+const CONSTRAINT_KEYWORDS: &[&str] = &["primary", "unique", "distinct", "foreign", "check", "constraint"];
+
+pub fn parse(sql: &str) -> Result<Schema, Error> {
+    let (comments, tokens): (Vec<Token>, Vec<Token>) = tokenize(sql, Span { start: 0, end: sql.len() })?
+        .into_iter()
+        .partition(|token| matches!(token.kind, TokenKind::Comment(_)));
+
+    let mut schema = Schema::default();
+    for comment in &comments {
+        parse_header(sql, comment, &mut schema);
+    }
+
+    let mut parser = Parser { sql, tokens, pos: 0 };
+    let mut previous_table = 0;
+    while let Some(token) = parser.peek() {
+        let start = token.span.start;
+        if parser.eat_symbol(';') {
+            continue;
+        }
+        let mut statement = parser.statement()?;
+        if let Statement::CreateTable(table) = &mut statement {
+            // The unload header comes between the previous create table and this one
+            table.unload = schema.unload_headers.iter()
+                .rev()
+                .find(|header| header.span.start < start && header.span.start >= previous_table)
+                .cloned();
+            previous_table = start;
+        }
+        schema.statements.push(statement);
+    }
+    Ok(schema)
+}
+
+// Parse a column's type and constraints as written after its name
+pub fn parse_column(name: &str, definition: &str) -> Result<ColumnDef, Error> {
+    let tokens = tokenize(definition, Span { start: 0, end: definition.len() })?;
+    let mut parser = Parser { sql: definition, tokens, pos: 0 };
+    let mut column = parser.column_definition(name.to_string(), 0)?;
+    column.span = Span { start: 0, end: definition.len() };
+    Ok(column)
+}
+
+fn error_at(sql: &str, offset: usize, message: &str) -> Error {
+    let before = &sql[..offset.min(sql.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+    Error::Processing(format!("Schema error at line {}, column {}: {}", line, column, message))
+}
+
+fn tokenize(sql: &str, range: Span) -> Result<Vec<Token>, Error> {
+    let chars: Vec<(usize, char)> = sql[range.start..range.end].char_indices()
+        .map(|(i, c)| (range.start + i, c))
+        .collect();
+    let offset = |i: usize| chars.get(i).map_or(range.end, |&(offset, _)| offset);
+    let is = |i: usize, f: &dyn Fn(char) -> bool| chars.get(i).is_some_and(|&(_, c)| f(c));
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while let Some(&(start, c)) = chars.get(i) {
+        let next = chars.get(i + 1).map(|&(_, c)| c);
+        let kind = match c {
+            _ if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '-' if next == Some('-') => {
+                while is(i, &|c| c != '\n') {
+                    i += 1;
+                }
+                continue;
+            }
+            '/' if next == Some('*') => {
+                let end = sql[start + 2..range.end].find("*/")
+                    .ok_or_else(|| error_at(sql, start, "unterminated comment"))?;
+                let end = start + 2 + end + 2;
+                while offset(i) < end {
+                    i += 1;
+                }
+                continue;
+            }
+            '{' => {
+                let end = start + sql[start..range.end].find('}')
+                    .ok_or_else(|| error_at(sql, start, "unterminated { comment"))?;
+                while offset(i) <= end {
+                    i += 1;
+                }
+                TokenKind::Comment(sql[start + 1..end].to_string())
+            }
+            '\'' | '"' => {
+                // Quotes are escaped by doubling them
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None if c == '\'' => return Err(error_at(sql, start, "unterminated string")),
+                        None => return Err(error_at(sql, start, "unterminated quoted identifier")),
+                        Some(&(_, quote)) if quote == c && is(i + 1, &|n| n == c) => {
+                            text.push(c);
+                            i += 2;
+                        }
+                        Some(&(_, quote)) if quote == c => {
+                            i += 1;
+                            break;
+                        }
+                        Some(&(_, other)) => {
+                            text.push(other);
+                            i += 1;
+                        }
+                    }
+                }
+                if c == '\'' { TokenKind::Text(text) } else { TokenKind::Quoted(text) }
+            }
+            _ if c.is_ascii_digit() || (c == '.' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                while is(i, &|c| c.is_ascii_alphanumeric() || c == '.') {
+                    i += 1;
+                }
+                TokenKind::Number(sql[start..offset(i)].to_string())
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                while is(i, &|c| c.is_alphanumeric() || c == '_' || c == '$') {
+                    i += 1;
+                }
+                TokenKind::Word(sql[start..offset(i)].to_string())
+            }
+            _ => {
+                i += 1;
+                TokenKind::Symbol(c)
+            }
+        };
+        tokens.push(Token { kind, span: Span { start, end: offset(i) } });
+    }
+    Ok(tokens)
+}
+
+// Record a table or unload header; other comments, and headers missing their counts, are ignored
+fn parse_header(sql: &str, comment: &Token, schema: &mut Schema) {
+    let base = comment.span.start + 1;
+    let text = match &comment.kind {
+        TokenKind::Comment(text) => text,
+        _ => return,
+    };
+    let words = words(text);
+    let word = |i: usize| words.get(i).map_or("", |&(_, word)| word);
+    let count = |label: &[&str]| -> Option<Count> {
+        let i = (0..words.len()).find(|&i| label.iter().enumerate().all(|(j, l)| word(i + j).eq_ignore_ascii_case(l)))?;
+        let (offset, value) = words.get(i + label.len())?;
+        let value = value.parse().ok()?;
+        let start = base + offset;
+        Some(Count { value, span: Span { start, end: start + word(i + label.len()).len() } })
+    };
+
+    if word(0).eq_ignore_ascii_case("table") && words.len() > 1 {
+        let start = base + words[1].0;
+        let name = tokenize(sql, Span { start, end: start + words[1].1.len() })
+            .ok()
+            .and_then(|tokens| Parser { sql, tokens, pos: 0 }.name().ok());
+        if let (Some(name), Some(columns)) = (name, count(&["number", "of", "columns", "="])) {
+            schema.table_headers.push(TableHeader { name, columns, span: comment.span });
+        }
+    } else if ["unload", "file", "name", "="].iter().enumerate().all(|(i, w)| word(i).eq_ignore_ascii_case(w)) {
+        if let (file, Some(rows)) = (word(4), count(&["number", "of", "rows", "="])) {
+            if !file.is_empty() {
+                schema.unload_headers.push(UnloadHeader { file: file.to_string(), rows, span: comment.span });
+            }
+        }
+    }
+}
+
+// Whitespace-separated words and their offsets
+fn words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(i),
+            (Some(s), true) => {
+                words.push((s, &text[s..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+struct Parser<'a> {
+    sql: &'a str,
+    // Tokens without comments
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    // The next token's keyword in lowercase, or "" if it isn't a word
+    fn keyword_at(&self, ahead: usize) -> String {
+        match self.tokens.get(self.pos + ahead).map(|token| &token.kind) {
+            Some(TokenKind::Word(word)) => word.to_ascii_lowercase(),
+            _ => String::new(),
+        }
+    }
+
+    fn keyword(&self) -> String {
+        self.keyword_at(0)
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        let found = self.keyword() == word;
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn is_symbol(&self, symbol: char) -> bool {
+        self.peek().is_some_and(|token| token.kind == TokenKind::Symbol(symbol))
+    }
+
+    fn eat_symbol(&mut self, symbol: char) -> bool {
+        let found = self.is_symbol(symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_word(&mut self, word: &str) -> Result<(), Error> {
+        if self.eat_word(word) {
+            return Ok(());
+        }
+        Err(self.error(&format!("expected '{}'", word)))
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<Span, Error> {
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::Symbol(symbol) => {
+                let span = token.span;
+                self.pos += 1;
+                Ok(span)
+            }
+            _ => Err(self.error(&format!("expected '{}'", symbol))),
+        }
+    }
+
+    // An error at the next token, saying what was found there
+    fn error(&self, expected: &str) -> Error {
+        match self.peek() {
+            Some(token) => error_at(self.sql, token.span.start, &format!(
+                "{}, found '{}'",
+                expected,
+                &self.sql[token.span.start..token.span.end]
+            )),
+            None => error_at(self.sql, self.sql.len(), &format!("{}, found end of file", expected)),
+        }
+    }
+
+    // Unquoted identifiers are case-insensitive, so they are kept in lowercase
+    fn identifier(&mut self) -> Result<String, Error> {
+        let identifier = match self.peek().map(|token| &token.kind) {
+            Some(TokenKind::Word(word)) => word.to_ascii_lowercase(),
+            Some(TokenKind::Quoted(name)) => name.clone(),
+            _ => return Err(self.error("expected a name")),
+        };
+        self.pos += 1;
+        Ok(identifier)
+    }
+
+    // [database[@server]:][owner.]name; the database and server are dropped
+    fn name(&mut self) -> Result<Name, Error> {
+        let mut first = self.identifier()?;
+        if self.eat_symbol('@') {
+            self.identifier()?;
+        }
+        if self.eat_symbol(':') {
+            first = self.identifier()?;
+        }
+        if self.eat_symbol('.') {
+            let name = self.identifier()?;
+            return Ok(Name { owner: Some(first), name });
+        }
+        Ok(Name { owner: None, name: first })
+    }
+
+    // A parenthesized list of column names, each optionally followed by asc or desc
+    fn column_list(&mut self) -> Result<Vec<String>, Error> {
+        self.expect_symbol('(')?;
+        let mut columns = Vec::new();
+        loop {
+            columns.push(self.identifier()?);
+            if !self.eat_word("asc") {
+                self.eat_word("desc");
+            }
+            if !self.eat_symbol(',') {
+                self.expect_symbol(')')?;
+                return Ok(columns);
+            }
+        }
+    }
+
+    // Skip a parenthesized group, whatever it holds
+    fn skip_parens(&mut self) -> Result<(), Error> {
+        let open = self.expect_symbol('(')?;
+        let mut depth = 1;
+        while depth > 0 {
+            let token = self.peek().ok_or_else(|| error_at(self.sql, open.start, "unclosed '('"))?;
+            match token.kind {
+                TokenKind::Symbol('(') => depth += 1,
+                TokenKind::Symbol(')') => depth -= 1,
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        Ok(())
+    }
+
+    // Skip to the end of the statement, consuming its semicolon. Returns the offset
+    // where the skipped text ends.
+    fn skip_statement(&mut self) -> Result<usize, Error> {
+        let mut end = self.peek().map_or(self.sql.len(), |token| token.span.start);
+        while let Some(token) = self.peek() {
+            match token.kind {
+                TokenKind::Symbol(';') => {
+                    self.pos += 1;
+                    break;
+                }
+                TokenKind::Symbol('(') => self.skip_parens()?,
+                TokenKind::Symbol(')') => return Err(self.error("unbalanced parentheses")),
+                _ => self.pos += 1,
+            }
+            end = self.tokens[self.pos - 1].span.end;
+        }
+        Ok(end)
+    }
+
+    fn statement(&mut self) -> Result<Statement, Error> {
+        if self.eat_word("create") {
+            return self.create();
+        }
+        if self.keyword() == "alter" && self.keyword_at(1) == "table" {
+            self.pos += 2;
+            return self.alter_table();
+        }
+        let keyword = self.keyword();
+        if keyword.is_empty() {
+            return Err(self.error("expected a statement"));
+        }
+        self.skip_statement()?;
+        Ok(Statement::Other(keyword))
+    }
+
+    fn create(&mut self) -> Result<Statement, Error> {
+        // Modifiers that don't change what kind of object is created
+        let mut unique = false;
+        loop {
+            match self.keyword().as_str() {
+                "unique" | "distinct" => unique = true,
+                "temp" | "raw" | "standard" | "public" | "private" | "dba" | "cluster" => {}
+                _ => break,
+            }
+            self.pos += 1;
+        }
+
+        let kind = self.keyword();
+        if kind.is_empty() {
+            return Err(self.error("expected what to create"));
+        }
+        self.pos += 1;
+        if self.keyword() == "if" && self.keyword_at(1) == "not" && self.keyword_at(2) == "exists" {
+            self.pos += 3;
+        }
+
+        let statement = match kind.as_str() {
+            "table" => Statement::CreateTable(self.create_table()?),
+            "index" => {
+                let name = self.name()?;
+                self.expect_word("on")?;
+                let table = self.name()?;
+                let columns = self.index_columns()?;
+                Statement::CreateIndex(CreateIndex { name, table, columns, unique })
+            }
+            "view" => {
+                let name = self.name()?;
+                let columns = if self.is_symbol('(') { self.column_list()? } else { Vec::new() };
+                self.expect_word("as")?;
+                let start = self.peek().map_or(self.sql.len(), |token| token.span.start);
+                let end = self.skip_statement()?;
+                let query = self.sql[start..end.max(start)].split_whitespace().collect::<Vec<_>>().join(" ");
+                return Ok(Statement::CreateView(CreateView { name, columns, query }));
+            }
+            "synonym" => {
+                let name = self.name()?;
+                self.expect_word("for")?;
+                Statement::CreateSynonym(CreateSynonym { name, target: self.name()? })
+            }
+            "sequence" => Statement::CreateSequence(self.name()?),
+            "trigger" => {
+                let name = self.name()?;
+                // insert, delete, select or update [of columns], then on the table
+                while !self.eat_word("on") {
+                    if self.peek().is_none() || self.is_symbol(';') {
+                        return Err(self.error(&format!("expected 'on' in trigger {}", name)));
+                    }
+                    self.pos += 1;
+                }
+                Statement::CreateTrigger(CreateTrigger { name, table: self.name()? })
+            }
+            "procedure" | "function" => {
+                let start = self.peek().map_or(self.sql.len(), |token| token.span.start);
+                let name = self.name()?;
+                // The body has statements of its own, so it ends at end procedure or end function
+                loop {
+                    if self.peek().is_none() {
+                        return Err(error_at(self.sql, start, &format!("expected 'end {}' closing {}", kind, name)));
+                    }
+                    if self.keyword() == "end" && ["procedure", "function"].contains(&self.keyword_at(1).as_str()) {
+                        self.pos += 2;
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                Statement::CreateProcedure(CreateProcedure { name, function: kind == "function" })
+            }
+            _ => Statement::Other(format!("create {}", kind)),
+        };
+        self.skip_statement()?;
+        Ok(statement)
+    }
+
+    fn create_table(&mut self) -> Result<CreateTable, Error> {
+        let name = self.name()?;
+        let open = self.expect_symbol('(')?;
+        let mut table = CreateTable { name, ..Default::default() };
+
+        let mut item_start = open.end;
+        loop {
+            if CONSTRAINT_KEYWORDS.contains(&self.keyword().as_str()) {
+                let constraint = self.table_constraint()?;
+                table.constraints.push(constraint);
+            } else {
+                let name = self.identifier()?;
+                let column = self.column_definition(name, item_start)?;
+                table.columns.push(column);
+            }
+
+            let separator = match self.peek() {
+                Some(token) if matches!(token.kind, TokenKind::Symbol(',' | ')')) => token.clone(),
+                Some(_) => return Err(self.error("expected ',' or ')'")),
+                None => return Err(error_at(self.sql, open.start, &format!("column list of {} is not closed", table.name))),
+            };
+            self.pos += 1;
+            let span = Span { start: item_start, end: separator.span.start };
+            if let Some(column) = table.columns.last_mut().filter(|column| column.span.start == item_start) {
+                column.span = span;
+            }
+            table.items.push(span);
+            item_start = separator.span.end;
+            if separator.kind == TokenKind::Symbol(')') {
+                break;
+            }
+        }
+
+        // Storage clauses such as extent sizes, dbspaces, fragmentation and lock mode are
+        // skipped with the rest of the statement
+        Ok(table)
+    }
+
+    // A column's type, default and constraints, up to the comma or parenthesis after it
+    fn column_definition(&mut self, name: String, start: usize) -> Result<ColumnDef, Error> {
+        let type_start = self.peek().map_or(self.sql.len(), |token| token.span.start);
+        let mut type_end = type_start;
+        let mut depth = 0;
+        while let Some(token) = self.peek() {
+            match &token.kind {
+                TokenKind::Symbol('(') => depth += 1,
+                TokenKind::Symbol(')') if depth == 0 => break,
+                TokenKind::Symbol(')') => depth -= 1,
+                TokenKind::Symbol(',') if depth == 0 => break,
+                TokenKind::Word(word) if depth == 0 && COLUMN_KEYWORDS.contains(&word.to_ascii_lowercase().as_str()) => break,
+                _ => {}
+            }
+            type_end = token.span.end;
+            self.pos += 1;
+        }
+        if type_end == type_start {
+            return Err(self.error(&format!("expected a type for column {}", name)));
+        }
+
+        let mut column = ColumnDef {
+            data_type: self.sql[type_start..type_end].split_whitespace().collect::<Vec<_>>().join(" "),
+            span: Span { start, end: type_end },
+            ..Default::default()
+        };
+
+        // The constraint a following `constraint name` belongs to
+        let mut named: Option<usize> = None;
+        loop {
+            let kind = match self.keyword().as_str() {
+                _ if self.peek().is_none() || self.is_symbol(',') || self.is_symbol(')') => break,
+                "default" => {
+                    self.pos += 1;
+                    column.default = Some(self.default_value()?);
+                    None
+                }
+                "not" => {
+                    self.pos += 1;
+                    column.not_null |= self.eat_word("null");
+                    None
+                }
+                "primary" => {
+                    self.pos += 1;
+                    self.expect_word("key")?;
+                    Some(ConstraintKind::PrimaryKey(vec![name.clone()]))
+                }
+                "unique" | "distinct" => {
+                    self.pos += 1;
+                    Some(ConstraintKind::Unique(vec![name.clone()]))
+                }
+                "references" => {
+                    self.pos += 1;
+                    let parent = self.name()?;
+                    let parent_columns = if self.is_symbol('(') { self.column_list()? } else { Vec::new() };
+                    Some(ConstraintKind::ForeignKey { columns: vec![name.clone()], parent, parent_columns })
+                }
+                "check" => {
+                    self.pos += 1;
+                    self.skip_parens()?;
+                    Some(ConstraintKind::Check)
+                }
+                "constraint" => {
+                    self.pos += 1;
+                    let constraint_name = self.name()?;
+                    if let Some(constraint) = named.and_then(|i| column.constraints.get_mut(i)) {
+                        constraint.name = Some(constraint_name);
+                    }
+                    named = None;
+                    continue;
+                }
+                // on delete cascade, enabled, filtering and the like
+                _ if self.is_symbol('(') => {
+                    self.skip_parens()?;
+                    None
+                }
+                _ => {
+                    self.pos += 1;
+                    None
+                }
+            };
+            named = kind.map(|kind| {
+                column.constraints.push(Constraint { name: None, kind });
+                column.constraints.len() - 1
+            });
+        }
+
+        column.name = name;
+        Ok(column)
+    }
+
+    fn default_value(&mut self) -> Result<String, Error> {
+        let token = self.peek().cloned();
+        let value = match token.map(|token| token.kind) {
+            Some(TokenKind::Text(text)) => text,
+            Some(TokenKind::Number(number) | TokenKind::Word(number)) => number,
+            Some(TokenKind::Symbol(sign @ ('-' | '+'))) => {
+                self.pos += 1;
+                match self.peek().map(|token| &token.kind) {
+                    Some(TokenKind::Number(number)) => format!("{}{}", sign, number),
+                    _ => return Err(self.error("expected a number")),
+                }
+            }
+            _ => return Err(self.error("expected a default value")),
+        };
+        self.pos += 1;
+        Ok(value)
+    }
+
+    // A table-level constraint, named before or after its definition
+    fn table_constraint(&mut self) -> Result<Constraint, Error> {
+        let mut name = None;
+        if self.eat_word("constraint") {
+            name = Some(self.name()?);
+        }
+
+        let kind = match self.keyword().as_str() {
+            "primary" => {
+                self.pos += 1;
+                self.expect_word("key")?;
+                ConstraintKind::PrimaryKey(self.column_list()?)
+            }
+            "unique" | "distinct" => {
+                self.pos += 1;
+                ConstraintKind::Unique(self.column_list()?)
+            }
+            "foreign" => {
+                self.pos += 1;
+                self.expect_word("key")?;
+                let columns = self.column_list()?;
+                self.expect_word("references")?;
+                let parent = self.name()?;
+                let parent_columns = if self.is_symbol('(') { self.column_list()? } else { Vec::new() };
+                ConstraintKind::ForeignKey { columns, parent, parent_columns }
+            }
+            "check" => {
+                self.pos += 1;
+                self.skip_parens()?;
+                ConstraintKind::Check
+            }
+            _ => return Err(self.error("expected a constraint")),
+        };
+
+        // on delete cascade, enabled, filtering and the like, up to the next item
+        while !(self.peek().is_none() || self.is_symbol(',') || self.is_symbol(')') || self.is_symbol(';')) {
+            if self.eat_word("constraint") {
+                name = Some(self.name()?);
+            } else if self.is_symbol('(') {
+                self.skip_parens()?;
+            } else {
+                self.pos += 1;
+            }
+        }
+        Ok(Constraint { name, kind })
+    }
+
+    fn alter_table(&mut self) -> Result<Statement, Error> {
+        let table = self.name()?;
+        let mut constraints = Vec::new();
+        while !(self.peek().is_none() || self.is_symbol(';')) {
+            if self.keyword() == "add" && self.keyword_at(1) == "constraint" {
+                self.pos += 2;
+                if self.eat_symbol('(') {
+                    loop {
+                        constraints.push(self.table_constraint()?);
+                        if !self.eat_symbol(',') {
+                            break;
+                        }
+                    }
+                    self.expect_symbol(')')?;
+                } else {
+                    constraints.push(self.table_constraint()?);
+                }
+            } else if self.is_symbol('(') {
+                self.skip_parens()?;
+            } else {
+                self.pos += 1;
+            }
+        }
+        self.eat_symbol(';');
+        Ok(Statement::AlterTable(AlterTable { table, constraints }))
+    }
+
+    // Names in an index's column list, skipping asc, desc and function names
+    fn index_columns(&mut self) -> Result<Vec<String>, Error> {
+        let open = self.expect_symbol('(')?;
+        let mut columns = Vec::new();
+        let mut depth = 1;
+        while depth > 0 {
+            let token = self.peek().ok_or_else(|| error_at(self.sql, open.start, "unclosed '('"))?;
+            match &token.kind {
+                TokenKind::Symbol('(') => depth += 1,
+                TokenKind::Symbol(')') => depth -= 1,
+                TokenKind::Word(word) if ["asc", "desc"].contains(&word.to_ascii_lowercase().as_str()) => {}
+                TokenKind::Word(_) | TokenKind::Quoted(_) if !matches!(
+                    self.tokens.get(self.pos + 1).map(|token| &token.kind),
+                    Some(TokenKind::Symbol('('))
+                ) => {
+                    columns.push(self.identifier()?);
+                    continue;
+                }
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        Ok(columns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn informix(name: &str) -> Name {
+        Name { owner: Some("informix".to_string()), name: name.to_string() }
+    }
+
+    #[test]
+    fn test_parse_statements() -> Result<(), Error> {
+        let sql = r#"
+            { DATABASE stores  delimiter | }
+            grant dba to "informix";
+
+            { TABLE "appuser".orders row size = 80 number of columns = 3 index size = 9 }
+            { unload file name = order00101.unl number of rows = 12 }
+            CREATE TABLE "appuser".Orders
+            (
+                -- Comments and mixed case keywords are fine
+                order_num SERIAL NOT NULL,
+                customer_num INTEGER REFERENCES "informix".customers CONSTRAINT "informix".r101_1,
+                note /* free text */ VARCHAR(40) DEFAULT 'it''s',
+                UNIQUE (customer_num, note) CONSTRAINT "informix".u101_2
+            )
+            fragment by expression
+                (order_num < 1000) in dbs1,
+                remainder in dbs2;
+
+            create unique index "informix".ix_orders on "appuser".orders (order_num desc) using btree;
+            create index "informix".ix_lower on "appuser".orders (lower(note));
+            alter table "appuser".orders add constraint (primary key (order_num)
+                constraint "informix".u101_1, check (order_num > 0));
+            create view "informix".big_orders (num) as
+                select order_num from "appuser".orders where order_num > 100;
+            create public synonym "informix".ords for stores@prod:"appuser".orders;
+            create sequence "informix".order_seq increment by 1 start with 1;
+            create trigger "informix".orders_upd update of note on "appuser".orders
+                for each row (execute procedure "informix".log_change());
+            create procedure "informix".log_change()
+                define x integer;
+                let x = 1;
+                if x > 0 then let x = 2; end if;
+            end procedure;
+            update statistics;
+        "#;
+
+        let schema = parse(sql)?;
+        let table = schema.tables().next().unwrap();
+        assert_eq!(table.name, Name { owner: Some("appuser".to_string()), name: "orders".to_string() });
+        assert_eq!(table.unload.as_ref().map(|u| (u.file.as_str(), u.rows.value)), Some(("order00101.unl", 12)));
+        assert_eq!(table.columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["order_num", "customer_num", "note"]);
+        assert_eq!((table.columns[0].data_type.as_str(), table.columns[0].not_null), ("SERIAL", true));
+        assert_eq!(table.columns[2].data_type, "VARCHAR(40)");
+        assert_eq!(table.columns[2].default.as_deref(), Some("it's"));
+        assert_eq!(table.columns[1].constraints, vec![Constraint {
+            name: Some(informix("r101_1")),
+            kind: ConstraintKind::ForeignKey {
+                columns: vec!["customer_num".to_string()],
+                parent: informix("customers"),
+                parent_columns: vec![],
+            },
+        }]);
+        assert_eq!(table.constraints, vec![Constraint {
+            name: Some(informix("u101_2")),
+            kind: ConstraintKind::Unique(vec!["customer_num".to_string(), "note".to_string()]),
+        }]);
+        assert_eq!(table.items.len(), 4);
+        assert!(sql[table.items[1].start..table.items[1].end].trim().starts_with("customer_num INTEGER"));
+
+        let header = &schema.table_headers[0];
+        assert_eq!((header.name.to_string(), header.columns.value), ("\"appuser\".orders".to_string(), 3));
+        assert_eq!(&sql[header.columns.span.start..header.columns.span.end], "3");
+
+        let statements = &schema.statements;
+        assert_eq!(statements.len(), 11);
+        assert_eq!(statements[0], Statement::Other("grant".to_string()));
+        assert_eq!(statements[2], Statement::CreateIndex(CreateIndex {
+            name: informix("ix_orders"),
+            table: Name { owner: Some("appuser".to_string()), name: "orders".to_string() },
+            columns: vec!["order_num".to_string()],
+            unique: true,
+        }));
+        assert!(matches!(&statements[3], Statement::CreateIndex(index) if index.columns == vec!["note"] && !index.unique));
+        assert!(matches!(&statements[4], Statement::AlterTable(alter) if alter.constraints.len() == 2
+            && alter.constraints[0].name == Some(informix("u101_1"))
+            && alter.constraints[1].kind == ConstraintKind::Check));
+        assert!(matches!(&statements[5], Statement::CreateView(view) if view.columns == vec!["num"]
+            && view.query == "select order_num from \"appuser\".orders where order_num > 100"));
+        assert!(matches!(&statements[6], Statement::CreateSynonym(synonym) if synonym.target.name == "orders"));
+        assert_eq!(statements[7], Statement::CreateSequence(informix("order_seq")));
+        assert!(matches!(&statements[8], Statement::CreateTrigger(trigger) if trigger.table.name == "orders"));
+        assert!(matches!(&statements[9], Statement::CreateProcedure(procedure) if !procedure.function));
+        assert_eq!(statements[10], Statement::Other("update".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let message = |sql: &str| parse(sql).unwrap_err().to_string();
+        assert_eq!(
+            message("create table t\n(\n  id integer,\n  name varchar(10) default 'open\n);"),
+            "Processing error: Schema error at line 4, column 28: unterminated string"
+        );
+        assert_eq!(
+            message("create table t (id integer, name varchar(10)"),
+            "Processing error: Schema error at line 1, column 16: column list of t is not closed"
+        );
+        assert_eq!(
+            message("create table \"informix\".t id integer;"),
+            "Processing error: Schema error at line 1, column 27: expected '(', found 'id'"
+        );
+        assert_eq!(
+            message("create table t (id);"),
+            "Processing error: Schema error at line 1, column 19: expected a type for column id, found ')'"
+        );
+        assert!(message("{ TABLE t }\n create procedure p() let x = 1;").contains("line 2, column 19: expected 'end procedure' closing p"));
+    }
+}
@@ -0,0 +1,364 @@
+use std::collections::{HashMap, HashSet};
+
+use regex::Regex;
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::processor::sql::TableInfo;
+
+// Where a rule keeps its column names: a list, a single field, or none at all
+enum Fields<R> {
+    None,
+    List(fn(&mut R) -> &mut Vec<String>),
+    Single(fn(&mut R) -> &mut String),
+}
+
+// The bare table name of a schema key, which is owner.name when owners share a table name
+fn bare_name(key: &str) -> &str {
+    key.rsplit('.').next().unwrap_or(key)
+}
+
+// Resolve a table selector from the config, e.g. customers, appuser.customers or
+// "appuser".customers, to the key of its table. None if no exported table matches; a bare
+// name that matches tables of several owners is an error.
+pub fn resolve_table(selector: &str, table_info: &HashMap<String, TableInfo>) -> Result<Option<String>, Error> {
+    if table_info.contains_key(selector) {
+        return Ok(Some(selector.to_string()));
+    }
+
+    let (owner, name) = match selector.rsplit_once('.') {
+        Some((owner, name)) => (Some(owner.trim().trim_matches('"')), name.trim().trim_matches('"')),
+        None => (None, selector.trim().trim_matches('"')),
+    };
+    let mut matches: Vec<(&String, &TableInfo)> = table_info.iter()
+        .filter(|(key, info)| bare_name(key) == name && (owner.is_none() || info.owner.as_deref() == owner))
+        .collect();
+    matches.sort_by(|a, b| a.0.cmp(b.0));
+
+    match matches.as_slice() {
+        [] => Ok(None),
+        [(key, _)] => Ok(Some(key.to_string())),
+        _ => Err(Error::Config(format!(
+            "Table '{}' is ambiguous: it could be {}; write it as owner.table",
+            selector,
+            matches.iter()
+                .map(|(_, info)| format!("{}.{}", info.owner.as_deref().unwrap_or_default(), name))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        ))),
+    }
+}
+
+// A glob such as "*_hist" or a regex between slashes such as "/.*_email$/"; None for plain names
+fn pattern(selector: &str) -> Result<Option<Regex>, Error> {
+    let source = match selector.strip_prefix('/').and_then(|s| s.strip_suffix('/')).filter(|s| !s.is_empty()) {
+        Some(regex) => regex.to_string(),
+        None if selector.contains(['*', '?']) => {
+            format!("^{}$", regex::escape(selector).replace(r"\*", ".*").replace(r"\?", "."))
+        }
+        None => return Ok(None),
+    };
+    Regex::new(&source)
+        .map(Some)
+        .map_err(|e| Error::Config(format!("Invalid selector '{}': {}", selector, e)))
+}
+
+// Keys of the tables a pattern matches, by key or as owner.table
+fn matching_tables(pattern: &Regex, table_info: &HashMap<String, TableInfo>) -> Vec<String> {
+    let mut tables: Vec<String> = table_info.iter()
+        .filter(|(key, info)| {
+            let qualified = info.owner.as_ref().map(|owner| format!("{}.{}", owner, bare_name(key)));
+            pattern.is_match(key) || qualified.is_some_and(|name| pattern.is_match(&name))
+        })
+        .map(|(key, _)| key.clone())
+        .collect();
+    tables.sort();
+    tables
+}
+
+// Replace column patterns with the table's matching columns, in schema order. True if the list
+// had a pattern.
+fn expand_fields(fields: &mut Vec<String>, columns: &[String]) -> Result<bool, Error> {
+    let mut expanded: Vec<String> = Vec::new();
+    let mut patterned = false;
+    for field in fields.drain(..) {
+        let matched = match pattern(&field)? {
+            Some(pattern) => {
+                patterned = true;
+                columns.iter().filter(|column| pattern.is_match(column)).cloned().collect()
+            }
+            None => vec![field],
+        };
+        for column in matched {
+            if !expanded.contains(&column) {
+                expanded.push(column);
+            }
+        }
+    }
+    *fields = expanded;
+    Ok(patterned)
+}
+
+// Expand a list of table names, e.g. excluded_tables
+fn expand_names(section: &str, names: &mut Vec<String>, table_info: &HashMap<String, TableInfo>, report: &mut Vec<String>) -> Result<(), Error> {
+    let mut expanded: Vec<String> = Vec::new();
+    for name in names.drain(..) {
+        let matched = match pattern(&name)? {
+            Some(pattern) => {
+                let tables = matching_tables(&pattern, table_info);
+                report.push(format!("{} '{}' -> {}", section, name, describe(&tables)));
+                tables
+            }
+            None => vec![name],
+        };
+        for table in matched {
+            if !expanded.contains(&table) {
+                expanded.push(table);
+            }
+        }
+    }
+    *names = expanded;
+    Ok(())
+}
+
+// Give each rule one copy per table its table selector matches, with its field selectors
+// expanded against that table. Excluded tables a pattern matches get no copy. Copies left with
+// no fields are dropped.
+fn expand_rules<R: Clone>(
+    section: &str,
+    rules: &mut Vec<R>,
+    table: fn(&mut R) -> &mut String,
+    fields: Fields<R>,
+    table_info: &HashMap<String, TableInfo>,
+    excluded: &HashSet<String>,
+    report: &mut Vec<String>,
+) -> Result<(), Error> {
+    let mut expanded = Vec::new();
+    for (i, mut rule) in std::mem::take(rules).into_iter().enumerate() {
+        let selector = table(&mut rule).clone();
+        let table_pattern = pattern(&selector)?;
+        let (tables, skipped): (Vec<String>, Vec<String>) = match &table_pattern {
+            Some(pattern) => matching_tables(pattern, table_info).into_iter().partition(|key| !excluded.contains(key)),
+            None => (vec![selector.clone()], Vec::new()),
+        };
+
+        let mut patterned = table_pattern.is_some();
+        let mut copies = Vec::new();
+        for key in tables {
+            let columns = match resolve_table(&key, table_info)? {
+                Some(key) => table_info[&key].fields.clone(),
+                None => Vec::new(),
+            };
+            let mut copy = rule.clone();
+            *table(&mut copy) = key.clone();
+            let written = match &fields {
+                Fields::None => vec![copy],
+                Fields::List(list) => {
+                    patterned |= expand_fields(list(&mut copy), &columns)?;
+                    if list(&mut copy).is_empty() { Vec::new() } else { vec![copy] }
+                }
+                Fields::Single(field) => {
+                    let mut names = vec![field(&mut copy).clone()];
+                    patterned |= expand_fields(&mut names, &columns)?;
+                    names.into_iter()
+                        .map(|name| {
+                            let mut copy = copy.clone();
+                            *field(&mut copy) = name;
+                            copy
+                        })
+                        .collect()
+                }
+            };
+            for mut copy in written {
+                let columns = match &fields {
+                    Fields::None => String::new(),
+                    Fields::List(list) => format!(" ({})", list(&mut copy).join(", ")),
+                    Fields::Single(field) => format!(" ({})", field(&mut copy)),
+                };
+                copies.push((format!("{}[{}] {}{}", section, expanded.len() + copies.len(), table(&mut copy), columns), copy));
+            }
+        }
+
+        if patterned {
+            let targets: Vec<String> = copies.iter().map(|(target, _)| target.clone()).collect();
+            let mut line = format!("{}[{}] '{}' -> {}", section, i, selector, describe(&targets));
+            if !skipped.is_empty() {
+                line.push_str(&format!(" (skipped excluded {})", skipped.join(", ")));
+            }
+            report.push(line);
+        }
+        expanded.extend(copies.into_iter().map(|(_, copy)| copy));
+    }
+    *rules = expanded;
+    Ok(())
+}
+
+fn describe(matches: &[String]) -> String {
+    if matches.is_empty() {
+        return "nothing".to_string();
+    }
+    matches.join(", ")
+}
+
+// Expand the config's glob and regex selectors into the tables and columns they match, then
+// point the remaining table names at their tables. Returns a line per expanded selector, with
+// rules numbered as they are after expansion.
+pub fn resolve_config(config: &mut Config, table_info: &HashMap<String, TableInfo>) -> Result<Vec<String>, Error> {
+    let mut report = Vec::new();
+    expand_names("excluded_tables", &mut config.excluded_tables, table_info, &mut report)?;
+    expand_names("verification.record_counts.sample_tables", &mut config.verification.record_counts.sample_tables, table_info, &mut report)?;
+    let mut excluded = HashSet::new();
+    for name in &config.excluded_tables {
+        excluded.extend(resolve_table(name, table_info)?);
+    }
+
+    let info = table_info;
+    let excluded = &excluded;
+    let scrubbing = &mut config.scrubbing;
+    expand_rules("scrubbing.random_names", &mut scrubbing.random_names, |r| &mut r.table, Fields::List(|r| &mut r.fields), info, excluded, &mut report)?;
+    expand_rules("scrubbing.generators", &mut scrubbing.generators, |r| &mut r.table, Fields::List(|r| &mut r.fields), info, excluded, &mut report)?;
+    let standardize = &mut config.standardize;
+    for (name, field) in [("address", &mut standardize.address), ("phone", &mut standardize.phone), ("email", &mut standardize.email)] {
+        let section = format!("standardize.{}.fields", name);
+        expand_rules(&section, &mut field.fields, |r| &mut r.table, Fields::Single(|r| &mut r.field), info, excluded, &mut report)?;
+    }
+    expand_rules("combination_fields", &mut config.combination_fields, |r| &mut r.table, Fields::None, info, excluded, &mut report)?;
+    expand_rules("masking", &mut config.masking, |r| &mut r.table, Fields::List(|r| &mut r.fields), info, excluded, &mut report)?;
+    expand_rules("row_filters", &mut config.row_filters, |r| &mut r.table, Fields::None, info, excluded, &mut report)?;
+    expand_rules("dates", &mut config.dates, |r| &mut r.table, Fields::List(|r| &mut r.fields), info, excluded, &mut report)?;
+    expand_rules("numbers", &mut config.numbers, |r| &mut r.table, Fields::List(|r| &mut r.fields), info, excluded, &mut report)?;
+    expand_rules("redaction", &mut config.redaction, |r| &mut r.table, Fields::List(|r| &mut r.fields), info, excluded, &mut report)?;
+    expand_rules("tokenize", &mut config.tokenize, |r| &mut r.table, Fields::List(|r| &mut r.fields), info, excluded, &mut report)?;
+    expand_rules("key_remapping.tables", &mut config.key_remapping.tables, |r| &mut r.table, Fields::None, info, excluded, &mut report)?;
+    expand_rules("null_fields", &mut config.null_fields, |r| &mut r.table, Fields::List(|r| &mut r.fields), info, excluded, &mut report)?;
+    expand_rules("drop_columns", &mut config.drop_columns, |r| &mut r.table, Fields::List(|r| &mut r.fields), info, excluded, &mut report)?;
+    expand_rules("synthetic_rows", &mut config.synthetic_rows, |r| &mut r.table, Fields::None, info, excluded, &mut report)?;
+    expand_rules("large_objects", &mut config.large_objects, |r| &mut r.table, Fields::List(|r| &mut r.fields), info, excluded, &mut report)?;
+
+    resolve_config_tables(config, table_info)?;
+    Ok(report)
+}
+
+// Rewrite the config's table names to the keys of the tables they select. Names no exported
+// table matches are left for the rules' own checks to report.
+fn resolve_config_tables(config: &mut Config, table_info: &HashMap<String, TableInfo>) -> Result<(), Error> {
+    for table in config.table_names_mut() {
+        if let Some(key) = resolve_table(table, table_info)? {
+            *table = key;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::sql::parse_sql_file;
+
+    const SQL: &str = r#"
+        { TABLE "informix".customers row size = 4 number of columns = 1 index size = 0 }
+        { unload file name = custo00100.unl number of rows = 1 }
+        create table "informix".customers (id serial not null, primary key (id));
+        { TABLE "appuser".customers row size = 4 number of columns = 1 index size = 0 }
+        { unload file name = custo00101.unl number of rows = 1 }
+        create table "appuser".customers (id serial not null, primary key (id));
+        { TABLE "appuser".orders row size = 8 number of columns = 2 index size = 0 }
+        { unload file name = order00102.unl number of rows = 1 }
+        create table "appuser".orders (id serial not null, customer_id integer);
+        alter table "appuser".orders add constraint (foreign key (customer_id)
+            references "appuser".customers constraint "appuser".r102_1);
+    "#;
+
+    #[test]
+    fn test_owners_keep_tables_apart() -> Result<(), Error> {
+        let tables = parse_sql_file(SQL)?;
+        let mut keys: Vec<&String> = tables.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["appuser.customers", "informix.customers", "orders"]);
+        assert_eq!(tables["orders"].owner.as_deref(), Some("appuser"));
+        assert_eq!(tables["orders"].foreign_keys[0].parent_table, "appuser.customers");
+
+        assert_eq!(resolve_table("orders", &tables)?.as_deref(), Some("orders"));
+        assert_eq!(resolve_table("appuser.orders", &tables)?.as_deref(), Some("orders"));
+        assert_eq!(resolve_table("\"appuser\".customers", &tables)?.as_deref(), Some("appuser.customers"));
+        assert_eq!(resolve_table("informix.orders", &tables)?, None);
+        assert_eq!(resolve_table("missing", &tables)?, None);
+
+        let err = resolve_table("customers", &tables).unwrap_err();
+        assert!(matches!(err, Error::Config(ref msg) if msg.contains("appuser.customers or informix.customers")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_config_tables() -> Result<(), Error> {
+        let tables = parse_sql_file(SQL)?;
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.excluded_tables = vec!["informix.customers".to_string(), "training_config".to_string()];
+        config.masking = serde_yaml::from_str("[{ table: appuser.orders, fields: [customer_id] }]")?;
+        config.scrubbing.random_names.clear();
+        config.scrubbing.generators.clear();
+        config.verification.record_counts.sample_tables.clear();
+        config.combination_fields.clear();
+        for standardize in [&mut config.standardize.address, &mut config.standardize.phone, &mut config.standardize.email] {
+            standardize.fields.clear();
+        }
+        resolve_config_tables(&mut config, &tables)?;
+        assert_eq!(config.excluded_tables, vec!["informix.customers", "training_config"]);
+        assert_eq!(config.masking[0].table, "orders");
+
+        config.null_fields = serde_yaml::from_str("[{ table: customers, fields: [id] }]")?;
+        assert!(matches!(resolve_config_tables(&mut config, &tables), Err(Error::Config(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_patterns_expand_against_schema() -> Result<(), Error> {
+        let tables = parse_sql_file(r#"
+            { TABLE "informix".customers row size = 4 number of columns = 3 index size = 0 }
+            { unload file name = custo00100.unl number of rows = 1 }
+            create table "informix".customers (id serial not null, email varchar(50), alt_email varchar(50));
+            { TABLE "informix".orders_hist row size = 4 number of columns = 2 index size = 0 }
+            { unload file name = order00101.unl number of rows = 1 }
+            create table "informix".orders_hist (id serial not null, contact_email varchar(50));
+            { TABLE "informix".prices_hist row size = 4 number of columns = 1 index size = 0 }
+            { unload file name = price00102.unl number of rows = 1 }
+            create table "informix".prices_hist (id serial not null);
+            { TABLE "informix".suppliers row size = 4 number of columns = 2 index size = 0 }
+            { unload file name = suppl00103.unl number of rows = 1 }
+            create table "informix".suppliers (id serial not null, contact_email varchar(50));
+        "#)?;
+
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.excluded_tables = vec!["training_config".to_string(), "*_hist".to_string()];
+        config.masking = serde_yaml::from_str(r#"
+            - { table: "*", fields: ["/email$/"] }
+            - { table: customers, fields: [id] }
+        "#)?;
+        config.standardize.email.fields = serde_yaml::from_str(r#"[{ table: "informix.*", field: "/.*_email$/" }]"#)?;
+
+        let report = resolve_config(&mut config, &tables)?;
+        assert_eq!(config.excluded_tables, vec!["training_config", "orders_hist", "prices_hist"]);
+        let masking: Vec<(&str, Vec<String>)> = config.masking.iter().map(|r| (r.table.as_str(), r.fields.clone())).collect();
+        assert_eq!(masking, vec![
+            ("customers", vec!["email".to_string(), "alt_email".to_string()]),
+            ("suppliers", vec!["contact_email".to_string()]),
+            ("customers", vec!["id".to_string()]),
+        ]);
+        let standardized: Vec<(&str, &str)> = config.standardize.email.fields.iter()
+            .map(|f| (f.table.as_str(), f.field.as_str()))
+            .collect();
+        assert_eq!(standardized, vec![("customers", "alt_email"), ("suppliers", "contact_email")]);
+
+        assert!(report.contains(&"excluded_tables '*_hist' -> orders_hist, prices_hist".to_string()));
+        assert!(report.contains(&"masking[0] '*' -> masking[0] customers (email, alt_email), masking[1] suppliers (contact_email) (skipped excluded orders_hist, prices_hist)".to_string()));
+
+        // Named explicitly, an excluded table keeps its rule
+        config.masking = serde_yaml::from_str("[{ table: orders_hist, fields: [contact_email] }]")?;
+        resolve_config(&mut config, &tables)?;
+        assert_eq!(config.masking[0].table, "orders_hist");
+
+        config.masking = serde_yaml::from_str(r#"[{ table: "/[/", fields: [id] }]"#)?;
+        assert!(matches!(resolve_config(&mut config, &tables), Err(Error::Config(_))));
+        Ok(())
+    }
+}
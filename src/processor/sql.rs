@@ -1,62 +1,331 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 use crate::error::Error;
-use regex::Regex;
+use crate::processor::collections::CollectionKind;
+use crate::processor::schema::{self, ColumnDef, ConstraintKind, Name, Schema, Span, Statement};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct TableInfo {
+    // Owner from the create table statement, e.g. "informix"
+    pub owner: Option<String>,
     pub unl_file: String,
+    // Row count the unload header declares
+    pub declared_rows: usize,
     pub fields: Vec<String>,
+    // Declared type of each field, in the same order
+    pub columns: Vec<ColumnInfo>,
+    // Column lists of primary keys, unique constraints and unique indexes
+    pub unique_keys: Vec<Vec<String>>,
+    pub primary_key: Vec<String>,
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ColumnInfo {
+    pub name: String,
+    // Type as declared, e.g. "decimal(10,2)" or "datetime year to second"
+    pub data_type: String,
+    pub not_null: bool,
+    pub default: Option<String>,
+}
+
+impl ColumnInfo {
+    // Lowercase type name without size or qualifiers, e.g. "decimal"
+    pub fn base_type(&self) -> String {
+        self.data_type
+            .split(|c: char| c.is_whitespace() || c == '(')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+    }
+
+    // The element type of a SET, MULTISET or LIST column, e.g. varchar(20) for set(varchar(20) not null)
+    pub fn element(&self) -> Option<ColumnInfo> {
+        CollectionKind::from_type(&self.base_type())?;
+        let inner = self.data_type.split_once('(')?.1.trim_end().strip_suffix(')')?;
+        schema::parse_column(&self.name, inner).ok().map(|column| ColumnInfo::from(&column))
+    }
+
+    // Maximum length in bytes of a character column, e.g. 20 for "varchar(20)" or 1 for "char"
+    pub fn width(&self) -> Option<usize> {
+        let size = self.data_type.split_once('(')
+            .and_then(|(_, rest)| rest.split([',', ')']).next())
+            .and_then(|size| size.trim().parse().ok());
+        match self.base_type().as_str() {
+            "char" | "character" | "nchar" => Some(size.unwrap_or(1)),
+            "varchar" | "nvarchar" => Some(size.unwrap_or(1)),
+            "lvarchar" => Some(size.unwrap_or(2048)),
+            _ => None,
+        }
+    }
+}
+
+// A reference from columns of this table to a parent table's key
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ForeignKey {
+    pub columns: Vec<String>,
+    pub parent_table: String,
+    pub parent_columns: Vec<String>,
+}
+
+impl From<&ColumnDef> for ColumnInfo {
+    fn from(column: &ColumnDef) -> Self {
+        ColumnInfo {
+            name: column.name.clone(),
+            data_type: column.data_type.clone(),
+            not_null: column.not_null,
+            default: column.default.clone(),
+        }
+    }
+}
+
+// Schema file of an export directory: <db>.sql in a <db>.exp directory as dbexport names it,
+// otherwise the directory's only .sql file besides load_data.sql
+pub fn schema_path(dir: &Path) -> Result<PathBuf, Error> {
+    let db = dir.file_name().and_then(|name| name.to_str()).and_then(|name| name.strip_suffix(".exp"));
+    if let Some(db) = db {
+        let path = dir.join(format!("{}.sql", db));
+        if path.is_file() {
+            return Ok(path);
+        }
+    }
+    let entries = fs::read_dir(dir)
+        .map_err(|e| Error::Config(format!("Can't read export directory {}: {}", dir.display(), e)))?;
+    let mut found = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+        if name.ends_with(".sql") && name != "load_data.sql" && path.is_file() {
+            found.push(path);
+        }
+    }
+    found.sort();
+    match (found.len(), db) {
+        (1, _) => Ok(found.remove(0)),
+        (0, Some(db)) => Err(Error::Config(format!("Schema file {} not found", dir.join(format!("{}.sql", db)).display()))),
+        (0, None) => Err(Error::Config(format!("No schema .sql file found in {}", dir.display()))),
+        _ => {
+            let names: Vec<String> = found.iter().map(|p| p.file_name().unwrap_or_default().to_string_lossy().into_owned()).collect();
+            Err(Error::Config(format!("Several .sql files in {}, can't tell which is the schema: {}", dir.display(), names.join(", "))))
+        }
+    }
 }
 
 pub fn parse_sql_file(sql: &str) -> Result<HashMap<String, TableInfo>, Error> {
+    let schema = schema::parse(sql)?;
+    let keys = TableKeys::new(&schema);
     let mut tables = HashMap::new();
-    
-    // Regular expressions for parsing
-    let unl_file_re = Regex::new(r#"\{\s*unload\s+file\s+name\s*=\s*(\S+\.unl)\s*"#)
-        .map_err(|e| Error::Processing(format!("Invalid regex: {}", e)))?;
-    
-    let create_table_re = Regex::new(r#"(?s)create\s+table\s+"informix"\.?"?([^"\s]+)"?\s*\((.*?)\)\s*extent"#)
-        .map_err(|e| Error::Processing(format!("Invalid regex: {}", e)))?;
-    
-    let field_re = Regex::new(r#"(?m)^\s*"?([^",\s]+)"?\s+(?:serial|integer|varchar|char|date|datetime|decimal|blob|set)[^,]*"#)
-        .map_err(|e| Error::Processing(format!("Invalid regex: {}", e)))?;
-    
-    // Split into table blocks
-    let blocks: Vec<&str> = sql.split("{ TABLE").collect();
-    
-    for block in blocks.iter().skip(1) { // Skip first empty block
-        // Extract UNL filename
-        let unl_file = match unl_file_re.captures(block) {
-            Some(caps) => caps.get(1).unwrap().as_str().to_string(),
-            None => continue, // Skip if no UNL file found
+
+    // Tables without an unload header have no data in the export
+    for table in schema.tables() {
+        let (unl_file, declared_rows) = match &table.unload {
+            Some(unload) => (unload.file.clone(), unload.rows.value),
+            None => continue,
         };
-        
-        // Extract table name and fields
-        if let Some(table_caps) = create_table_re.captures(block) {
-            let table_name = table_caps.get(1).unwrap().as_str().to_string();
-            let fields_section = table_caps.get(2).unwrap().as_str();
-            
-            // Extract field names
-            let fields: Vec<String> = field_re.captures_iter(fields_section)
-                .filter_map(|caps| caps.get(1))
-                .map(|m| m.as_str().to_string())
-                .collect();
-            
-            if fields.is_empty() {
-                return Err(Error::Processing(format!("No fields found in table {}", table_name)));
-            }
-            
-            tables.insert(table_name, TableInfo { unl_file, fields });
-        }
+        let key = keys.find(&table.name).unwrap_or(&table.name.name).to_string();
+        let columns: Vec<ColumnInfo> = table.columns.iter().map(ColumnInfo::from).collect();
+        let fields = columns.iter().map(|c| c.name.clone()).collect();
+        let owner = table.name.owner.clone();
+        tables.insert(key, TableInfo { owner, unl_file, declared_rows, fields, columns, ..Default::default() });
     }
-    
+
     if tables.is_empty() {
         return Err(Error::Processing("No valid tables found in SQL file".to_string()));
     }
-    
+
+    collect_keys(&schema, &keys, &mut tables);
     Ok(tables)
 }
 
+// The key each created table is known by: its bare name, or owner.name when several owners
+// have a table of that name
+struct TableKeys {
+    keys: Vec<(Name, String)>,
+}
+
+impl TableKeys {
+    fn new(schema: &Schema) -> Self {
+        let names: Vec<&Name> = schema.tables().map(|table| &table.name).collect();
+        let keys = names.iter()
+            .map(|name| {
+                let shared = names.iter().filter(|other| other.name == name.name).count() > 1;
+                let key = match &name.owner {
+                    Some(owner) if shared => format!("{}.{}", owner, name.name),
+                    _ => name.name.clone(),
+                };
+                ((*name).clone(), key)
+            })
+            .collect();
+        TableKeys { keys }
+    }
+
+    // The key of the table a statement names; None if it isn't created here or a bare name
+    // could be more than one table
+    fn find(&self, name: &Name) -> Option<&str> {
+        let mut matches = self.keys.iter()
+            .filter(|(table, _)| table.name == name.name && (name.owner.is_none() || table.owner == name.owner));
+        match (matches.next(), matches.next()) {
+            (Some((_, key)), None) => Some(key),
+            _ => None,
+        }
+    }
+}
+
+// Constraints of each table in file order, with unique indexes as unique constraints
+fn constraints(schema: &Schema) -> Vec<(&Name, Cow<'_, ConstraintKind>)> {
+    let mut constraints = Vec::new();
+    for statement in &schema.statements {
+        match statement {
+            Statement::CreateTable(table) => {
+                constraints.extend(table.all_constraints().map(|c| (&table.name, Cow::Borrowed(&c.kind))));
+            }
+            Statement::AlterTable(alter) => {
+                constraints.extend(alter.constraints.iter().map(|c| (&alter.table, Cow::Borrowed(&c.kind))));
+            }
+            Statement::CreateIndex(index) if index.unique => {
+                constraints.push((&index.table, Cow::Owned(ConstraintKind::Unique(index.columns.clone()))));
+            }
+            _ => {}
+        }
+    }
+    constraints
+}
+
+// Collect primary keys, unique constraints, unique indexes and foreign keys for the parsed tables.
+// A reference without a column list points at the parent's primary key, so keys come first.
+fn collect_keys(schema: &Schema, keys: &TableKeys, tables: &mut HashMap<String, TableInfo>) {
+    let constraints: Vec<(&str, Cow<'_, ConstraintKind>)> = constraints(schema).into_iter()
+        .filter_map(|(name, kind)| Some((keys.find(name)?, kind)))
+        .collect();
+    for (table_name, kind) in &constraints {
+        match kind.as_ref() {
+            ConstraintKind::PrimaryKey(columns) => add_unique_key(tables, table_name, columns.clone(), true),
+            ConstraintKind::Unique(columns) => add_unique_key(tables, table_name, columns.clone(), false),
+            _ => {}
+        }
+    }
+
+    for (table_name, kind) in &constraints {
+        let (columns, parent, parent_columns) = match kind.as_ref() {
+            ConstraintKind::ForeignKey { columns, parent, parent_columns } => (columns, parent, parent_columns),
+            _ => continue,
+        };
+        let parent_table = match keys.find(parent) {
+            Some(parent_table) => parent_table.to_string(),
+            None => continue,
+        };
+        let parent_columns = if parent_columns.is_empty() {
+            match tables.get(&parent_table) {
+                Some(parent) => parent.primary_key.clone(),
+                None => continue,
+            }
+        } else {
+            parent_columns.clone()
+        };
+        if columns.is_empty() || columns.len() != parent_columns.len() {
+            continue;
+        }
+
+        if let Some(table) = tables.get_mut(*table_name) {
+            let key = ForeignKey { columns: columns.clone(), parent_table, parent_columns };
+            if !table.foreign_keys.contains(&key) {
+                table.foreign_keys.push(key);
+            }
+        }
+    }
+}
+
+fn add_unique_key(tables: &mut HashMap<String, TableInfo>, table_name: &str, columns: Vec<String>, primary: bool) {
+    if let Some(table) = tables.get_mut(table_name) {
+        if columns.is_empty() {
+            return;
+        }
+        if primary {
+            table.primary_key = columns.clone();
+        }
+        if !table.unique_keys.contains(&columns) {
+            table.unique_keys.push(columns);
+        }
+    }
+}
+
+// Remove columns from a table's create table statement and its `number of columns` header,
+// for exports that leave those columns out. Fails if an index still uses one of them.
+pub fn drop_columns(sql: &str, table_name: &str, columns: &[String]) -> Result<String, Error> {
+    let dropped = |name: &str| columns.iter().any(|c| c.eq_ignore_ascii_case(name));
+    let schema = schema::parse(sql)?;
+    let keys = TableKeys::new(&schema);
+    let is_table = |name: &Name| keys.find(name) == Some(table_name);
+
+    for statement in &schema.statements {
+        if let Statement::CreateIndex(index) = statement {
+            if is_table(&index.table) && index.columns.iter().any(|c| dropped(c)) {
+                return Err(Error::Config(format!(
+                    "Cannot drop columns of table '{}': index {} uses them",
+                    table_name,
+                    index.name
+                )));
+            }
+        }
+    }
+
+    let table = schema.tables()
+        .find(|table| is_table(&table.name))
+        .ok_or_else(|| Error::Processing(format!("Create table statement for {} not found", table_name)))?;
+
+    let removed: Vec<Span> = table.columns.iter().filter(|c| dropped(&c.name)).map(|c| c.span).collect();
+    let kept: Vec<&str> = table.items.iter()
+        .filter(|item| !removed.contains(item))
+        .map(|item| &sql[item.start..item.end])
+        .collect();
+    let (body_start, body_end) = match (table.items.first(), table.items.last()) {
+        (Some(first), Some(last)) => (first.start, last.end),
+        _ => return Ok(sql.to_string()),
+    };
+    let text = &sql[body_start..body_end];
+    let mut new_body = kept.join(",");
+    if !new_body.ends_with(char::is_whitespace) {
+        new_body.push_str(&text[text.trim_end().len()..]);
+    }
+
+    let mut result = sql.to_string();
+    result.replace_range(body_start..body_end, &new_body);
+    // The header comes before the statement, so its offsets still hold
+    if let Some(header) = schema.table_headers.iter().find(|header| is_table(&header.name)) {
+        let count = header.columns.value.saturating_sub(removed.len());
+        result.replace_range(header.columns.span.start..header.columns.span.end, &count.to_string());
+    }
+
+    Ok(result)
+}
+
+// Set the row counts dbimport reads from the tables' unload headers, by unload file
+pub fn set_row_counts(sql: &str, rows: &[(String, usize)]) -> Result<String, Error> {
+    let schema = schema::parse(sql)?;
+    let mut edits = Vec::new();
+    for (unl_file, count) in rows {
+        let header = schema.unload_headers.iter()
+            .find(|header| header.file == *unl_file)
+            .ok_or_else(|| Error::Processing(format!("Unload header for {} not found", unl_file)))?;
+        edits.push((header.rows.span, count.to_string()));
+    }
+
+    // From the end, so earlier offsets stay valid
+    edits.sort_by_key(|(span, _)| std::cmp::Reverse(span.start));
+    let mut result = sql.to_string();
+    for (span, count) in edits {
+        result.replace_range(span.start..span.end, &count);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+fn column(name: &str, data_type: &str, not_null: bool) -> ColumnInfo {
+    ColumnInfo { name: name.to_string(), data_type: data_type.to_string(), not_null, default: None }
+}
+
 #[test]
 fn test_parse_create_table_statements() {
     let sql = r#"
@@ -95,7 +364,9 @@ fn test_parse_create_table_statements() {
         tables.insert(
             "customers".to_string(),
             TableInfo {
+                owner: Some("informix".to_string()),
                 unl_file: "custo00100.unl".to_string(),
+                declared_rows: 73,
                 fields: vec![
                     "id".to_string(),
                     "first_name".to_string(),
@@ -104,13 +375,24 @@ fn test_parse_create_table_statements() {
                     "address".to_string(),
                     "phone".to_string(),
                 ],
+                columns: vec![
+                    column("id", "serial", true),
+                    column("first_name", "varchar(50)", false),
+                    column("last_name", "varchar(50)", false),
+                    column("email", "varchar(100)", false),
+                    column("address", "varchar(200)", false),
+                    column("phone", "varchar(20)", false),
+                ],
+                ..Default::default()
             }
         );
         
         tables.insert(
             "employees".to_string(),
             TableInfo {
+                owner: Some("informix".to_string()),
                 unl_file: "emplo00101.unl".to_string(),
+                declared_rows: 53,
                 fields: vec![
                     "id".to_string(),
                     "customer_id".to_string(),
@@ -119,6 +401,15 @@ fn test_parse_create_table_statements() {
                     "address".to_string(),
                     "phone".to_string(),
                 ],
+                columns: vec![
+                    column("id", "serial", true),
+                    column("customer_id", "integer", false),
+                    column("name", "varchar(100)", false),
+                    column("email", "varchar(100)", false),
+                    column("address", "varchar(200)", false),
+                    column("phone", "varchar(20)", false),
+                ],
+                ..Default::default()
             }
         );
         
@@ -154,7 +445,9 @@ fn test_parse_table_with_complex_fields() {
         tables.insert(
             "complex_table".to_string(),
             TableInfo {
+                owner: Some("informix".to_string()),
                 unl_file: "compl00102.unl".to_string(),
+                declared_rows: 100,
                 fields: vec![
                     "id".to_string(),
                     "decimal_field".to_string(),
@@ -165,6 +458,17 @@ fn test_parse_table_with_complex_fields() {
                     "varying_field".to_string(),
                     "fixed_field".to_string(),
                 ],
+                columns: vec![
+                    column("id", "serial", true),
+                    column("decimal_field", "decimal(10,2)", false),
+                    column("date_field", "date", false),
+                    column("timestamp_field", "datetime year to fraction(5)", false),
+                    column("blob_field", "blob", false),
+                    column("list_field", "set(integer not null)", false),
+                    column("varying_field", "varchar(255, 512)", false),
+                    column("fixed_field", "char(10)", false),
+                ],
+                ..Default::default()
             }
         );
         
@@ -187,4 +491,248 @@ fn test_error_handling() {
     "#;
     assert!(parse_sql_file(sql).is_err());
 
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_parse_unique_keys() {
+    let sql = r#"
+        { TABLE "informix".repositories row size = 261 number of columns = 4 index size = 0 }
+        { unload file name = repos00103.unl number of rows = 74 }
+        create table "informix".repositories 
+        (
+            id serial not null,
+            project_id integer,
+            owner_name varchar(50),
+            repo_name varchar(50)
+        ) extent size 16 next size 16 lock mode row;
+
+        create unique index "informix".ix_repo_name on "informix".repositories 
+            (owner_name,repo_name desc) using btree ;
+        alter table "informix".repositories add constraint primary key 
+            (id) constraint "informix".u103_1  ;
+    "#;
+
+    let result = parse_sql_file(sql).unwrap();
+    assert_eq!(
+        result["repositories"].unique_keys,
+        vec![
+            vec!["owner_name".to_string(), "repo_name".to_string()],
+            vec!["id".to_string()],
+        ]
+    );
+}
+
+#[test]
+fn test_parse_foreign_keys() {
+    let sql = r#"
+        { TABLE "informix".customers row size = 100 number of columns = 2 index size = 0 }
+        { unload file name = custo00100.unl number of rows = 2 }
+        create table "informix".customers 
+        (
+            id serial not null,
+            first_name varchar(50)
+        ) extent size 16 next size 16 lock mode row;
+
+        { TABLE "informix".projects row size = 100 number of columns = 3 index size = 0 }
+        { unload file name = proje00102.unl number of rows = 2 }
+        create table "informix".projects 
+        (
+            id serial not null,
+            customer_id integer,
+            project_name varchar(100),
+            primary key (id) constraint "informix".u102_1,
+            foreign key (customer_id) references "informix".customers (id) constraint "informix".r102_2
+        ) extent size 16 next size 16 lock mode row;
+
+        { TABLE "informix".repositories row size = 100 number of columns = 2 index size = 0 }
+        { unload file name = repos00103.unl number of rows = 2 }
+        create table "informix".repositories 
+        (
+            id serial not null,
+            project_id integer
+        ) extent size 16 next size 16 lock mode row;
+
+        alter table "informix".customers add constraint primary key (id) 
+            constraint "informix".u100_1  ;
+        alter table "informix".repositories add constraint (foreign key 
+            (project_id) references "informix".projects  constraint "informix".r103_1);
+    "#;
+
+    let result = parse_sql_file(sql).unwrap();
+    assert_eq!(result["customers"].primary_key, vec!["id".to_string()]);
+    assert_eq!(result["projects"].foreign_keys, vec![ForeignKey {
+        columns: vec!["customer_id".to_string()],
+        parent_table: "customers".to_string(),
+        parent_columns: vec!["id".to_string()],
+    }]);
+    assert_eq!(result["projects"].primary_key, vec!["id".to_string()]);
+    // Without a column list the reference is to the parent's primary key
+    assert_eq!(result["repositories"].foreign_keys, vec![ForeignKey {
+        columns: vec!["project_id".to_string()],
+        parent_table: "projects".to_string(),
+        parent_columns: vec!["id".to_string()],
+    }]);
+    assert!(result["customers"].foreign_keys.is_empty());
+}
+
+#[test]
+fn test_parse_column_types() {
+    let sql = r#"
+        { TABLE "informix".accounts row size = 120 number of columns = 7 index size = 0 }
+        { unload file name = accou00104.unl number of rows = 3 }
+        create table "informix".accounts 
+        (
+            id serial8 not null constraint "informix".n104_1,
+            balance money(12,2) default 0.00 not null,
+            rate smallfloat,
+            status char(1) default 'A' not null,
+            notes lvarchar(2000),
+            active boolean,
+            opened datetime year to second,
+            primary key (id)
+        ) extent size 16 next size 16 lock mode row;
+    "#;
+
+    let tables = parse_sql_file(sql).unwrap();
+    let columns = &tables["accounts"].columns;
+    assert_eq!(tables["accounts"].fields.len(), 7);
+    assert_eq!(columns[0], column("id", "serial8", true));
+    assert_eq!(columns[1].data_type, "money(12,2)");
+    assert_eq!(columns[1].base_type(), "money");
+    assert_eq!(columns[1].default.as_deref(), Some("0.00"));
+    assert!(columns[1].not_null);
+    assert_eq!(columns[3].default.as_deref(), Some("A"));
+    assert_eq!(columns[4], column("notes", "lvarchar(2000)", false));
+    assert_eq!(columns[6].data_type, "datetime year to second");
+    assert_eq!(columns[3].width(), Some(1));
+    assert_eq!(columns[4].width(), Some(2000));
+    assert_eq!(columns[1].width(), None);
+}
+
+#[test]
+fn test_set_row_counts() -> Result<(), Error> {
+    let sql = r#"
+        { TABLE "informix".customers row size = 429 number of columns = 6 index size = 0 }
+        { unload file name = custo00100.unl number of rows = 73 }
+    "#;
+    let updated = set_row_counts(sql, &[("custo00100.unl".to_string(), 80)])?;
+    assert!(updated.contains("{ unload file name = custo00100.unl number of rows = 80 }"));
+    assert!(set_row_counts(sql, &[("proje00102.unl".to_string(), 1)]).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_collection_and_row_types() -> Result<(), Error> {
+    let sql = r#"
+        { TABLE "informix".contacts row size = 300 number of columns = 5 index size = 0 }
+        { unload file name = conta00100.unl number of rows = 1 }
+        create table "informix".contacts 
+        (
+            id serial not null,
+            tags set(varchar(20) not null),
+            "address" row(street varchar(40), zip char(5)) not null,
+            phones list(char(12) not null) default 'LIST{}',
+            history multiset(row(changed date, note varchar(10)) not null),
+            primary key (id)
+        ) in datadbs lock mode row;
+    "#;
+    let tables = parse_sql_file(sql)?;
+    let contacts = &tables["contacts"];
+    assert_eq!(contacts.fields, vec!["id", "tags", "address", "phones", "history"]);
+    assert_eq!(contacts.columns[1], column("tags", "set(varchar(20) not null)", false));
+    assert_eq!(contacts.columns[2], column("address", "row(street varchar(40), zip char(5))", true));
+    assert_eq!(contacts.columns[4].data_type, "multiset(row(changed date, note varchar(10)) not null)");
+    assert_eq!(contacts.primary_key, vec!["id"]);
+
+    let element = contacts.columns[1].element().unwrap();
+    assert_eq!((element.data_type.as_str(), element.not_null, element.width()), ("varchar(20)", true, Some(20)));
+    assert_eq!(contacts.columns[2].element(), None);
+
+    let dropped = drop_columns(sql, "contacts", &["address".to_string()])?;
+    assert!(dropped.contains("tags set(varchar(20) not null),\n            phones list"));
+    assert!(dropped.contains("number of columns = 4 "));
+    Ok(())
+}
+
+#[test]
+fn test_drop_columns() {
+    let sql = r#"
+        { TABLE "informix".users row size = 300 number of columns = 4 index size = 9 }
+        { unload file name = users00105.unl number of rows = 2 }
+        create table "informix".users 
+        (
+            id serial not null,
+            password_hash char(64),
+            balance decimal(10,2),
+            api_token varchar(100)
+        ) extent size 16 next size 16 lock mode row;
+        create unique index "informix".ix_users_id on "informix".users (id) using btree ;
+    "#;
+
+    let columns = vec!["password_hash".to_string(), "api_token".to_string()];
+    let result = drop_columns(sql, "users", &columns).unwrap();
+    assert!(result.contains("number of columns = 2 "));
+    let tables = parse_sql_file(&result).unwrap();
+    assert_eq!(tables["users"].fields, vec!["id", "balance"]);
+    assert_eq!(tables["users"].unique_keys, vec![vec!["id".to_string()]]);
+
+    let err = drop_columns(sql, "users", &["id".to_string()]).unwrap_err();
+    assert!(matches!(err, Error::Config(ref msg) if msg.contains("ix_users_id")));
+}
+
+#[test]
+fn test_parse_schema_variants() -> Result<(), Error> {
+    let sql = r#"
+        { TABLE "appuser".orders row size = 8 number of columns = 2 index size = 0 }
+        { unload file name = order00100.unl number of rows = 1 }
+        CREATE TABLE "appuser".orders
+        (
+            id SERIAL NOT NULL, -- surrogate key
+            customer_id INTEGER
+        ) IN datadbs LOCK MODE ROW;
+        { TABLE "informix".customers row size = 4 number of columns = 1 index size = 0 }
+        { unload file name = custo00101.unl number of rows = 1 }
+        create table customers (id integer primary key) fragment by round robin in dbs1, dbs2;
+        create view "informix".customer_orders as select * from orders;
+        create synonym "informix".cust for customers;
+        ALTER TABLE "appuser".orders ADD CONSTRAINT FOREIGN KEY (customer_id) REFERENCES customers;
+    "#;
+    let tables = parse_sql_file(sql)?;
+    assert_eq!(tables["orders"].fields, vec!["id", "customer_id"]);
+    assert_eq!(tables["customers"].primary_key, vec!["id"]);
+    assert_eq!(tables["orders"].foreign_keys, vec![ForeignKey {
+        columns: vec!["customer_id".to_string()],
+        parent_table: "customers".to_string(),
+        parent_columns: vec!["id".to_string()],
+    }]);
+
+    let err = parse_sql_file("create table t (id integer,\n  name varchar(10) default 'x\n);").unwrap_err();
+    assert!(err.to_string().contains("line 2, column 28: unterminated string"));
+    Ok(())
+}
+
+#[test]
+fn test_schema_path() -> Result<(), Error> {
+    let temp_dir = tempfile::TempDir::new()?;
+    let exp = temp_dir.path().join("stores.exp");
+    fs::create_dir(&exp)?;
+    let err = schema_path(&exp).unwrap_err().to_string();
+    assert!(err.contains(&exp.join("stores.sql").display().to_string()), "{}", err);
+
+    // A single .sql file is used whatever its name; load_data.sql is never the schema
+    fs::write(exp.join("load_data.sql"), "")?;
+    fs::write(exp.join("test_live.sql"), "")?;
+    assert_eq!(schema_path(&exp)?, exp.join("test_live.sql"));
+    fs::write(exp.join("other.sql"), "")?;
+    let err = schema_path(&exp).unwrap_err().to_string();
+    assert!(err.contains("other.sql, test_live.sql"), "{}", err);
+    fs::write(exp.join("stores.sql"), "")?;
+    assert_eq!(schema_path(&exp)?, exp.join("stores.sql"));
+
+    let plain = temp_dir.path().join("refreshed");
+    fs::create_dir(&plain)?;
+    let err = schema_path(&plain).unwrap_err().to_string();
+    assert!(err.contains("No schema .sql file found in"), "{}", err);
+    assert!(schema_path(&temp_dir.path().join("missing")).unwrap_err().to_string().contains("missing"));
+    Ok(())
+}
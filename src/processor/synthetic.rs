@@ -0,0 +1,445 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use chrono::{Duration, NaiveDate};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::config::{Config, SyntheticColumn, SyntheticRowsConfig};
+use crate::error::Error;
+use crate::processor::collections::CollectionKind;
+use crate::processor::columns::type_default;
+use crate::processor::dates::{DateFormat, DatetimeLiteral};
+use crate::processor::generators;
+use crate::processor::numbers::{self, NumericFormat};
+use crate::processor::random::{self, seeded_rng};
+use crate::processor::redact::truncate;
+use crate::processor::sql::{ColumnInfo, TableInfo};
+use crate::processor::unl::{find_field_index, UnlRow};
+
+// Tries at a row before giving up on finding values that keep its unique keys unique
+const MAX_ATTEMPTS: usize = 100;
+
+// Written key values per parent table and referenced column list
+type ParentKeys = HashMap<(String, Vec<String>), Vec<Vec<String>>>;
+
+// What synthetic values are drawn from besides the column types
+pub struct ValueSource<'a> {
+    pub seed: u64,
+    pub date_format: &'a DateFormat,
+    pub adjectives: &'a [String],
+    pub nouns: &'a [String],
+}
+
+// The unique key values of a table's written rows, so synthetic rows can avoid them and
+// continue its integer keys
+#[derive(Debug, Default)]
+pub struct WrittenKeys {
+    seen: HashMap<Vec<usize>, HashSet<Vec<String>>>,
+    // Largest value of each single-column integer key
+    max: HashMap<usize, i64>,
+    sequences: HashSet<usize>,
+}
+
+impl WrittenKeys {
+    pub fn new(info: &TableInfo) -> Self {
+        let mut keys = WrittenKeys::default();
+        for key in &info.unique_keys {
+            let indices: Option<Vec<usize>> = key.iter().map(|column| find_field_index(column, &info.fields)).collect();
+            let Some(indices) = indices else { continue };
+            if let [idx] = indices.as_slice() {
+                if info.columns.get(*idx).is_some_and(|column| is_integer(&column.base_type())) {
+                    keys.sequences.insert(*idx);
+                }
+            }
+            keys.seen.insert(indices, HashSet::new());
+        }
+        keys
+    }
+
+    pub fn observe(&mut self, row: &UnlRow) {
+        for (indices, seen) in self.seen.iter_mut() {
+            if let Some(values) = key_values(indices, row) {
+                seen.insert(values);
+            }
+        }
+        for idx in &self.sequences {
+            if let Some(value) = row.get_field(*idx).and_then(|v| v.trim().parse::<i64>().ok()) {
+                let max = self.max.entry(*idx).or_insert(value);
+                *max = (*max).max(value);
+            }
+        }
+    }
+
+    fn next_in_sequence(&self, idx: usize) -> i64 {
+        self.max.get(&idx).map_or(1, |max| max + 1)
+    }
+
+    fn is_unique(&self, row: &UnlRow) -> bool {
+        self.seen.iter().all(|(indices, seen)| key_values(indices, row).is_none_or(|values| !seen.contains(&values)))
+    }
+}
+
+// Rows appended to tables by synthetic_rows rules, with the parent keys they can reference
+#[derive(Debug, Default)]
+pub struct SyntheticRows {
+    rules: HashMap<String, SyntheticRowsConfig>,
+    // Parent tables whose written keys synthetic rows pick from, with the referenced columns
+    parents: HashMap<String, Vec<Vec<String>>>,
+    parent_keys: RwLock<ParentKeys>,
+}
+
+impl SyntheticRows {
+    pub fn new(config: &Config, table_info: &HashMap<String, TableInfo>) -> Result<Self, Error> {
+        let excluded = |table: &str| config.excluded_tables.iter().any(|t| t == table);
+        let mut rules = HashMap::new();
+        let mut parents: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+
+        for rule in &config.synthetic_rows {
+            let info = match table_info.get(&rule.table) {
+                Some(info) if !excluded(&rule.table) => info,
+                _ => return Err(Error::Config(format!(
+                    "synthetic_rows table '{}' is excluded or not in the export",
+                    rule.table
+                ))),
+            };
+            for (field, spec) in &rule.columns {
+                let idx = find_field_index(field, &info.fields).ok_or_else(|| Error::Config(format!(
+                    "synthetic_rows column '{}' not found in table '{}'",
+                    field,
+                    rule.table
+                )))?;
+                check_spec(&rule.table, field, spec, info.columns.get(idx))?;
+            }
+
+            // Columns left to their type need a type we can make values for, unless they may be NULL
+            let referencing: HashSet<&str> = info.foreign_keys.iter().flat_map(|fk| fk.columns.iter().map(String::as_str)).collect();
+            for column in &info.columns {
+                let configured = rule.columns.keys().any(|field| field.eq_ignore_ascii_case(&column.name));
+                if column.not_null && !configured && !referencing.contains(column.name.as_str())
+                    && type_default(column, &DateFormat::default()).is_none()
+                {
+                    return Err(Error::Config(format!(
+                        "synthetic_rows can't make values for {}.{} ({}); configure the column",
+                        rule.table,
+                        column.name,
+                        column.data_type
+                    )));
+                }
+            }
+
+            for fk in &info.foreign_keys {
+                if table_info.contains_key(&fk.parent_table) && !excluded(&fk.parent_table) {
+                    let keys = parents.entry(fk.parent_table.clone()).or_default();
+                    if !keys.contains(&fk.parent_columns) {
+                        keys.push(fk.parent_columns.clone());
+                    }
+                }
+            }
+            if rules.insert(rule.table.clone(), rule.clone()).is_some() {
+                return Err(Error::Config(format!("synthetic_rows lists table '{}' twice", rule.table)));
+            }
+        }
+
+        Ok(SyntheticRows { rules, parents, parent_keys: RwLock::default() })
+    }
+
+    // (child, parent) pairs: parents are written first so synthetic rows can reference them
+    pub fn dependencies(&self, table_info: &HashMap<String, TableInfo>) -> Vec<(String, String)> {
+        let mut dependencies = Vec::new();
+        for table in self.rules.keys() {
+            for fk in table_info.get(table).map(|info| info.foreign_keys.as_slice()).unwrap_or_default() {
+                if fk.parent_table != *table && self.parents.contains_key(&fk.parent_table) {
+                    dependencies.push((table.clone(), fk.parent_table.clone()));
+                }
+            }
+        }
+        dependencies
+    }
+
+    pub fn has_rows(&self, table_name: &str) -> bool {
+        self.rules.contains_key(table_name)
+    }
+
+    // Forget a table's written keys, e.g. before a retry processes it again
+    pub fn reset(&self, table_name: &str) -> Result<(), Error> {
+        self.write()?.retain(|(table, _), _| table != table_name);
+        Ok(())
+    }
+
+    // Remember a written row's keys for the synthetic rows that may reference it
+    pub fn record(&self, table_name: &str, row: &UnlRow, info: &TableInfo) -> Result<(), Error> {
+        let keys = match self.parents.get(table_name) {
+            Some(keys) => keys,
+            None => return Ok(()),
+        };
+        let mut parent_keys = self.write()?;
+        for columns in keys {
+            let indices: Option<Vec<usize>> = columns.iter().map(|column| find_field_index(column, &info.fields)).collect();
+            if let Some(values) = indices.and_then(|indices| key_values(&indices, row)) {
+                parent_keys.entry((table_name.to_string(), columns.clone())).or_default().push(values);
+            }
+        }
+        Ok(())
+    }
+
+    // The table's synthetic rows, each seeded by its number so reruns give the same rows
+    pub fn generate(&self, table_name: &str, info: &TableInfo, written: &mut WrittenKeys, source: &ValueSource) -> Result<Vec<UnlRow>, Error> {
+        let rule = match self.rules.get(table_name) {
+            Some(rule) => rule,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut rows = Vec::with_capacity(rule.count);
+        for n in 0..rule.count {
+            let mut accepted = None;
+            for attempt in 0..MAX_ATTEMPTS {
+                let row = self.generate_row(rule, info, written, source, &[table_name, &n.to_string(), &attempt.to_string()])?;
+                if written.is_unique(&row) {
+                    accepted = Some(row);
+                    break;
+                }
+            }
+            let row = accepted.ok_or_else(|| Error::Processing(format!(
+                "Could not make synthetic row {} of table '{}' unique after {} attempts",
+                n + 1,
+                table_name,
+                MAX_ATTEMPTS
+            )))?;
+            written.observe(&row);
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    fn generate_row(&self, rule: &SyntheticRowsConfig, info: &TableInfo, written: &WrittenKeys, source: &ValueSource, seed_parts: &[&str]) -> Result<UnlRow, Error> {
+        let mut rng = seeded_rng(source.seed, &[&["synthetic"], seed_parts].concat());
+        let mut fields = vec![String::new(); info.fields.len()];
+        let mut assigned = vec![false; info.fields.len()];
+        let configured = |field: &str| rule.columns.keys().any(|f| f.eq_ignore_ascii_case(field));
+
+        // References take a whole key of one written parent row
+        let parent_keys = self.read()?;
+        for fk in &info.foreign_keys {
+            if fk.columns.iter().all(|column| configured(column)) {
+                continue;
+            }
+            let indices: Vec<usize> = fk.columns.iter().filter_map(|column| find_field_index(column, &info.fields)).collect();
+            let choice = parent_keys.get(&(fk.parent_table.clone(), fk.parent_columns.clone()))
+                .and_then(|keys| keys.choose(&mut rng));
+            match choice {
+                Some(values) => {
+                    for (idx, value) in indices.iter().zip(values) {
+                        fields[*idx] = value.clone();
+                    }
+                }
+                None if fk.columns.iter().any(|column| column_info(info, column).is_some_and(|c| c.not_null)) => {
+                    return Err(Error::Processing(format!(
+                        "Synthetic rows of table '{}' must reference '{}', which has no rows",
+                        rule.table,
+                        fk.parent_table
+                    )));
+                }
+                None => {}
+            }
+            for idx in indices {
+                assigned[idx] = true;
+            }
+        }
+        drop(parent_keys);
+
+        for (idx, field) in info.fields.iter().enumerate() {
+            let column = info.columns.get(idx);
+            let spec = rule.columns.iter().find(|(f, _)| f.eq_ignore_ascii_case(field)).map(|(_, spec)| spec);
+            let value = match spec {
+                Some(spec) => spec_value(spec, &mut rng),
+                None if assigned[idx] => continue,
+                None if written.sequences.contains(&idx) => Some(written.next_in_sequence(idx).to_string()),
+                None => column.and_then(|column| type_value(column, source, &mut rng)),
+            };
+            let mut value = value.unwrap_or_default();
+            if let Some(width) = column.and_then(ColumnInfo::width) {
+                truncate(&mut value, width);
+            }
+            fields[idx] = value;
+        }
+        Ok(UnlRow { fields })
+    }
+
+    fn read(&self) -> Result<RwLockReadGuard<'_, ParentKeys>, Error> {
+        self.parent_keys.read()
+            .map_err(|e| Error::Processing(format!("Failed to lock synthetic row parents: {}", e)))
+    }
+
+    fn write(&self) -> Result<RwLockWriteGuard<'_, ParentKeys>, Error> {
+        self.parent_keys.write()
+            .map_err(|e| Error::Processing(format!("Failed to lock synthetic row parents: {}", e)))
+    }
+}
+
+fn check_spec(table_name: &str, field: &str, spec: &SyntheticColumn, column: Option<&ColumnInfo>) -> Result<(), Error> {
+    let problem = if spec.options_set() != 1 {
+        Some("needs exactly one of generator, values, value or null")
+    } else if spec.values.as_ref().is_some_and(Vec::is_empty) {
+        Some("has an empty values list")
+    } else if (spec.null || spec.value.as_deref() == Some("")) && column.is_some_and(|c| c.not_null) {
+        Some("is not null")
+    } else {
+        None
+    };
+    match problem {
+        Some(problem) => Err(Error::Config(format!("synthetic_rows column {}.{} {}", table_name, field, problem))),
+        None => Ok(()),
+    }
+}
+
+fn spec_value<R: Rng>(spec: &SyntheticColumn, rng: &mut R) -> Option<String> {
+    if let Some(kind) = spec.generator {
+        return Some(generators::generate(kind, rng));
+    }
+    if let Some(values) = &spec.values {
+        return values.choose(rng).cloned();
+    }
+    spec.value.clone()
+}
+
+// A random value of the column's declared type; None (NULL) for types without one
+fn type_value<R: Rng>(column: &ColumnInfo, source: &ValueSource, rng: &mut R) -> Option<String> {
+    let base_type = column.base_type();
+    if is_integer(&base_type) {
+        let max = if base_type == "smallint" { 1000 } else { 100_000 };
+        return Some(rng.gen_range(1..=max).to_string());
+    }
+    if let Some(format) = NumericFormat::from_column(column) {
+        let scale = format.scale.unwrap_or(2);
+        let units = format.clamp(numbers::to_units(rng.gen_range(0.0..1000.0), scale));
+        return Some(numbers::format_units(units, scale));
+    }
+
+    let start = NaiveDate::from_ymd_opt(2015, 1, 1)?.and_hms_opt(0, 0, 0)?;
+    let datetime = start + Duration::seconds(rng.gen_range(0..10 * 365 * 86_400));
+    match base_type.as_str() {
+        "char" | "character" | "nchar" | "varchar" | "nvarchar" | "lvarchar" => {
+            random::generate_random_name(source.adjectives, source.nouns, rng).or_else(|| Some("sample".to_string()))
+        }
+        "date" => Some(source.date_format.format(datetime.date())),
+        "datetime" => {
+            let literal = DatetimeLiteral::parse(&type_default(column, source.date_format)?)?;
+            Some(literal.with_value(datetime).to_string())
+        }
+        "boolean" => Some(if rng.gen_bool(0.5) { "t" } else { "f" }.to_string()),
+        base_type => CollectionKind::from_type(base_type).map(|kind| kind.empty_literal()),
+    }
+}
+
+fn is_integer(base_type: &str) -> bool {
+    matches!(base_type, "smallint" | "integer" | "int" | "serial" | "int8" | "bigint" | "serial8" | "bigserial")
+}
+
+fn column_info<'a>(info: &'a TableInfo, field: &str) -> Option<&'a ColumnInfo> {
+    find_field_index(field, &info.fields).and_then(|idx| info.columns.get(idx))
+}
+
+// The row's values at the indices, or None if any is NULL
+fn key_values(indices: &[usize], row: &UnlRow) -> Option<Vec<String>> {
+    indices.iter()
+        .map(|idx| row.get_field(*idx).filter(|value| !value.is_empty()).map(String::from))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::sql::ForeignKey;
+
+    fn column(name: &str, data_type: &str, not_null: bool) -> ColumnInfo {
+        ColumnInfo { name: name.to_string(), data_type: data_type.to_string(), not_null, default: None }
+    }
+
+    fn table_info() -> HashMap<String, TableInfo> {
+        let table = |columns: Vec<ColumnInfo>, foreign_keys: Vec<ForeignKey>| TableInfo {
+            fields: columns.iter().map(|c| c.name.clone()).collect(),
+            columns,
+            unique_keys: vec![vec!["id".to_string()]],
+            primary_key: vec!["id".to_string()],
+            foreign_keys,
+            ..Default::default()
+        };
+
+        let mut tables = HashMap::new();
+        tables.insert("customers".to_string(), table(vec![column("id", "serial", true), column("name", "varchar(20)", false)], vec![]));
+        tables.insert("tickets".to_string(), table(
+            vec![
+                column("id", "serial", true),
+                column("customer_id", "integer", true),
+                column("opened", "date", false),
+                column("amount", "decimal(6,2)", false),
+                column("status", "char(6)", true),
+                column("notes", "text", false),
+            ],
+            vec![ForeignKey {
+                columns: vec!["customer_id".to_string()],
+                parent_table: "customers".to_string(),
+                parent_columns: vec!["id".to_string()],
+            }],
+        ));
+        tables
+    }
+
+    fn build(rules: &str) -> Result<SyntheticRows, Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.excluded_tables.clear();
+        config.synthetic_rows = serde_yaml::from_str(rules)?;
+        SyntheticRows::new(&config, &table_info())
+    }
+
+    fn generate(synthetic: &SyntheticRows, written: &[&str]) -> Result<Vec<UnlRow>, Error> {
+        let tables = table_info();
+        let info = &tables["tickets"];
+        let mut keys = WrittenKeys::new(info);
+        for line in written {
+            keys.observe(&UnlRow::from_line(line)?);
+        }
+        let words = vec!["quick".to_string()];
+        let source = ValueSource { seed: 42, date_format: &DateFormat::default(), adjectives: &words, nouns: &words };
+        synthetic.generate("tickets", info, &mut keys, &source)
+    }
+
+    #[test]
+    fn test_rows_follow_schema_and_parents() -> Result<(), Error> {
+        let tables = table_info();
+        let synthetic = build("[{ table: tickets, count: 5, columns: { status: { values: [open, closed] } } }]")?;
+        assert_eq!(synthetic.dependencies(&tables), vec![("tickets".to_string(), "customers".to_string())]);
+        for line in ["7|Ann|", "9|Bob|"] {
+            synthetic.record("customers", &UnlRow::from_line(line)?, &tables["customers"])?;
+        }
+
+        let rows = generate(&synthetic, &["40|7|01/02/2020|1.00|open||"])?;
+        assert_eq!(rows.len(), 5);
+        let ids: Vec<&str> = rows.iter().map(|row| row.fields[0].as_str()).collect();
+        assert_eq!(ids, vec!["41", "42", "43", "44", "45"]);
+        for row in &rows {
+            assert_eq!(row.fields.len(), 6);
+            assert!(["7", "9"].contains(&row.fields[1].as_str()));
+            assert!(DateFormat::default().parse_date(&row.fields[2]).is_some());
+            assert!(row.fields[3].split_once('.').is_some_and(|(_, cents)| cents.len() == 2));
+            assert!(["open", "closed"].contains(&row.fields[4].as_str()));
+            assert_eq!(row.fields[5], "");
+        }
+        assert_eq!(rows, generate(&synthetic, &["40|7|01/02/2020|1.00|open||"])?);
+
+        synthetic.reset("customers")?;
+        assert!(matches!(generate(&synthetic, &[]), Err(Error::Processing(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_synthetic_config_errors() {
+        // status is not null and char(6) has a type value, but null is refused
+        assert!(matches!(build("[{ table: tickets, count: 1, columns: { status: { null: true } } }]"), Err(Error::Config(_))));
+        assert!(matches!(build("[{ table: tickets, count: 1, columns: { status: {} } }]"), Err(Error::Config(_))));
+        assert!(matches!(build("[{ table: tickets, count: 1, columns: { missing: { value: x } } }]"), Err(Error::Config(_))));
+        assert!(matches!(build("[{ table: nowhere, count: 1 }]"), Err(Error::Config(_))));
+        assert!(build("[{ table: customers, count: 1, columns: { name: { generator: company } } }]").is_ok());
+    }
+}
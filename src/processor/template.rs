@@ -0,0 +1,73 @@
+use crate::error::Error;
+
+// Render a template such as "{owner_name}/{repo_name}", asking `resolve` for each
+// placeholder's value. "{{" and "}}" produce literal braces.
+pub fn render<F>(template: &str, mut resolve: F) -> Result<String, Error>
+where
+    F: FnMut(&str) -> Result<String, Error>,
+{
+    let mut output = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                output.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                output.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(Error::Config(format!(
+                            "Unclosed placeholder in template '{}'",
+                            template
+                        ))),
+                    }
+                }
+                let name = name.trim();
+                if name.is_empty() {
+                    return Err(Error::Config(format!("Empty placeholder in template '{}'", template)));
+                }
+                output.push_str(&resolve(name)?);
+            }
+            '}' => {
+                return Err(Error::Config(format!("Unmatched '}}' in template '{}'", template)));
+            }
+            c => output.push(c),
+        }
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template() -> Result<(), Error> {
+        let rendered = render("{owner}/{repo} {{literal}}", |name| match name {
+            "owner" => Ok("alice".to_string()),
+            "repo" => Ok("tools".to_string()),
+            other => Err(Error::Config(format!("unknown {}", other))),
+        })?;
+        assert_eq!(rendered, "alice/tools {literal}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_template_errors() {
+        let resolve = |_: &str| Ok("x".to_string());
+        assert!(render("{open", resolve).is_err());
+        assert!(render("close}", resolve).is_err());
+        assert!(render("{ }", resolve).is_err());
+        assert!(render("{missing}", |name: &str| Err(Error::Config(name.to_string()))).is_err());
+    }
+}
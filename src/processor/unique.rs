@@ -0,0 +1,120 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::error::Error;
+use crate::processor::unl::UnlRow;
+
+// Random redraws before falling back to a numeric suffix
+pub const MAX_ATTEMPTS: u32 = 100;
+// Numeric suffixes tried after the redraws before giving up on the row
+pub const MAX_SUFFIX: u32 = 10_000;
+
+type SeenValues = HashMap<String, HashMap<Vec<usize>, HashSet<Vec<String>>>>;
+
+// Values already written per table and unique scope. A scope is the sorted list of
+// column indices that must be unique together: a single column, or a multi-column key.
+#[derive(Debug, Default)]
+pub struct UniqueTracker {
+    seen: Mutex<SeenValues>,
+}
+
+impl UniqueTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Forget everything recorded for a table, e.g. before a retry processes it again
+    pub fn reset(&self, table_name: &str) -> Result<(), Error> {
+        self.lock()?.remove(table_name);
+        Ok(())
+    }
+
+    // Record the row's values for every scope, with `assignments` applied on top of the row.
+    // Returns false, recording nothing, if any scope already holds the same values.
+    pub fn claim(
+        &self,
+        table_name: &str,
+        scopes: &[Vec<usize>],
+        row: &UnlRow,
+        assignments: &[(usize, String)],
+    ) -> Result<bool, Error> {
+        let keys: Vec<Vec<String>> = scopes.iter()
+            .map(|scope| scope.iter()
+                .map(|idx| assignments.iter()
+                    .find(|(assigned, _)| assigned == idx)
+                    .map(|(_, value)| value.as_str())
+                    .or_else(|| row.get_field(*idx))
+                    .unwrap_or_default()
+                    .to_string())
+                .collect())
+            .collect();
+
+        let mut seen = self.lock()?;
+        let table = seen.entry(table_name.to_string()).or_default();
+        let taken = scopes.iter()
+            .zip(&keys)
+            .any(|(scope, key)| table.get(scope).is_some_and(|values| values.contains(key)));
+        if taken {
+            return Ok(false);
+        }
+
+        for (scope, key) in scopes.iter().zip(keys) {
+            table.entry(scope.clone()).or_default().insert(key);
+        }
+        Ok(true)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, SeenValues>, Error> {
+        self.seen.lock()
+            .map_err(|e| Error::Processing(format!("Failed to lock unique value tracker: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(fields: &[&str]) -> UnlRow {
+        UnlRow { fields: fields.iter().map(|f| f.to_string()).collect() }
+    }
+
+    #[test]
+    fn test_single_column_scope() -> Result<(), Error> {
+        let tracker = UniqueTracker::new();
+        let scopes = vec![vec![1]];
+        let r = row(&["1", "old"]);
+
+        assert!(tracker.claim("repos", &scopes, &r, &[(1, "happy-fox".to_string())])?);
+        assert!(!tracker.claim("repos", &scopes, &r, &[(1, "happy-fox".to_string())])?);
+        assert!(tracker.claim("repos", &scopes, &r, &[(1, "quick-dog".to_string())])?);
+
+        // Tables are tracked independently and can be reset
+        assert!(tracker.claim("projects", &scopes, &r, &[(1, "happy-fox".to_string())])?);
+        tracker.reset("repos")?;
+        assert!(tracker.claim("repos", &scopes, &r, &[(1, "happy-fox".to_string())])?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_column_scope() -> Result<(), Error> {
+        let tracker = UniqueTracker::new();
+        let scopes = vec![vec![0, 1]];
+
+        assert!(tracker.claim("repos", &scopes, &row(&["alice", "x"]), &[(1, "repo".to_string())])?);
+        assert!(tracker.claim("repos", &scopes, &row(&["bob", "x"]), &[(1, "repo".to_string())])?);
+        assert!(!tracker.claim("repos", &scopes, &row(&["alice", "y"]), &[(1, "repo".to_string())])?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_claim_records_nothing() -> Result<(), Error> {
+        let tracker = UniqueTracker::new();
+        let r = row(&["a", "b"]);
+
+        assert!(tracker.claim("t", &[vec![0]], &r, &[])?);
+        // Column 1 is free but column 0 is taken, so neither may be recorded
+        assert!(!tracker.claim("t", &[vec![0], vec![1]], &r, &[])?);
+        assert!(tracker.claim("t", &[vec![1]], &r, &[])?);
+        Ok(())
+    }
+}
@@ -3,11 +3,36 @@ use std::fs::File;
 use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::collections::HashMap;
 use crate::error::Error;
-use crate::config::{Config, ScrubbingConfig, RandomNameConfig};
-use crate::processor::sql::TableInfo;
+use rand::rngs::StdRng;
+use rand::Rng;
+use crate::config::{
+    CombinationFieldConfig, Config, DateMethod, DateRuleConfig, GeneratorConfig, GeneratorKind, LookupConfig, MaskingConfig, NamePart,
+    NumericMethod, NumericRuleConfig, RedactionPattern, Replacement, TokenAlphabet, TokenizeConfig,
+    RandomNameConfig, SourceField, SourceStage,
+};
+use crate::processor::collections;
+use crate::processor::columns::ColumnRules;
+use crate::processor::dates;
+use crate::processor::generators;
+use crate::processor::keys::KeyRemapper;
+use crate::processor::large_objects::LargeObjects;
+use crate::processor::lookup::{LookupIndex, LookupKey};
+use crate::processor::names::{self, Gender, PersonName};
+use crate::processor::numbers::{self, NumericFormat, SumCorrections};
+use crate::processor::filter::{FileStats, Removal, RowFilters};
+use crate::processor::plan::{RulePlan, RuleRef, StandardizeKind};
+use crate::processor::predicate::{DateContext, RowPredicate};
+use crate::processor::mask::mask_value;
+use crate::processor::random::{self, seeded_rng, WordLists};
+use crate::processor::redact::{self, Found, KnownValues, Redactor};
+use crate::processor::sql::{ColumnInfo, TableInfo};
+use crate::processor::template;
+use crate::processor::synthetic::{SyntheticRows, ValueSource, WrittenKeys};
+use crate::processor::tokens::{self, Tokenizer};
+use crate::processor::unique::{self, UniqueTracker};
 
 // Struct to represent a row in a UNL file
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct UnlRow {
     pub fields: Vec<String>,
 }
@@ -29,16 +54,94 @@ impl UnlRow {
         Ok(())
     }
     
+    // Split a record on its unescaped delimiters. A backslash escapes the next character, so
+    // "\|" is a literal pipe, "\\" a backslash and a backslash before a newline continues the
+    // value on the next line. Fields hold the unescaped values.
     pub fn from_line(line: &str) -> Result<Self, Error> {
-        // Remove trailing pipe if it exists to avoid empty last field
-        let line = line.strip_suffix('|').unwrap_or(line);
-        Ok(UnlRow {
-            fields: line.split('|').map(String::from).collect()
-        })
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        // A trailing pipe ends the last field rather than starting an empty one
+        let mut delimited = false;
+        let mut chars = line.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => field.push(chars.next().unwrap_or('\\')),
+                '|' => fields.push(std::mem::take(&mut field)),
+                c => field.push(c),
+            }
+            delimited = c == '|';
+        }
+        if !delimited {
+            fields.push(field);
+        }
+        Ok(UnlRow { fields })
     }
 
+    // The row as a UNL record, escaping backslashes, pipes and newlines in its values
     pub fn to_line(&self) -> String {
-        self.fields.join("|") + "|"  // UNL format requires trailing pipe
+        let mut line = String::new();
+        for field in &self.fields {
+            for c in field.chars() {
+                if matches!(c, '\\' | '|' | '\n') {
+                    line.push('\\');
+                }
+                line.push(c);
+            }
+            line.push('|');  // UNL format requires trailing pipe
+        }
+        line
+    }
+}
+
+// Reads the records of a UNL file, which run on past a newline escaped with a backslash
+pub struct UnlReader<R> {
+    reader: R,
+}
+
+impl UnlReader<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        Ok(UnlReader::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> UnlReader<R> {
+    pub fn new(reader: R) -> Self {
+        UnlReader { reader }
+    }
+
+    // The next record's text without its final newline, still escaped
+    fn next_record(&mut self) -> Result<Option<String>, Error> {
+        let mut record = String::new();
+        loop {
+            let read = self.reader.read_line(&mut record)?;
+            if read == 0 {
+                return Ok((!record.is_empty()).then_some(record));
+            }
+            if !record.ends_with('\n') {
+                return Ok(Some(record));
+            }
+            // An odd number of backslashes before the newline escapes it
+            let body = &record[..record.len() - 1];
+            let backslashes = body.len() - body.trim_end_matches('\\').len();
+            if backslashes.is_multiple_of(2) {
+                record.pop();
+                if record.ends_with('\r') {
+                    record.pop();
+                }
+                return Ok(Some(record));
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for UnlReader<R> {
+    type Item = Result<UnlRow, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_record() {
+            Ok(record) => record.map(|record| UnlRow::from_line(&record)),
+            Err(e) => Some(Err(e)),
+        }
     }
 }
 
@@ -47,6 +150,21 @@ pub struct UnlProcessor {
     table_info: HashMap<String, TableInfo>,
     adjectives: Vec<String>,
     nouns: Vec<String>,
+    word_lists: WordLists,
+    unique_values: UniqueTracker,
+    lookups: LookupIndex,
+    plan: RulePlan,
+    predicates: HashMap<RuleRef, RowPredicate>,
+    row_filters: RowFilters,
+    columns: ColumnRules,
+    // DBDATE format and the day date conditions count back from
+    dates: DateContext,
+    sums: SumCorrections,
+    redactor: Redactor,
+    tokenizer: Tokenizer,
+    keys: KeyRemapper,
+    synthetic: SyntheticRows,
+    large_objects: LargeObjects,
 }
 
 impl UnlProcessor {
@@ -55,176 +173,1350 @@ impl UnlProcessor {
         table_info: HashMap<String, TableInfo>,
         adjectives: Vec<String>,
         nouns: Vec<String>,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, Error> {
+        generators::check_domains(&config.scrubbing.generators)?;
+        check_person_names(&config, &table_info)?;
+        check_date_rules(&config, &table_info)?;
+        check_number_rules(&config, &table_info)?;
+        check_token_columns(&config, &table_info)?;
+        let plan = RulePlan::build(&config)?;
+        let dates = DateContext::new(&config)?;
+        collections::check_rule_writes(&plan, &table_info)?;
+        let mut predicates = HashMap::new();
+        for (table_name, rule) in plan.rules() {
+            let predicate = RowPredicate::compile(table_name, rule.conditions(&config), &table_info, &dates)?;
+            if !predicate.is_empty() {
+                predicates.insert(rule, predicate);
+            }
+        }
+
+        Ok(Self {
+            row_filters: RowFilters::new(&config, &table_info, &dates)?,
+            columns: ColumnRules::new(&config, &table_info)?,
+            dates,
+            sums: SumCorrections::default(),
+            redactor: Redactor::new()?,
+            tokenizer: Tokenizer::new(&config)?,
+            keys: KeyRemapper::new(&config, &table_info)?,
+            synthetic: SyntheticRows::new(&config, &table_info)?,
+            large_objects: LargeObjects::new(&config, &table_info, &plan)?,
+            plan,
+            predicates,
+            lookups: LookupIndex::new(&config),
             config,
             table_info,
             adjectives,
             nouns,
+            word_lists: WordLists::default(),
+            unique_values: UniqueTracker::new(),
+        })
+    }
+
+    pub fn with_word_lists(mut self, word_lists: WordLists) -> Self {
+        self.word_lists = word_lists;
+        self
+    }
+
+    // Tables that must be processed before others so removed rows can cascade, remapped
+    // keys are known and synthetic rows have parents to reference, as (child, parent)
+    pub fn table_dependencies(&self) -> Vec<(String, String)> {
+        let mut dependencies = self.row_filters.dependencies(&self.table_info);
+        dependencies.extend(self.keys.dependencies());
+        dependencies.extend(self.synthetic.dependencies(&self.table_info));
+        dependencies
+    }
+
+    pub fn reference_date(&self) -> chrono::NaiveDate {
+        self.dates.reference_date
+    }
+
+    // Whether tokenize rules run without a token_secret, keyed by export.random_seed
+    pub fn tokens_keyed_by_seed(&self) -> bool {
+        !self.config.tokenize.is_empty() && self.tokenizer.keyed_by_seed()
+    }
+
+    pub fn key_remapper(&self) -> &KeyRemapper {
+        &self.keys
+    }
+
+    pub fn large_objects(&self) -> &LargeObjects {
+        &self.large_objects
+    }
+
+    // Note the large-object files an excluded table's rows reference, so they stay out of the export
+    pub fn record_excluded_files(&self, table_name: &str, input_path: &Path) -> Result<(), Error> {
+        if !self.large_objects.has_columns(table_name) {
+            return Ok(());
         }
+        self.large_objects.reset(table_name)?;
+        for row in UnlReader::open(input_path)? {
+            self.large_objects.observe(table_name, &row?, None, |_| false)?;
+        }
+        Ok(())
     }
 
-    pub fn process_file(&self, table_name: &str, input_path: &Path, output_path: &Path) -> Result<(), Error> {
-        let file = File::open(input_path)?;
-        let reader = BufReader::new(file);
+    // The table's fields as written, if drop_columns removes some of them
+    pub fn kept_fields(&self, table_name: &str) -> Option<Vec<String>> {
+        self.columns.kept_fields(table_name, self.table_info.get(table_name)?)
+    }
+
+    pub fn process_file(&self, table_name: &str, input_path: &Path, output_path: &Path) -> Result<FileStats, Error> {
+        // Start from a clean slate so retries don't see values from the failed attempt
+        self.unique_values.reset(table_name)?;
+        self.lookups.reset(table_name)?;
+        self.row_filters.reset(table_name)?;
+        self.sums.reset(table_name)?;
+        self.keys.reset(table_name)?;
+        self.synthetic.reset(table_name)?;
+        self.large_objects.reset(table_name)?;
+        self.prepare_sums(table_name, input_path)?;
+        self.prepare_keys(table_name, input_path)?;
+        let mut stats = FileStats::default();
+        let is_lookup_parent = !self.lookups.keys_for(table_name).is_empty();
+        let is_remapped = self.keys.touches(table_name);
+        let has_large_objects = self.large_objects.has_columns(table_name);
+        let info = self.table_info.get(table_name);
+        let mut written = info.filter(|_| self.synthetic.has_rows(table_name)).map(WrittenKeys::new);
+        if self.has_unique_rules(table_name) {
+            let row_count = self.count_kept_rows(table_name, input_path)?;
+            self.check_unique_capacity(table_name, row_count)?;
+        }
+
         let output = File::create(output_path)?;
         let mut writer = BufWriter::new(output);
 
-        for line in reader.lines() {
-            let line = line?;
-            let mut row = UnlRow::from_line(&line)?;
-            self.process_row(table_name, &mut row)?;
-            writeln!(writer, "{}", row.to_line())?;
+        let mut trace = self.open_trace(table_name)?;
+        let trace_rows = self.config.verification.logging.trace_rows;
+
+        for row in UnlReader::open(input_path)? {
+            let mut row = row?;
+            stats.rows_read += 1;
+
+            if let Some(removal) = self.row_filters.check(table_name, &row, &self.table_info)? {
+                match removal {
+                    Removal::Filtered { .. } => stats.rows_filtered += 1,
+                    Removal::Cascaded => stats.rows_cascaded += 1,
+                }
+                if removal.cascades() {
+                    self.row_filters.record_removed(table_name, &row, &self.table_info)?;
+                }
+                self.large_objects.observe(table_name, &row, None, |_| false)?;
+                continue;
+            }
+
+            // Trace the first rows kept, numbered by their line in the input file
+            let traced = stats.rows_written() <= trace_rows;
+            let original = (is_lookup_parent || is_remapped || has_large_objects).then(|| row.clone());
+            match trace.as_mut().filter(|_| traced) {
+                Some(trace) => {
+                    for (column, rule) in self.process_row_traced(table_name, &mut row)? {
+                        writeln!(trace, "row {}: {}.{} <- {}", stats.rows_read, table_name, column, rule)?;
+                    }
+                }
+                None => self.process_row(table_name, &mut row)?,
+            }
+
+            if let Some(original) = &original {
+                self.record_lookups(table_name, original, &row)?;
+                if let Some(info) = info {
+                    self.keys.remap(table_name, original, &mut row, &info.fields)?;
+                }
+                self.large_objects.observe(table_name, original, Some(&mut row), |idx| self.columns.keeps(table_name, idx))?;
+            }
+            self.write_row(table_name, row, written.as_mut(), &mut writer)?;
+        }
+
+        // Synthetic rows go after the exported ones, built from the rows as written
+        if let (Some(info), Some(written)) = (info, written.as_mut()) {
+            let source = ValueSource {
+                seed: self.config.export.random_seed,
+                date_format: &self.dates.format,
+                adjectives: &self.adjectives,
+                nouns: &self.nouns,
+            };
+            for row in self.synthetic.generate(table_name, info, written, &source)? {
+                stats.rows_generated += 1;
+                self.write_row(table_name, row, None, &mut writer)?;
+            }
         }
 
         writer.flush()?;
+        if let Some(trace) = trace.as_mut() {
+            trace.flush()?;
+        }
+        Ok(stats)
+    }
+
+    // Write a finished row, keeping its keys for synthetic rows before dropping any columns
+    fn write_row(&self, table_name: &str, row: UnlRow, written: Option<&mut WrittenKeys>, writer: &mut impl Write) -> Result<(), Error> {
+        if let Some(info) = self.table_info.get(table_name) {
+            self.synthetic.record(table_name, &row, info)?;
+        }
+        if let Some(written) = written {
+            written.observe(&row);
+        }
+        let row = self.columns.drop_fields(table_name, row);
+        writeln!(writer, "{}", row.to_line())?;
         Ok(())
     }
 
+    // Per-table trace file in the log directory when rule tracing is enabled
+    fn open_trace(&self, table_name: &str) -> Result<Option<BufWriter<File>>, Error> {
+        let logging = &self.config.verification.logging;
+        if !logging.trace_rules {
+            return Ok(None);
+        }
+
+        let log_dir = Path::new(&logging.directory);
+        std::fs::create_dir_all(log_dir)?;
+        let mut trace = BufWriter::new(File::create(
+            log_dir.join(format!("{}trace_{}.log", logging.prefix, table_name))
+        )?);
+        writeln!(trace, "# Rule that produced each column's final value; unlisted columns keep their exported value")?;
+        Ok(Some(trace))
+    }
+
+    fn has_unique_rules(&self, table_name: &str) -> bool {
+        self.config.scrubbing.random_names.iter().any(|c| c.table == table_name && c.unique)
+            || self.config.scrubbing.generators.iter().any(|c| c.table == table_name && c.unique)
+    }
+
     pub fn process_row(&self, table_name: &str, row: &mut UnlRow) -> Result<(), Error> {
-        // Apply random name scrubbing
-        for config in &self.config.scrubbing.random_names {
-            if config.table == table_name {
-                for field in &config.fields {
-                    if let Some(idx) = find_field_index_by_table(table_name, field, &self.table_info)? {
-                        let new_name = self.generate_random_name(&config.style)?;
-                        row.set_field(idx, new_name)?;
+        // Keep the exported values for rules that read them before scrubbing
+        let original = row.clone();
+
+        for step in self.plan.steps(table_name) {
+            self.apply_rule(table_name, step.rule, &original, row)?;
+        }
+
+        Ok(())
+    }
+
+    // Like process_row, also returning which rule produced each column's final value
+    pub fn process_row_traced(&self, table_name: &str, row: &mut UnlRow) -> Result<Vec<(String, String)>, Error> {
+        let original = row.clone();
+        let mut producers: Vec<(String, String)> = Vec::new();
+
+        for step in self.plan.steps(table_name) {
+            if !self.apply_rule(table_name, step.rule, &original, row)? {
+                continue;
+            }
+            for column in &step.writes {
+                if find_field_index_by_table(table_name, column, &self.table_info)?.is_none() {
+                    continue;
+                }
+                producers.retain(|(c, _)| c != column);
+                producers.push((column.clone(), step.rule.describe()));
+            }
+        }
+
+        Ok(producers)
+    }
+
+    fn apply_rule(&self, table_name: &str, rule: RuleRef, original: &UnlRow, row: &mut UnlRow) -> Result<bool, Error> {
+        // Conditions test the row as exported, so earlier rules can't change which rules apply
+        if let Some(predicate) = self.predicates.get(&rule) {
+            if !predicate.matches(original)? {
+                return Ok(false);
+            }
+        }
+
+        match rule {
+            RuleRef::RandomName(i) => self.apply_random_name(table_name, &self.config.scrubbing.random_names[i], row),
+            RuleRef::Generator(i) => self.apply_generator(table_name, &self.config.scrubbing.generators[i], row),
+            RuleRef::Standardize(kind, i) => self.apply_standardization(table_name, kind, i, row),
+            RuleRef::Mask(i) => self.apply_masking(table_name, &self.config.masking[i], row),
+            RuleRef::Date(i) => self.apply_dates(table_name, i, original, row),
+            RuleRef::Number(i) => self.apply_numbers(table_name, i, original, row),
+            RuleRef::Redact(i) => self.apply_redaction(table_name, i, original, row),
+            RuleRef::Token(i) => self.apply_tokens(table_name, &self.config.tokenize[i], row),
+            RuleRef::Combination(i) => {
+                self.apply_combination(table_name, &self.config.combination_fields[i], original, row)
+            }
+            RuleRef::NullFields(i) => self.apply_null_fields(table_name, i, row),
+        }?;
+        Ok(true)
+    }
+
+    // Write NULL, or the rule's resolved default, to each field
+    fn apply_null_fields(&self, table_name: &str, rule: usize, row: &mut UnlRow) -> Result<(), Error> {
+        for (field, value) in self.columns.null_values(rule) {
+            if let Some(idx) = find_field_index_by_table(table_name, field, &self.table_info)? {
+                row.set_field(idx, value.clone())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_random_name(&self, table_name: &str, config: &RandomNameConfig, row: &mut UnlRow) -> Result<(), Error> {
+        if config.style == "person" {
+            return self.apply_person_name(table_name, config, row);
+        }
+
+        for field in &config.fields {
+            if let Some(idx) = find_field_index_by_table(table_name, field, &self.table_info)? {
+                let original = row.get_field(idx).unwrap_or_default();
+                let gender = self.name_gender(table_name, config, original, row)?;
+                let scopes = self.unique_scopes(table_name, config.unique, &[idx])?;
+                let assignments = self.generate_unique(
+                    table_name,
+                    &scopes,
+                    row,
+                    &["name", table_name, field, original],
+                    |rng| Ok(vec![(idx, self.generate_random_name(config, gender, rng)?)]),
+                )?;
+                apply_assignments(row, assignments)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Generate one person and split it across the configured fields so first and last names agree
+    fn apply_person_name(&self, table_name: &str, config: &RandomNameConfig, row: &mut UnlRow) -> Result<(), Error> {
+        let parts = person_parts(table_name, config)?;
+
+        let mut indices = Vec::new();
+        for field in &config.fields {
+            indices.push(find_field_index_by_table(table_name, field, &self.table_info)?);
+        }
+
+        // The original first name drives keep_gender; a full name contributes its first word
+        let original_first = parts.iter()
+            .zip(&indices)
+            .find_map(|(part, idx)| match (part, idx) {
+                (NamePart::First, Some(idx)) => row.get_field(*idx),
+                (NamePart::Full, Some(idx)) => row.get_field(*idx)
+                    .and_then(|full| full.split_whitespace().next()),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let gender = self.name_gender(table_name, config, original_first, row)?;
+
+        let originals: Vec<&str> = indices.iter()
+            .map(|idx| idx.and_then(|idx| row.get_field(idx)).unwrap_or_default())
+            .collect();
+        let originals = originals.join("|");
+        let fields = config.fields.join(",");
+
+        // A unique person rule makes the combination of its fields unique
+        let present: Vec<usize> = indices.iter().flatten().copied().collect();
+        let scopes = self.unique_scopes(table_name, config.unique, &present)?;
+        let assignments = self.generate_unique(
+            table_name,
+            &scopes,
+            row,
+            &["person", table_name, &fields, &originals],
+            |rng| {
+                let person = PersonName::generate(gender, rng);
+                Ok(parts.iter()
+                    .zip(&indices)
+                    .filter_map(|(part, idx)| idx.map(|idx| (idx, person.part(*part))))
+                    .collect())
+            },
+        )?;
+        apply_assignments(row, assignments)
+    }
+
+    // Gender for person styles: an explicit gender column wins over guessing from the original name
+    fn name_gender(
+        &self,
+        table_name: &str,
+        config: &RandomNameConfig,
+        original_first: &str,
+        row: &UnlRow,
+    ) -> Result<Option<Gender>, Error> {
+        if let Some(gender_field) = &config.gender_field {
+            let idx = find_field_index_by_table(table_name, gender_field, &self.table_info)?
+                .ok_or_else(|| Error::Processing(format!(
+                    "Gender field '{}' not found in table '{}'",
+                    gender_field,
+                    table_name
+                )))?;
+            if let Some(gender) = row.get_field(idx).and_then(Gender::from_code) {
+                return Ok(Some(gender));
+            }
+        }
+        if config.keep_gender {
+            return Ok(Gender::from_first_name(original_first));
+        }
+        Ok(None)
+    }
+
+    fn generate_random_name(&self, config: &RandomNameConfig, gender: Option<Gender>, rng: &mut StdRng) -> Result<String, Error> {
+        match config.style.as_str() {
+            "github" if !config.dictionaries.is_empty() => {
+                let dictionaries = config.dictionaries.iter()
+                    .map(|name| self.word_lists.dictionaries.get(name)
+                        .map(Vec::as_slice)
+                        .ok_or_else(|| Error::Config(format!("Unknown dictionary: {}", name))))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                random::generate_from_dictionaries(&dictionaries, rng)
+                    .ok_or_else(|| Error::Processing("Dictionary has no words".to_string()))
+            }
+            "github" => {
+                let adjectives = self.rule_words(config.adjectives.as_ref(), &self.adjectives)?;
+                let nouns = self.rule_words(config.nouns.as_ref(), &self.nouns)?;
+                random::generate_random_name(adjectives, nouns, rng)
+                    .ok_or_else(|| Error::Processing("No adjectives or nouns available".to_string()))
+            }
+            "first_name" => Ok(names::first_name(gender, rng)),
+            "last_name" => Ok(names::last_name(rng)),
+            _ => Err(Error::Config(format!("Unsupported name style: {}", config.style)))
+        }
+    }
+
+    // A rule's own word list file if it names one, otherwise the global list
+    fn rule_words<'a>(&'a self, path: Option<&String>, default: &'a [String]) -> Result<&'a [String], Error> {
+        match path {
+            Some(path) => self.word_lists.files.get(path)
+                .map(Vec::as_slice)
+                .ok_or_else(|| Error::Config(format!("Word list '{}' was not loaded", path))),
+            None => Ok(default),
+        }
+    }
+
+    fn apply_generator(&self, table_name: &str, gen_config: &GeneratorConfig, row: &mut UnlRow) -> Result<(), Error> {
+        for field in &gen_config.fields {
+            let idx = match find_field_index_by_table(table_name, field, &self.table_info)? {
+                Some(idx) => idx,
+                None => continue,
+            };
+            let original = row.get_field(idx).unwrap_or_default();
+            // Keep Informix NULLs as NULLs
+            if original.is_empty() {
+                continue;
+            }
+
+            let mut name_parts = Vec::new();
+            for name_field in &gen_config.name_fields {
+                let name_idx = find_field_index_by_table(table_name, name_field, &self.table_info)?
+                    .ok_or_else(|| Error::Processing(format!(
+                        "Name field '{}' not found in table '{}'",
+                        name_field,
+                        table_name
+                    )))?;
+                name_parts.push(row.get_field(name_idx).unwrap_or_default());
+            }
+
+            let scopes = self.unique_scopes(table_name, gen_config.unique, &[idx])?;
+            let assignments = self.generate_unique(
+                table_name,
+                &scopes,
+                row,
+                &["generator", table_name, field, original],
+                |rng| {
+                    let value = match gen_config.generator {
+                        GeneratorKind::Email => {
+                            generators::email(&name_parts, gen_config.domain.as_deref(), rng)
+                        }
+                        kind => generators::generate(kind, rng),
+                    };
+                    Ok(vec![(idx, value)])
+                },
+            )?;
+            apply_assignments(row, assignments)?;
+        }
+
+        Ok(())
+    }
+
+    // Scopes a generated value must be unique in: the written columns themselves when the
+    // rule asks for unique output, plus every schema unique key that contains one of them
+    fn unique_scopes(&self, table_name: &str, unique: bool, indices: &[usize]) -> Result<Vec<Vec<usize>>, Error> {
+        let mut scopes = Vec::new();
+        if indices.is_empty() {
+            return Ok(scopes);
+        }
+
+        if unique {
+            let mut scope = indices.to_vec();
+            scope.sort_unstable();
+            scopes.push(scope);
+        }
+
+        if self.config.scrubbing.unique_from_schema {
+            let table = self.table_info.get(table_name)
+                .ok_or_else(|| Error::Processing(format!("Table '{}' not found", table_name)))?;
+            for key in &table.unique_keys {
+                let mut scope = key.iter()
+                    .filter_map(|column| find_field_index(column, &table.fields))
+                    .collect::<Vec<_>>();
+                scope.sort_unstable();
+                if scope.len() == key.len() && scope.iter().any(|idx| indices.contains(idx)) && !scopes.contains(&scope) {
+                    scopes.push(scope);
+                }
+            }
+        }
+
+        Ok(scopes)
+    }
+
+    // Generate values that are unique in every scope. Collisions are redrawn with a
+    // deterministic per-attempt seed, then disambiguated with a numeric suffix.
+    fn generate_unique<F>(
+        &self,
+        table_name: &str,
+        scopes: &[Vec<usize>],
+        row: &UnlRow,
+        seed_parts: &[&str],
+        mut generate: F,
+    ) -> Result<Vec<(usize, String)>, Error>
+    where
+        F: FnMut(&mut StdRng) -> Result<Vec<(usize, String)>, Error>,
+    {
+        let seed = self.config.export.random_seed;
+        let first = generate(&mut seeded_rng(seed, seed_parts))?;
+        if scopes.is_empty() || self.unique_values.claim(table_name, scopes, row, &first)? {
+            return Ok(first);
+        }
+
+        for attempt in 1..unique::MAX_ATTEMPTS {
+            let attempt = attempt.to_string();
+            let mut parts = seed_parts.to_vec();
+            parts.push(&attempt);
+            let candidate = generate(&mut seeded_rng(seed, &parts))?;
+            if self.unique_values.claim(table_name, scopes, row, &candidate)? {
+                return Ok(candidate);
+            }
+        }
+
+        // The suffixed value must still fit its column, or the load would truncate it
+        let width = first.last()
+            .and_then(|(idx, _)| self.table_info.get(table_name)?.columns.get(*idx))
+            .and_then(ColumnInfo::width);
+        for suffix in 2..unique::MAX_SUFFIX {
+            let mut candidate = first.clone();
+            if let Some((_, value)) = candidate.last_mut() {
+                match with_suffix(value, suffix, width) {
+                    Some(suffixed) => *value = suffixed,
+                    None => break,
+                }
+            }
+            if self.unique_values.claim(table_name, scopes, row, &candidate)? {
+                return Ok(candidate);
+            }
+        }
+        Err(Error::Processing(format!(
+            "Could not find a unique value for table '{}' after {} redraws and suffixes up to {}",
+            table_name,
+            unique::MAX_ATTEMPTS,
+            unique::MAX_SUFFIX
+        )))
+    }
+
+    // Fail early when a unique rule cannot produce enough distinct values for the table
+    fn check_unique_capacity(&self, table_name: &str, row_count: usize) -> Result<(), Error> {
+        let mut capacities = Vec::new();
+        for config in self.config.scrubbing.random_names.iter().filter(|c| c.table == table_name && c.unique) {
+            capacities.push((config.fields.join(", "), self.name_space(config)?));
+        }
+        for config in self.config.scrubbing.generators.iter().filter(|c| c.table == table_name && c.unique) {
+            capacities.push((config.fields.join(", "), generators::space(config.generator)));
+        }
+
+        for (fields, space) in capacities {
+            if space < row_count as u128 {
+                return Err(Error::Config(format!(
+                    "Unique rule for {}.{} can generate only {} distinct values but the table has {} rows",
+                    table_name,
+                    fields,
+                    space,
+                    row_count
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // Number of distinct values a random name rule can produce
+    fn name_space(&self, config: &RandomNameConfig) -> Result<u128, Error> {
+        let first_names = (names::FEMALE_FIRST_NAMES.len() + names::MALE_FIRST_NAMES.len()) as u128;
+        let surnames = names::SURNAMES.len() as u128;
+        let space = match config.style.as_str() {
+            "github" if !config.dictionaries.is_empty() => config.dictionaries.iter()
+                .map(|name| self.word_lists.dictionaries.get(name).map_or(0, |words| words.len() as u128))
+                .product(),
+            "github" => {
+                let adjectives = self.rule_words(config.adjectives.as_ref(), &self.adjectives)?;
+                let nouns = self.rule_words(config.nouns.as_ref(), &self.nouns)?;
+                adjectives.len() as u128 * nouns.len() as u128
+            }
+            "first_name" => first_names,
+            "last_name" => surnames,
+            "person" => person_parts(&config.table, config)?.iter()
+                .map(|part| match part {
+                    NamePart::First | NamePart::Middle => first_names,
+                    NamePart::MiddleInitial => 26,
+                    NamePart::Last => surnames,
+                    NamePart::Full => first_names * surnames,
+                })
+                .product(),
+            _ => u128::MAX,
+        };
+        Ok(space)
+    }
+
+    fn apply_standardization(&self, table_name: &str, kind: StandardizeKind, index: usize, row: &mut UnlRow) -> Result<(), Error> {
+        let standardize = kind.field(&self.config.standardize);
+        let field_config = &standardize.fields[index];
+        if let Some(idx) = find_field_index_by_table(table_name, &field_config.field, &self.table_info)? {
+            row.set_field(idx, standardize.value.clone())?;
+        }
+
+        Ok(())
+    }
+
+    fn apply_masking(&self, table_name: &str, mask_config: &MaskingConfig, row: &mut UnlRow) -> Result<(), Error> {
+        for field in &mask_config.fields {
+            if let Some(idx) = find_field_index_by_table(table_name, field, &self.table_info)? {
+                let original = row.get_field(idx).unwrap_or_default();
+                // Seed from the value itself so identical values mask identically in every table
+                let column = self.column_info(table_name, field);
+                let masked = collections::map_value(column, original, |value| {
+                    let mut rng = seeded_rng(self.config.export.random_seed, &["mask", value]);
+                    Ok(mask_value(value, mask_config.keep_last, &mut rng))
+                })?;
+                row.set_field(idx, masked)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn apply_dates(&self, table_name: &str, rule: usize, original: &UnlRow, row: &mut UnlRow) -> Result<(), Error> {
+        let date_config = &self.config.dates[rule];
+        let days = match date_config.method {
+            DateMethod::Shift => self.date_offset(table_name, date_config, original),
+            _ => 0,
+        };
+
+        for field in &date_config.fields {
+            let idx = match find_field_index_by_table(table_name, field, &self.table_info)? {
+                Some(idx) => idx,
+                None => continue,
+            };
+            let value = row.get_field(idx).unwrap_or_default();
+            // Keep Informix NULLs as NULLs
+            if value.trim().is_empty() {
+                continue;
+            }
+
+            let changed = match date_config.method {
+                DateMethod::Shift => dates::shift_value(value, &self.dates.format, days),
+                DateMethod::Year => dates::generalize_value(value, &self.dates.format, false),
+                DateMethod::Month => dates::generalize_value(value, &self.dates.format, true),
+            };
+            let changed = changed.ok_or_else(|| Error::Processing(format!(
+                "Value '{}' of {}.{} is not a date (DBDATE {}) or datetime",
+                value,
+                table_name,
+                field,
+                self.config.export.dbdate
+            )))?;
+            row.set_field(idx, changed)?;
+        }
+
+        Ok(())
+    }
+
+    // Days to shift a row's dates by, the same for every row of the same entity
+    fn date_offset(&self, table_name: &str, date_config: &DateRuleConfig, original: &UnlRow) -> i64 {
+        let entity = date_config.entity.as_deref().unwrap_or(table_name);
+        let mut parts = vec!["date", entity];
+        // The entity fields are checked to exist at startup
+        if let Some(info) = self.table_info.get(table_name) {
+            for field in entity_fields(date_config, info) {
+                if let Some(idx) = find_field_index(field, &info.fields) {
+                    parts.push(original.get_field(idx).unwrap_or_default());
+                }
+            }
+        }
+
+        let mut rng = seeded_rng(self.config.export.random_seed, &parts);
+        let days = rng.gen_range(1..=date_config.max_shift_days as i64);
+        if rng.gen_bool(0.5) { -days } else { days }
+    }
+
+    fn apply_numbers(&self, table_name: &str, rule: usize, original: &UnlRow, row: &mut UnlRow) -> Result<(), Error> {
+        let numeric_config = &self.config.numbers[rule];
+        for field in &numeric_config.fields {
+            let idx = match find_field_index_by_table(table_name, field, &self.table_info)? {
+                Some(idx) => idx,
+                None => continue,
+            };
+            let value = row.get_field(idx).unwrap_or_default();
+            // Keep Informix NULLs as NULLs
+            if value.trim().is_empty() {
+                continue;
+            }
+
+            let (_, mut units, scale) = self.perturb(table_name, numeric_config, field, original, value)?;
+            if numeric_config.preserve_sum {
+                let format = numeric_format(&self.table_info, table_name, field)?;
+                units = self.sums.next(table_name, rule, field, units, &format)?;
+            }
+            row.set_field(idx, numbers::format_units(units, scale))?;
+        }
+        Ok(())
+    }
+
+    // The exported and perturbed value in units of the column's scale, and that scale
+    fn perturb(
+        &self,
+        table_name: &str,
+        numeric_config: &NumericRuleConfig,
+        field: &str,
+        original: &UnlRow,
+        value: &str,
+    ) -> Result<(i128, i128, u32), Error> {
+        let format = numeric_format(&self.table_info, table_name, field)?;
+        let number: f64 = value.trim().parse().map_err(|_| Error::Processing(format!(
+            "Value '{}' of {}.{} is not a number",
+            value,
+            table_name,
+            field
+        )))?;
+
+        let changed = match numeric_config.method {
+            NumericMethod::Noise => {
+                // Seed from the whole exported row so equal amounts in different rows differ
+                let line = original.to_line();
+                let mut rng = seeded_rng(self.config.export.random_seed, &["number", table_name, field, &line]);
+                numbers::noise(number, numeric_config.noise, &mut rng)
+            }
+            // The size is checked to be above 0 at startup
+            NumericMethod::Bucket => numbers::bucket(number, numeric_config.bucket_size.unwrap_or(1.0)),
+        };
+
+        let scale = format.scale_for(value);
+        Ok((numbers::to_units(number, scale), format.clamp(numbers::to_units(changed, scale)), scale))
+    }
+
+    // Records of the table's file that the row filters keep
+    fn count_kept_rows(&self, table_name: &str, input_path: &Path) -> Result<usize, Error> {
+        let mut kept = 0;
+        for row in UnlReader::open(input_path)? {
+            if self.row_filters.check(table_name, &row?, &self.table_info)?.is_none() {
+                kept += 1;
+            }
+        }
+        Ok(kept)
+    }
+
+    // First pass for key remapping: the keys of the rows that will be written
+    fn prepare_keys(&self, table_name: &str, input_path: &Path) -> Result<(), Error> {
+        let idx = match self.keys.key_column(table_name) {
+            Some(column) => find_field_index_by_table(table_name, column, &self.table_info)?,
+            None => return Ok(()),
+        };
+        let idx = idx.ok_or_else(|| Error::Config(format!("Key column of table '{}' not found", table_name)))?;
+
+        let mut exported = Vec::new();
+        for row in UnlReader::open(input_path)? {
+            let row = row?;
+            if self.row_filters.check(table_name, &row, &self.table_info)?.is_some() {
+                continue;
+            }
+            exported.push(row.get_field(idx).unwrap_or_default().trim().to_string());
+        }
+        self.keys.prepare(table_name, exported, self.config.export.random_seed)
+    }
+
+    // First pass for numbers rules that preserve sums: each column's exported and perturbed
+    // totals over the rows that will be written
+    fn prepare_sums(&self, table_name: &str, input_path: &Path) -> Result<(), Error> {
+        let rules: Vec<(usize, &NumericRuleConfig)> = self.config.numbers.iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.table == table_name && rule.preserve_sum)
+            .collect();
+        if rules.is_empty() {
+            return Ok(());
+        }
+
+        let mut totals: HashMap<(usize, &str), (i128, i128, i128)> = HashMap::new();
+        for row in UnlReader::open(input_path)? {
+            let row = row?;
+            if self.row_filters.check(table_name, &row, &self.table_info)?.is_some() {
+                continue;
+            }
+            for (i, rule) in &rules {
+                if let Some(predicate) = self.predicates.get(&RuleRef::Number(*i)) {
+                    if !predicate.matches(&row)? {
+                        continue;
+                    }
+                }
+                for field in &rule.fields {
+                    let value = find_field_index_by_table(table_name, field, &self.table_info)?
+                        .and_then(|idx| row.get_field(idx))
+                        .unwrap_or_default();
+                    if value.trim().is_empty() {
+                        continue;
                     }
+                    let (exported, perturbed, _) = self.perturb(table_name, rule, field, &row, value)?;
+                    let total = totals.entry((*i, field.as_str())).or_default();
+                    total.0 += exported;
+                    total.1 += perturbed;
+                    total.2 += 1;
                 }
             }
         }
 
-        // Apply standardization
-        self.apply_standardization(table_name, row)?;
+        for ((rule, field), (exported, perturbed, rows)) in totals {
+            self.sums.prepare(table_name, rule, field, exported, perturbed, rows)?;
+        }
+        Ok(())
+    }
+
+    fn apply_redaction(&self, table_name: &str, rule: usize, original: &UnlRow, row: &mut UnlRow) -> Result<(), Error> {
+        let redaction = &self.config.redaction[rule];
+
+        // Each known column's exported value, which is searched for, and its current value
+        let mut known = Vec::new();
+        for field in &redaction.known_fields {
+            let idx = find_field_index_by_table(table_name, field, &self.table_info)?
+                .ok_or_else(|| Error::Config(format!(
+                    "Known field '{}' not found in table '{}'",
+                    field,
+                    table_name
+                )))?;
+            known.push((
+                field.as_str(),
+                original.get_field(idx).unwrap_or_default(),
+                row.get_field(idx).unwrap_or_default().to_string(),
+            ));
+        }
+        let searched: Vec<(&str, &str)> = known.iter().map(|(field, exported, _)| (*field, *exported)).collect();
+        let searched = KnownValues::new(&searched)?;
 
-        // Apply field combinations
-        self.apply_combinations(table_name, row)?;
+        for field in &redaction.fields {
+            let idx = match find_field_index_by_table(table_name, field, &self.table_info)? {
+                Some(idx) => idx,
+                None => continue,
+            };
+            let text = row.get_field(idx).unwrap_or_default();
+            if text.is_empty() {
+                continue;
+            }
+
+            let width = self.column_width(table_name, field);
+            let redacted = collections::map_value(self.column_info(table_name, field), text, |text| {
+                let mut redacted = self.redactor.redact(text, &redaction.patterns, &searched, |found, matched| {
+                    match redaction.replacement {
+                        Replacement::Placeholder => redact::placeholder(found),
+                        Replacement::Pseudonym => self.redaction_pseudonym(found, matched, &known),
+                    }
+                });
+                if let Some(width) = width {
+                    redact::truncate(&mut redacted, width);
+                }
+                Ok(redacted)
+            })?;
+            row.set_field(idx, redacted)?;
+        }
+        Ok(())
+    }
+
+    // A known value becomes its column's scrubbed value; found patterns become fake values
+    // seeded from the text, so the same address reads the same everywhere
+    fn redaction_pseudonym(&self, found: Found<'_>, matched: &str, known: &[(&str, &str, String)]) -> String {
+        let matched = matched.to_lowercase();
+        let mut rng = seeded_rng(self.config.export.random_seed, &["redact", &matched]);
+        match found {
+            Found::Known(field) => known.iter()
+                .find(|(f, exported, current)| *f == field && !current.is_empty() && !current.eq_ignore_ascii_case(exported))
+                .map(|(_, _, current)| current.clone())
+                // Never write the value back when its column wasn't scrubbed
+                .unwrap_or_else(|| redact::placeholder(found)),
+            Found::Pattern(RedactionPattern::Email) => generators::email(&[], None, &mut rng),
+            Found::Pattern(RedactionPattern::Phone) => generators::phone(&mut rng),
+            Found::Pattern(RedactionPattern::Url) => format!("https://www.example.com/{:08x}", rng.gen::<u32>()),
+        }
+    }
+
+    fn apply_tokens(&self, table_name: &str, token_config: &TokenizeConfig, row: &mut UnlRow) -> Result<(), Error> {
+        for field in &token_config.fields {
+            let idx = match find_field_index_by_table(table_name, field, &self.table_info)? {
+                Some(idx) => idx,
+                None => continue,
+            };
+            let value = row.get_field(idx).unwrap_or_default();
+            // Keep Informix NULLs as NULLs
+            if value.is_empty() {
+                continue;
+            }
+
+            let length = token_length(token_config);
+            let token = collections::map_value(self.column_info(table_name, field), value, |value| {
+                Ok(self.tokenizer.token(value, &token_config.prefix, length, token_config.alphabet))
+            })?;
+            row.set_field(idx, token)?;
+        }
+        Ok(())
+    }
+
+    // Declared width of a character column, or of a collection column's elements, from the schema
+    fn column_width(&self, table_name: &str, field: &str) -> Option<usize> {
+        self.column_info(table_name, field).and_then(declared_width)
+    }
+
+    fn column_info(&self, table_name: &str, field: &str) -> Option<&ColumnInfo> {
+        self.table_info.get(table_name)?
+            .columns.iter()
+            .find(|c| c.name.eq_ignore_ascii_case(field))
+    }
+
+    fn apply_combination(
+        &self,
+        table_name: &str,
+        combo_config: &CombinationFieldConfig,
+        original: &UnlRow,
+        row: &mut UnlRow,
+    ) -> Result<(), Error> {
+        // Resolve every source against the row as it stands before this combination writes
+        let mut values = Vec::new();
+        for field_config in &combo_config.fields {
+            values.push(self.combination_source_value(table_name, field_config, original, row)?);
+        }
+
+        let combined = match &combo_config.format {
+            Some(format) => template::render(format, |name| {
+                if let Some(pos) = combo_config.fields.iter().position(|f| f.name() == Some(name)) {
+                    return Ok(values[pos].clone());
+                }
+                // Not a declared source: any column of the table, after scrubbing
+                let idx = find_field_index_by_table(table_name, name, &self.table_info)?
+                    .ok_or_else(|| Error::Processing(format!(
+                        "Template field '{}' not found in table '{}'",
+                        name,
+                        table_name
+                    )))?;
+                Ok(row.get_field(idx).unwrap_or_default().to_string())
+            })?,
+            None => values.join(&combo_config.separator),
+        };
+
+        // Find target field index
+        let target_idx = find_field_index_by_table(
+            table_name,
+            &combo_config.target_field,
+            &self.table_info
+        )?
+        .ok_or_else(|| Error::Processing(format!(
+            "Target field '{}' not found in table '{}'",
+            combo_config.target_field,
+            table_name
+        )))?;
+
+        row.set_field(target_idx, combined)?;
 
         Ok(())
     }
 
-    fn generate_random_name(&self, style: &str) -> Result<String, Error> {
-        use rand::seq::SliceRandom;
-        let mut rng = rand::thread_rng();
-        
-        match style {
-            "github" => {
-                let adj = self.adjectives.choose(&mut rng)
-                    .ok_or_else(|| Error::Processing("No adjectives available".to_string()))?;
-                let noun = self.nouns.choose(&mut rng)
-                    .ok_or_else(|| Error::Processing("No nouns available".to_string()))?;
-                Ok(format!("{}-{}", adj, noun))
+    fn combination_source_value(
+        &self,
+        table_name: &str,
+        field_config: &SourceField,
+        original: &UnlRow,
+        row: &UnlRow,
+    ) -> Result<String, Error> {
+        let source_field = match (&field_config.source_field, &field_config.literal) {
+            (Some(source_field), None) => source_field,
+            (None, Some(literal)) => return Ok(literal.clone()),
+            _ => return Err(Error::Config(format!(
+                "Combination source in table '{}' needs exactly one of source_field or literal",
+                table_name
+            ))),
+        };
+
+        let value = match &field_config.lookup {
+            Some(lookup) => self.lookup_value(table_name, lookup, source_field, field_config.stage, original)?,
+            None => {
+                let idx = find_field_index_by_table(table_name, source_field, &self.table_info)?
+                    .ok_or_else(|| Error::Processing(format!(
+                        "Source field '{}' not found in table '{}'",
+                        source_field,
+                        table_name
+                    )))?;
+
+                let source_row = match field_config.stage {
+                    SourceStage::BeforeScrub => original,
+                    SourceStage::AfterScrub => row,
+                };
+                source_row.get_field(idx)
+                    .ok_or_else(|| Error::Processing(format!(
+                        "Field index {} out of bounds for row with {} fields",
+                        idx,
+                        source_row.fields.len()
+                    )))?
+                    .to_string()
+            }
+        };
+
+        match &field_config.random_style {
+            Some(style) => {
+                // The random_names rule scrubbing the source column brings its word lists and
+                // gender settings, so a name combined here matches the scrubbed column
+                let source_table = field_config.lookup.as_ref().map_or(table_name, |lookup| lookup.table.as_str());
+                let rule = self.config.scrubbing.random_names.iter()
+                    .find(|rule| rule.table == source_table && rule.fields.contains(source_field));
+                let name_config = RandomNameConfig {
+                    style: style.clone(),
+                    ..rule.cloned().unwrap_or_else(|| RandomNameConfig {
+                        table: source_table.to_string(),
+                        fields: vec![source_field.clone()],
+                        ..Default::default()
+                    })
+                };
+                // A looked up row's gender column isn't at hand, only its name
+                let gender = match &field_config.lookup {
+                    None => self.name_gender(table_name, &name_config, &value, original)?,
+                    Some(_) if name_config.keep_gender => Gender::from_first_name(&value),
+                    Some(_) => None,
+                };
+                let mut rng = seeded_rng(
+                    self.config.export.random_seed,
+                    &["name", source_table, source_field, &value],
+                );
+                self.generate_random_name(&name_config, gender, &mut rng)
+            }
+            None => Ok(value),
+        }
+    }
+
+    // Value of a parent table's column for the row this row's join field points at
+    fn lookup_value(
+        &self,
+        table_name: &str,
+        lookup: &LookupConfig,
+        source_field: &str,
+        stage: SourceStage,
+        original: &UnlRow,
+    ) -> Result<String, Error> {
+        let join_idx = find_field_index_by_table(table_name, &lookup.join_field, &self.table_info)?
+            .ok_or_else(|| Error::Processing(format!(
+                "Join field '{}' not found in table '{}'",
+                lookup.join_field,
+                table_name
+            )))?;
+
+        // Joins use the exported values on both sides; a NULL reference has nothing to look up
+        let join_value = original.get_field(join_idx).unwrap_or_default();
+        if join_value.is_empty() {
+            return Ok(String::new());
+        }
+
+        self.lookups.get(&LookupKey::new(lookup, source_field, stage), join_value)?
+            .ok_or_else(|| Error::Processing(format!(
+                "No row in '{}' with {} = '{}' for {}.{}",
+                lookup.table,
+                lookup.key,
+                join_value,
+                table_name,
+                lookup.join_field
+            )))
+    }
+
+    // Remember this parent row's values for the child tables that look them up
+    fn record_lookups(&self, table_name: &str, original: &UnlRow, row: &UnlRow) -> Result<(), Error> {
+        for key in self.lookups.keys_for(table_name) {
+            let field_index = |field: &str| -> Result<usize, Error> {
+                find_field_index_by_table(table_name, field, &self.table_info)?
+                    .ok_or_else(|| Error::Processing(format!(
+                        "Lookup field '{}' not found in table '{}'",
+                        field,
+                        table_name
+                    )))
+            };
+            let key_idx = field_index(&key.key)?;
+            let field_idx = field_index(&key.field)?;
+
+            let source_row = match key.stage {
+                SourceStage::BeforeScrub => original,
+                SourceStage::AfterScrub => row,
+            };
+            self.lookups.record(
+                key,
+                original.get_field(key_idx).unwrap_or_default().to_string(),
+                source_row.get_field(field_idx).unwrap_or_default().to_string(),
+            )?;
+        }
+        Ok(())
+    }
+
+}
+
+// Function to find field index in a table schema
+pub fn find_field_index(field_name: &str, table_fields: &[String]) -> Option<usize> {
+    table_fields.iter().position(|f| f == field_name)
+}
+
+// Function to find field index using table name and field name
+pub fn find_field_index_by_table(
+    table_name: &str,
+    field_name: &str,
+    table_info: &HashMap<String, TableInfo>
+) -> Result<Option<usize>, Error> {
+    if let Some(table) = table_info.get(table_name) {
+        Ok(find_field_index(field_name, &table.fields))
+    } else {
+        Err(Error::Processing(format!("Table '{}' not found", table_name)))
+    }
+}
+
+// Which part of a generated person goes into each field of a person-style rule
+fn person_parts(table_name: &str, config: &RandomNameConfig) -> Result<Vec<NamePart>, Error> {
+    let parts = if config.parts.is_empty() {
+        match config.fields.len() {
+            1 => vec![NamePart::Full],
+            2 => vec![NamePart::First, NamePart::Last],
+            3 => vec![NamePart::First, NamePart::Middle, NamePart::Last],
+            n => return Err(Error::Config(format!(
+                "Person name style for table '{}' needs explicit parts for {} fields",
+                table_name,
+                n
+            ))),
+        }
+    } else {
+        config.parts.clone()
+    };
+    if parts.len() != config.fields.len() {
+        return Err(Error::Config(format!(
+            "Person name style for table '{}' has {} fields but {} parts",
+            table_name,
+            config.fields.len(),
+            parts.len()
+        )));
+    }
+    Ok(parts)
+}
+
+// Person-style rules must split into known parts and name a gender column the table has,
+// found before any table is written rather than at its first row
+fn check_person_names(config: &Config, table_info: &HashMap<String, TableInfo>) -> Result<(), Error> {
+    for rule in config.scrubbing.random_names.iter().filter(|rule| rule.style == "person") {
+        person_parts(&rule.table, rule)?;
+        let (gender_field, info) = match (&rule.gender_field, table_info.get(&rule.table)) {
+            (Some(gender_field), Some(info)) => (gender_field, info),
+            _ => continue,
+        };
+        if find_field_index(gender_field, &info.fields).is_none() {
+            return Err(Error::Config(format!(
+                "Gender field '{}' not found in table '{}'",
+                gender_field,
+                rule.table
+            )));
+        }
+    }
+    Ok(())
+}
+
+// Shifting and generalizing work on whole dates, so DATETIME columns must start at year
+fn check_date_rules(config: &Config, table_info: &HashMap<String, TableInfo>) -> Result<(), Error> {
+    for (i, rule) in config.dates.iter().enumerate() {
+        if rule.method == DateMethod::Shift && rule.max_shift_days == 0 {
+            return Err(Error::Config(format!("dates[{}] needs a max_shift_days above 0", i)));
+        }
+        let info = match table_info.get(&rule.table) {
+            Some(info) => info,
+            None => continue,
+        };
+        if rule.method == DateMethod::Shift {
+            let fields = entity_fields(rule, info);
+            if fields.is_empty() {
+                return Err(Error::Config(format!(
+                    "dates[{}] for table '{}' needs entity_fields, as the table has no primary key",
+                    i,
+                    rule.table
+                )));
+            }
+            if let Some(field) = fields.iter().find(|field| find_field_index(field, &info.fields).is_none()) {
+                return Err(Error::Config(format!(
+                    "Entity field '{}' of dates[{}] not found in table '{}'",
+                    field,
+                    i,
+                    rule.table
+                )));
             }
-            _ => Err(Error::Config(format!("Unsupported name style: {}", style)))
         }
-    }
-
-    fn apply_standardization(&self, table_name: &str, row: &mut UnlRow) -> Result<(), Error> {
-        // Apply address standardization
-        for field_config in &self.config.standardize.address.fields {
-            if field_config.table == table_name {
-                if let Some(idx) = find_field_index_by_table(table_name, &field_config.field, &self.table_info)? {
-                    row.set_field(idx, self.config.standardize.address.value.clone())?;
+        for field in &rule.fields {
+            let column = info.columns.iter().find(|c| c.name.eq_ignore_ascii_case(field));
+            if let Some(column) = column.filter(|c| c.base_type() == "datetime") {
+                let first = column.data_type.split_whitespace().nth(1).unwrap_or_default();
+                if !first.eq_ignore_ascii_case("year") {
+                    return Err(Error::Config(format!(
+                        "dates[{}] cannot change {}.{}: a '{}' column has no date to shift or generalize",
+                        i,
+                        rule.table,
+                        field,
+                        column.data_type
+                    )));
                 }
             }
         }
+    }
+    Ok(())
+}
 
-        // Apply phone standardization
-        for field_config in &self.config.standardize.phone.fields {
-            if field_config.table == table_name {
-                if let Some(idx) = find_field_index_by_table(table_name, &field_config.field, &self.table_info)? {
-                    row.set_field(idx, self.config.standardize.phone.value.clone())?;
-                }
+// What identifies the entity whose dates shift together: the rule's entity_fields, or the
+// table's primary key
+fn entity_fields<'a>(rule: &'a DateRuleConfig, info: &'a TableInfo) -> &'a [String] {
+    if rule.entity_fields.is_empty() { &info.primary_key } else { &rule.entity_fields }
+}
+
+fn check_number_rules(config: &Config, table_info: &HashMap<String, TableInfo>) -> Result<(), Error> {
+    for (i, rule) in config.numbers.iter().enumerate() {
+        match rule.method {
+            NumericMethod::Noise if !(0.0..=1.0).contains(&rule.noise) => {
+                return Err(Error::Config(format!("numbers[{}] needs a noise between 0 and 1", i)));
+            }
+            NumericMethod::Bucket if !rule.bucket_size.is_some_and(|size| size > 0.0) => {
+                return Err(Error::Config(format!("numbers[{}] needs a bucket_size above 0", i)));
             }
+            _ => {}
         }
-
-        // Apply email standardization
-        for field_config in &self.config.standardize.email.fields {
-            if field_config.table == table_name {
-                if let Some(idx) = find_field_index_by_table(table_name, &field_config.field, &self.table_info)? {
-                    row.set_field(idx, self.config.standardize.email.value.clone())?;
-                }
+        if !rule.preserve_sum {
+            continue;
+        }
+        if rule.method != NumericMethod::Noise {
+            return Err(Error::Config(format!("numbers[{}]: preserve_sum only applies to noise", i)));
+        }
+        for field in &rule.fields {
+            if numeric_format(table_info, &rule.table, field)?.scale.is_none() {
+                return Err(Error::Config(format!(
+                    "numbers[{}]: preserve_sum needs {}.{} to have a fixed scale, such as decimal(p,s) or money",
+                    i,
+                    rule.table,
+                    field
+                )));
             }
         }
-
-        Ok(())
     }
+    Ok(())
+}
 
-    fn apply_combinations(&self, table_name: &str, row: &mut UnlRow) -> Result<(), Error> {
-        for combo_config in &self.config.combination_fields {
-            if combo_config.table == table_name {
-                let mut combined_values = Vec::new();
-    
-                // Collect all source field values
-                for field_config in &combo_config.fields {
-                    let idx = find_field_index_by_table(
-                        table_name,
-                        &field_config.source_field,
-                        &self.table_info
-                    )?
-                    .ok_or_else(|| Error::Processing(format!(
-                        "Source field '{}' not found in table '{}'",
-                        field_config.source_field,
-                        table_name
-                    )))?;
-    
-                    let value = row.get_field(idx)
-                        .ok_or_else(|| Error::Processing(format!(
-                            "Field index {} out of bounds for row with {} fields",
-                            idx,
-                            row.fields.len()
-                        )))?
-                        .to_string();
-                    combined_values.push(value);
+// Every token of a rule has the same length, whatever column it is written to, so it must
+// fit each of the rule's columns. Numeric columns only take numeric tokens.
+fn check_token_columns(config: &Config, table_info: &HashMap<String, TableInfo>) -> Result<(), Error> {
+    for (i, rule) in config.tokenize.iter().enumerate() {
+        let length = token_length(rule);
+        if length <= rule.prefix.chars().count() {
+            return Err(Error::Config(format!(
+                "tokenize[{}]: tokens of {} characters leave no room after the prefix '{}'",
+                i,
+                length,
+                rule.prefix
+            )));
+        }
+        let columns = match table_info.get(&rule.table) {
+            Some(info) => &info.columns,
+            None => continue,
+        };
+        for field in &rule.fields {
+            let column = match columns.iter().find(|c| c.name.eq_ignore_ascii_case(field)) {
+                Some(column) => column,
+                None => continue,
+            };
+            let target = column.element().unwrap_or_else(|| column.clone());
+            if let Some(width) = target.width() {
+                if length > width {
+                    return Err(Error::Config(format!(
+                        "tokenize[{}]: tokens of {} characters don't fit {}.{}, which holds {}; set a shorter length",
+                        i,
+                        length,
+                        rule.table,
+                        field,
+                        width
+                    )));
                 }
-    
-                // Find target field index
-                let target_idx = find_field_index_by_table(
-                    table_name,
-                    &combo_config.target_field,
-                    &self.table_info
-                )?
-                .ok_or_else(|| Error::Processing(format!(
-                    "Target field '{}' not found in table '{}'",
-                    combo_config.target_field,
-                    table_name
-                )))?;
-    
-                // Combine values and set target field
-                let combined = combined_values.join(&combo_config.separator);
-                row.set_field(target_idx, combined)?;
+                continue;
+            }
+            let digits = NumericFormat::from_column(&target).and_then(|format| format.digits());
+            let numeric = rule.alphabet == TokenAlphabet::Numeric && rule.prefix.bytes().all(|b| b.is_ascii_digit());
+            match digits {
+                Some(digits) if numeric && length <= digits => {}
+                Some(digits) => return Err(Error::Config(format!(
+                    "tokenize[{}]: {}.{} is declared {}, which takes tokens of up to {} digits with alphabet: numeric and a numeric prefix",
+                    i,
+                    rule.table,
+                    field,
+                    column.data_type,
+                    digits
+                ))),
+                None => return Err(Error::Config(format!(
+                    "tokenize[{}]: {}.{} is declared {}, which can't hold a token",
+                    i,
+                    rule.table,
+                    field,
+                    column.data_type
+                ))),
             }
         }
-    
-        Ok(())
     }
+    Ok(())
+}
 
+fn token_length(rule: &TokenizeConfig) -> usize {
+    rule.length.unwrap_or(rule.prefix.chars().count() + tokens::DEFAULT_LENGTH)
 }
 
-// Function to find field index in a table schema
-pub fn find_field_index(field_name: &str, table_fields: &[String]) -> Option<usize> {
-    table_fields.iter().position(|f| f == field_name)
+// Width in bytes of a character column, or of a collection column's elements
+fn declared_width(column: &ColumnInfo) -> Option<usize> {
+    match column.element() {
+        Some(element) => element.width(),
+        None => column.width(),
+    }
 }
 
-// Function to find field index using table name and field name
-pub fn find_field_index_by_table(
-    table_name: &str,
-    field_name: &str,
-    table_info: &HashMap<String, TableInfo>
-) -> Result<Option<usize>, Error> {
-    if let Some(table) = table_info.get(table_name) {
-        Ok(find_field_index(field_name, &table.fields))
-    } else {
-        Err(Error::Processing(format!("Table '{}' not found", table_name)))
+// Scale and bounds from the schema; columns of tables without parsed types are floating
+fn numeric_format(table_info: &HashMap<String, TableInfo>, table_name: &str, field: &str) -> Result<NumericFormat, Error> {
+    let column = table_info.get(table_name)
+        .and_then(|info| info.columns.iter().find(|c| c.name.eq_ignore_ascii_case(field)));
+    match column {
+        Some(column) => NumericFormat::from_column(column).ok_or_else(|| Error::Config(format!(
+            "{}.{} is declared {}, not a numeric type",
+            table_name,
+            field,
+            column.data_type
+        ))),
+        None => Ok(NumericFormat::FLOATING),
     }
 }
 
+fn apply_assignments(row: &mut UnlRow, assignments: Vec<(usize, String)>) -> Result<(), Error> {
+    for (idx, value) in assignments {
+        row.set_field(idx, value)?;
+    }
+    Ok(())
+}
+
+// Append a disambiguating suffix, keeping it in the local part of email addresses. The
+// value is shortened to fit a column of `width` bytes; None if nothing of it would be left.
+fn with_suffix(value: &str, suffix: u32, width: Option<usize>) -> Option<String> {
+    let (mut base, domain) = match value.split_once('@') {
+        Some((local, domain)) => (local.to_string(), format!("@{}", domain)),
+        None => (value.to_string(), String::new()),
+    };
+    let tail = format!("-{}{}", suffix, domain);
+    if let Some(width) = width {
+        let room = width.checked_sub(tail.len()).filter(|room| *room > 0)?;
+        while base.len() > room {
+            base.pop();
+        }
+    }
+    Some(base + &tail)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{MaskingConfig, ScrubbingConfig, RandomNameConfig};
     use std::collections::HashMap;   
     use tempfile::NamedTempFile;
     use std::io::Write;
+    use chrono::NaiveDate;
+    use crate::processor::dates::DateFormat;
+    use crate::processor::collections::{Collection, Element};
 
     // Helper function to create test table schema
     fn create_test_schema() -> Vec<String> {
@@ -255,6 +1547,7 @@ mod tests {
                     "address".to_string(),
                     "phone".to_string(),
                 ],
+                ..Default::default()
             }
         );
         
@@ -271,6 +1564,7 @@ mod tests {
                     "address".to_string(),
                     "phone".to_string(),
                 ],
+                ..Default::default()
             }
         );
         
@@ -310,6 +1604,7 @@ mod tests {
                     "address".to_string(),
                     "phone".to_string(),
                 ],
+                ..Default::default()
             },
         );
 
@@ -317,7 +1612,7 @@ mod tests {
         let adjectives = vec!["happy".to_string(), "quick".to_string()];
         let nouns = vec!["fox".to_string(), "dog".to_string()];
 
-        UnlProcessor::new(config, table_info, adjectives, nouns)
+        UnlProcessor::new(config, table_info, adjectives, nouns).unwrap()
     }
 
     #[test]
@@ -361,13 +1656,17 @@ mod tests {
                     table: "customers".to_string(),
                     fields: vec!["first_name".to_string(), "last_name".to_string()],
                     style: "github".to_string(),
+                    ..Default::default()
                 },
                 RandomNameConfig {
                     table: "employees".to_string(),
                     fields: vec!["name".to_string()],
                     style: "github".to_string(),
+                    ..Default::default()
                 },
             ],
+            generators: vec![],
+            unique_from_schema: false,
         };
         
         // Test customer fields
@@ -435,6 +1734,7 @@ mod tests {
             table: "customers".to_string(),
             fields: vec!["first_name".to_string(), "last_name".to_string()],
             style: "github".to_string(),
+            ..Default::default()
         };
         
         // Test finding all fields specified in config
@@ -453,6 +1753,7 @@ mod tests {
             table: "customers".to_string(),
             fields: vec!["nonexistent1".to_string(), "nonexistent2".to_string()],
             style: "github".to_string(),
+            ..Default::default()
         };
         
         // Test finding non-existent fields
@@ -510,6 +1811,830 @@ mod tests {
         Ok(())
     }
 
+    fn create_lookup_processor() -> Result<UnlProcessor, Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.combination_fields = serde_yaml::from_str(r#"
+            - table: repositories
+              fields:
+                - source_field: project_name
+                  lookup: { table: projects, join_field: project_id, key: id }
+                - source_field: repo_name
+              separator: "/"
+              target_field: full_path
+        "#)?;
+
+        let mut table_info = HashMap::new();
+        table_info.insert(
+            "projects".to_string(),
+            TableInfo {
+                unl_file: "proje00102.unl".to_string(),
+                fields: vec!["id".to_string(), "project_name".to_string(), "name1".to_string(), "name2".to_string(), "combo_name".to_string()],
+                ..Default::default()
+            },
+        );
+        table_info.insert(
+            "repositories".to_string(),
+            TableInfo {
+                unl_file: "repos00103.unl".to_string(),
+                fields: vec!["id".to_string(), "project_id".to_string(), "owner_name".to_string(), "repo_name".to_string(), "full_path".to_string()],
+                ..Default::default()
+            },
+        );
+        UnlProcessor::new(
+            config,
+            table_info,
+            vec!["happy".to_string(), "quick".to_string()],
+            vec!["fox".to_string(), "dog".to_string()],
+        )
+    }
+
+    #[test]
+    fn test_lookup_from_parent_table() -> Result<(), Error> {
+        let processor = create_lookup_processor()?;
+
+        let mut projects = NamedTempFile::new()?;
+        writeln!(projects, "1|Secret Project|a|b||")?;
+        writeln!(projects, "2|Other Project|c|d||")?;
+        let projects_out = NamedTempFile::new()?;
+        processor.process_file("projects", projects.path(), projects_out.path())?;
+        let scrubbed_projects: Vec<UnlRow> = std::fs::read_to_string(projects_out.path())?
+            .lines()
+            .map(UnlRow::from_line)
+            .collect::<Result<_, _>>()?;
+
+        let mut repos = NamedTempFile::new()?;
+        writeln!(repos, "10|2|owner|SecretRepo|old|")?;
+        writeln!(repos, "11||owner|OtherRepo|old|")?;
+        let repos_out = NamedTempFile::new()?;
+        processor.process_file("repositories", repos.path(), repos_out.path())?;
+        let content = std::fs::read_to_string(repos_out.path())?;
+        let rows: Vec<UnlRow> = content.lines().map(UnlRow::from_line).collect::<Result<_, _>>()?;
+
+        // The scrubbed name of project 2, joined with the scrubbed repo name
+        let expected = format!("{}/{}", scrubbed_projects[1].fields[1], rows[0].fields[3]);
+        assert_ne!(scrubbed_projects[1].fields[1], "Other Project");
+        assert_eq!(rows[0].get_field(4), Some(expected.as_str()));
+
+        // A NULL reference looks up nothing
+        assert_eq!(rows[1].get_field(4), Some(format!("/{}", rows[1].fields[3]).as_str()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lookup_missing_parent_row() -> Result<(), Error> {
+        let processor = create_lookup_processor()?;
+
+        let mut repos = NamedTempFile::new()?;
+        writeln!(repos, "10|99|owner|SecretRepo|old|")?;
+        let repos_out = NamedTempFile::new()?;
+        let result = processor.process_file("repositories", repos.path(), repos_out.path());
+        assert!(matches!(result, Err(Error::Processing(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rule_trace() -> Result<(), Error> {
+        let log_dir = tempfile::tempdir()?;
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.verification.logging.directory = log_dir.path().to_string_lossy().to_string();
+        config.verification.logging.trace_rules = true;
+        config.verification.logging.trace_rows = 1;
+        config.masking = serde_yaml::from_str(r#"
+            - table: customers
+              fields: [phone]
+        "#)?;
+        let processor = UnlProcessor::new(config, create_test_table_info(), vec!["a".to_string()], vec!["b".to_string()])?;
+
+        let mut input = NamedTempFile::new()?;
+        writeln!(input, "1001|John|Doe|john@example.com|123 Main St|555-1234|")?;
+        writeln!(input, "1002|Jane|Smith|jane@example.com|456 Oak St|555-5678|")?;
+        let output = NamedTempFile::new()?;
+        processor.process_file("customers", input.path(), output.path())?;
+
+        let trace = std::fs::read_to_string(log_dir.path().join("verify_trace_customers.log"))?;
+        assert!(trace.contains("row 1: customers.first_name <- scrubbing.random_names[0]"));
+        // The mask transforms the standardized phone, so it produced the final value
+        assert!(trace.contains("row 1: customers.phone <- masking[0]"));
+        assert!(!trace.contains("standardize.phone"));
+        assert!(!trace.contains("customers.id"));
+        assert!(!trace.contains("row 2:"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_conditional_rules() -> Result<(), Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.scrubbing.random_names = serde_yaml::from_str(r#"
+            - table: customers
+              fields: [first_name, last_name]
+              style: github
+              where:
+                - field: id
+                  not_in: [1001, 1002]
+        "#)?;
+        // Standardize only example.com addresses, and mask only ids from 2000
+        config.standardize.email.fields = serde_yaml::from_str(r#"
+            - table: customers
+              field: email
+              where:
+                - field: email
+                  regex: "@example\\.com$"
+        "#)?;
+        config.masking = serde_yaml::from_str(r#"
+            - table: customers
+              fields: [email]
+              where:
+                - field: id
+                  min: 2000
+        "#)?;
+        let processor = UnlProcessor::new(config, create_test_table_info(), vec!["happy".to_string()], vec!["fox".to_string()])?;
+
+        let mut essential = create_test_row();
+        processor.process_row("customers", &mut essential)?;
+        assert_eq!(essential.get_field(1), Some("Essential1"));
+        assert_eq!(essential.get_field(2), Some("User1"));
+        assert_eq!(essential.get_field(3), Some("test@example.com"));
+
+        let mut other = UnlRow::from_line("2001|Jane|Doe|jane@corp.test|1 Main St|555-2001|")?;
+        processor.process_row("customers", &mut other)?;
+        assert_eq!(other.get_field(1), Some("happy-fox"));
+        assert_eq!(other.get_field(2), Some("happy-fox"));
+        // Not @example.com, so only the mask applied
+        assert_ne!(other.get_field(3), Some("jane@corp.test"));
+        assert_ne!(other.get_field(3), Some("test@example.com"));
+        assert!(other.get_field(3).unwrap().contains('@'));
+        Ok(())
+    }
+
+    #[test]
+    fn test_masking() -> Result<(), Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.standardize.phone.fields.clear();
+        config.masking = vec![MaskingConfig {
+            table: "customers".to_string(),
+            fields: vec!["phone".to_string()],
+            keep_last: 2,
+            where_clause: Vec::new(),
+        }];
+        let processor = UnlProcessor::new(
+            config,
+            create_test_table_info(),
+            vec!["happy".to_string()],
+            vec!["fox".to_string()],
+        )?;
+
+        let mut first = create_test_row();
+        let mut second = create_test_row();
+        processor.process_row("customers", &mut first)?;
+        processor.process_row("customers", &mut second)?;
+
+        let masked = first.get_field(5).unwrap();
+        assert_ne!(masked, "555-1001");
+        assert_eq!(masked.len(), 8);
+        assert_eq!(&masked[3..4], "-");
+        assert!(masked.ends_with("01"));
+        assert!(masked.chars().filter(|c| *c != '-').all(|c| c.is_ascii_digit()));
+
+        // Same input and seed gives the same masked value
+        assert_eq!(first, second);
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_shift_per_entity() -> Result<(), Error> {
+        let table_info = crate::processor::sql::parse_sql_file(r#"
+            { TABLE "informix".customers row size = 20 number of columns = 3 index size = 0 }
+            { unload file name = custo00100.unl number of rows = 1 }
+            create table "informix".customers 
+            (
+                id serial not null,
+                birth_date date,
+                joined datetime year to second,
+                primary key (id)
+            ) extent size 16 next size 16 lock mode row;
+            { TABLE "informix".orders row size = 20 number of columns = 4 index size = 0 }
+            { unload file name = order00101.unl number of rows = 1 }
+            create table "informix".orders 
+            (
+                id serial not null,
+                customer_id integer,
+                ordered date,
+                pickup datetime hour to minute
+            ) extent size 16 next size 16 lock mode row;
+        "#)?;
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.dates = serde_yaml::from_str(r#"
+            - table: customers
+              fields: [birth_date, joined]
+              entity: customer
+            - table: orders
+              fields: [ordered]
+              entity: customer
+              entity_fields: [customer_id]
+        "#)?;
+        let processor = UnlProcessor::new(config.clone(), table_info.clone(), vec!["happy".to_string()], vec!["fox".to_string()])?;
+
+        let mut customer = UnlRow::from_line("7|03/04/1980|2020-01-01 09:30:00|")?;
+        let mut order = UnlRow::from_line("1|7|03/04/1980|")?;
+        processor.process_row("customers", &mut customer)?;
+        processor.process_row("orders", &mut order)?;
+
+        // The same entity moves by the same number of days everywhere, keeping intervals
+        let shifted = DateFormat::default().parse_date(customer.get_field(1).unwrap()).unwrap();
+        let offset = shifted - NaiveDate::from_ymd_opt(1980, 3, 4).unwrap();
+        assert_ne!(offset.num_days(), 0);
+        assert!(offset.num_days().abs() <= 365);
+        assert_eq!(order.get_field(2), customer.get_field(1));
+        let joined = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap() + offset;
+        assert_eq!(customer.get_field(2).unwrap(), format!("{} 09:30:00", joined.format("%Y-%m-%d")));
+
+        // Generalizing with a different DBDATE
+        config.export.dbdate = "Y4MD-".to_string();
+        config.dates = serde_yaml::from_str("[{ table: customers, fields: [birth_date], method: month }]")?;
+        let processor = UnlProcessor::new(config.clone(), table_info.clone(), vec!["happy".to_string()], vec!["fox".to_string()])?;
+        let mut customer = UnlRow::from_line("7|1980-03-04||")?;
+        processor.process_row("customers", &mut customer)?;
+        assert_eq!(customer.to_line(), "7|1980-03-01||");
+
+        let mut invalid = UnlRow::from_line("8|03/04/1980||")?;
+        assert!(processor.process_row("customers", &mut invalid).is_err());
+
+        // Rules that could never apply fail before any row is read
+        for dates in [
+            "[{ table: orders, fields: [pickup], method: year }]",
+            "[{ table: orders, fields: [ordered], max_shift_days: 0 }]",
+            // orders has no primary key to tell its entities apart
+            "[{ table: orders, fields: [ordered] }]",
+            "[{ table: orders, fields: [ordered], entity_fields: [client_id] }]",
+        ] {
+            config.dates = serde_yaml::from_str(dates)?;
+            let processor = UnlProcessor::new(config.clone(), table_info.clone(), vec!["happy".to_string()], vec!["fox".to_string()]);
+            assert!(matches!(processor, Err(Error::Config(_))), "{}", dates);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_numeric_noise_preserves_sum() -> Result<(), Error> {
+        let table_info = crate::processor::sql::parse_sql_file(r#"
+            { TABLE "informix".employees row size = 20 number of columns = 3 index size = 0 }
+            { unload file name = emplo00101.unl number of rows = 5 }
+            create table "informix".employees 
+            (
+                id serial not null,
+                salary decimal(8,2),
+                band smallint
+            ) extent size 16 next size 16 lock mode row;
+        "#)?;
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.numbers = serde_yaml::from_str(r#"
+            - table: employees
+              fields: [salary]
+              noise: 0.2
+              preserve_sum: true
+            - table: employees
+              fields: [band]
+              method: bucket
+              bucket_size: 10
+        "#)?;
+        let processor = UnlProcessor::new(config.clone(), table_info.clone(), vec!["happy".to_string()], vec!["fox".to_string()])?;
+
+        let mut input = NamedTempFile::new()?;
+        let salaries = ["52000.00", "61250.50", "48999.99", "", "75000.00"];
+        for (i, salary) in salaries.iter().enumerate() {
+            writeln!(input, "{}|{}|{}|", i + 1, salary, 10 + i * 7)?;
+        }
+        let output = NamedTempFile::new()?;
+        processor.process_file("employees", input.path(), output.path())?;
+
+        let rows: Vec<UnlRow> = std::fs::read_to_string(output.path())?
+            .lines()
+            .map(UnlRow::from_line)
+            .collect::<Result<_, _>>()?;
+        let total = |values: Vec<&str>| values.iter().map(|v| v.parse::<f64>().unwrap_or_default()).sum::<f64>();
+        let written: Vec<&str> = rows.iter().map(|r| r.get_field(1).unwrap()).collect();
+        assert_eq!(format!("{:.2}", total(written.clone())), format!("{:.2}", total(salaries.to_vec())));
+        assert_ne!(written[0], salaries[0]);
+        assert_eq!(written[3], "");
+        assert!(written.iter().filter(|v| !v.is_empty()).all(|v| v.split_once('.').unwrap().1.len() == 2));
+        let bands: Vec<&str> = rows.iter().map(|r| r.get_field(2).unwrap()).collect();
+        assert_eq!(bands, vec!["10", "10", "20", "30", "30"]);
+
+        // Rules that can't apply are caught before any file is read
+        for numbers in [
+            "[{ table: employees, fields: [salary], method: bucket, bucket_size: 5, preserve_sum: true }]",
+            "[{ table: employees, fields: [salary], noise: 1.5 }]",
+            "[{ table: employees, fields: [band], method: bucket }]",
+            "[{ table: employees, fields: [band], method: bucket, bucket_size: 0 }]",
+        ] {
+            config.numbers = serde_yaml::from_str(numbers)?;
+            let processor = UnlProcessor::new(config.clone(), table_info.clone(), vec!["happy".to_string()], vec!["fox".to_string()]);
+            assert!(matches!(processor, Err(Error::Config(_))), "{}", numbers);
+        }
+
+        // Corrected values stay within decimal(5,2), the rest of the difference going to later rows
+        let table_info = crate::processor::sql::parse_sql_file(r#"
+            { TABLE "informix".refunds row size = 20 number of columns = 2 index size = 0 }
+            { unload file name = refun00102.unl number of rows = 4 }
+            create table "informix".refunds 
+            (
+                id serial not null,
+                amount decimal(5,2)
+            ) extent size 16 next size 16 lock mode row;
+        "#)?;
+        config.numbers = serde_yaml::from_str("[{ table: refunds, fields: [amount], noise: 0.5, preserve_sum: true }]")?;
+        let processor = UnlProcessor::new(config, table_info, vec!["happy".to_string()], vec!["fox".to_string()])?;
+        let mut input = NamedTempFile::new()?;
+        let amounts = ["999.99", "999.99", "999.99", "12.00"];
+        for (i, amount) in amounts.iter().enumerate() {
+            writeln!(input, "{}|{}|", i + 1, amount)?;
+        }
+        processor.process_file("refunds", input.path(), output.path())?;
+        let written: Vec<String> = UnlReader::open(output.path())?
+            .map(|row| row.map(|row| row.get_field(1).unwrap_or_default().to_string()))
+            .collect::<Result<_, _>>()?;
+        assert!(written.iter().all(|v| v.parse::<f64>().unwrap() <= 999.99), "{:?}", written);
+        assert_eq!(
+            format!("{:.2}", total(written.iter().map(String::as_str).collect())),
+            format!("{:.2}", total(amounts.to_vec()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_redaction() -> Result<(), Error> {
+        let mut table_info = create_test_table_info();
+        if let Some(customers) = table_info.get_mut("customers") {
+            customers.fields.push("notes".to_string());
+        }
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.redaction = serde_yaml::from_str(r#"
+            - table: customers
+              fields: [notes]
+              known_fields: [first_name, last_name]
+        "#)?;
+        let processor = UnlProcessor::new(config.clone(), table_info.clone(), vec!["happy".to_string()], vec!["fox".to_string()])?;
+
+        let line = "1001|Ada|Lovelace|ada@example.com|1 Main St|555-1001|Ada called from 503-555-1234, mail ada@corp.com|";
+        let mut row = UnlRow::from_line(line)?;
+        processor.process_row("customers", &mut row)?;
+        assert_eq!(row.get_field(6), Some("[FIRST_NAME] called from [PHONE], mail [EMAIL]"));
+
+        // Escaped pipes and newlines keep the whole note in one record
+        let mut input = NamedTempFile::new()?;
+        write!(input, "1001|Ada|Lovelace|ada@example.com|1 Main St|555-1001|Ada wrote\\\nplease call 503-555-1234 a\\|b|\n")?;
+        writeln!(input, "1002|Bo|Lee|bo@example.com|2 Main St|555-1002||")?;
+        let output = NamedTempFile::new()?;
+        let stats = processor.process_file("customers", input.path(), output.path())?;
+        assert_eq!(stats.rows_read, 2);
+        let written = std::fs::read_to_string(output.path())?;
+        assert!(written.contains("|[FIRST_NAME] wrote\\\nplease call [PHONE] a\\|b|\n1002|"), "{}", written);
+        let rows = UnlReader::open(output.path())?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get_field(6), Some("[FIRST_NAME] wrote\nplease call [PHONE] a|b"));
+
+        // Pseudonyms use the scrubbed name and stay the same for the same text
+        config.redaction[0].replacement = Replacement::Pseudonym;
+        let processor = UnlProcessor::new(config, table_info, vec!["happy".to_string()], vec!["fox".to_string()])?;
+        let mut first = UnlRow::from_line(line)?;
+        let mut second = UnlRow::from_line(line)?;
+        processor.process_row("customers", &mut first)?;
+        processor.process_row("customers", &mut second)?;
+        let notes = first.get_field(6).unwrap();
+        assert!(notes.starts_with(&format!("{} called from ", first.get_field(1).unwrap())));
+        assert!(notes.contains("-555-01"));
+        assert!(notes.ends_with("@example.com") || notes.ends_with("@example.net") || notes.ends_with("@example.org"));
+        assert_eq!(first, second);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tokens_join_across_tables() -> Result<(), Error> {
+        let table_info = crate::processor::sql::parse_sql_file(r#"
+            { TABLE "informix".customers row size = 20 number of columns = 2 index size = 0 }
+            { unload file name = custo00100.unl number of rows = 1 }
+            create table "informix".customers 
+            (
+                id serial not null,
+                customer_number char(10)
+            ) extent size 16 next size 16 lock mode row;
+            { TABLE "informix".invoices row size = 20 number of columns = 3 index size = 0 }
+            { unload file name = invoi00101.unl number of rows = 1 }
+            create table "informix".invoices 
+            (
+                id serial not null,
+                customer_number varchar(40),
+                account_no integer
+            ) extent size 16 next size 16 lock mode row;
+        "#)?;
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.tokenize = serde_yaml::from_str(r#"
+            - { table: customers, fields: [customer_number], prefix: "C", length: 10 }
+            - { table: invoices, fields: [customer_number], prefix: "C", length: 10 }
+        "#)?;
+        let processor = UnlProcessor::new(config.clone(), table_info.clone(), vec!["happy".to_string()], vec!["fox".to_string()])?;
+
+        let mut customer = UnlRow::from_line("1|AC-778123|")?;
+        let mut invoice = UnlRow::from_line("9|AC-778123|")?;
+        let mut null = UnlRow::from_line("2||")?;
+        processor.process_row("customers", &mut customer)?;
+        processor.process_row("invoices", &mut invoice)?;
+        processor.process_row("customers", &mut null)?;
+        let token = customer.get_field(1).unwrap();
+        assert_eq!(token.len(), 10);
+        assert!(token.starts_with('C'));
+        assert_eq!(invoice.get_field(1), Some(token));
+        assert_eq!(null.get_field(1), Some(""));
+
+        // Without a token_secret the key comes from the seed, which is reported
+        assert!(processor.tokens_keyed_by_seed());
+
+        // Tokens must fit every column, including at the default length
+        for length in [Some(12), None] {
+            config.tokenize[0].length = length;
+            let processor = UnlProcessor::new(config.clone(), table_info.clone(), vec!["happy".to_string()], vec!["fox".to_string()]);
+            assert!(matches!(processor, Err(Error::Config(_))), "{:?}", length);
+        }
+
+        // Numeric columns take numeric tokens with room for every value of the type
+        config.tokenize = serde_yaml::from_str("[{ table: invoices, fields: [account_no], prefix: '9', length: 9, alphabet: numeric }]")?;
+        let processor = UnlProcessor::new(config.clone(), table_info.clone(), vec!["happy".to_string()], vec!["fox".to_string()])?;
+        let mut invoice = UnlRow::from_line("9|AC-778123|4411|")?;
+        processor.process_row("invoices", &mut invoice)?;
+        let account = invoice.get_field(2).unwrap();
+        assert!(account.len() == 9 && account.starts_with('9') && account.parse::<i32>().is_ok(), "{}", account);
+        for rule in [
+            "{ table: invoices, fields: [account_no], length: 9 }",
+            "{ table: invoices, fields: [account_no], length: 10, alphabet: numeric }",
+            "{ table: invoices, fields: [account_no], prefix: A, length: 9, alphabet: numeric }",
+            "{ table: invoices, fields: [id], alphabet: numeric }",
+        ] {
+            config.tokenize = serde_yaml::from_str(&format!("[{}]", rule))?;
+            let processor = UnlProcessor::new(config.clone(), table_info.clone(), vec!["happy".to_string()], vec!["fox".to_string()]);
+            assert!(matches!(processor, Err(Error::Config(_))), "{}", rule);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_inline_text_round_trip() -> Result<(), Error> {
+        let table_info = crate::processor::sql::parse_sql_file(r#"
+            { TABLE "informix".tickets row size = 20 number of columns = 3 index size = 0 }
+            { unload file name = ticke00100.unl number of rows = 1 }
+            create table "informix".tickets 
+            (
+                id serial not null,
+                code varchar(20),
+                body text,
+                primary key (id)
+            ) extent size 16 next size 16 lock mode row;
+        "#)?;
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.masking = serde_yaml::from_str("[{ table: tickets, fields: [code] }]")?;
+        config.synthetic_rows = serde_yaml::from_str(r#"
+            - table: tickets
+              count: 1
+              columns:
+                code: { value: "new|line\nhere" }
+                body: { null: true }
+        "#)?;
+        let processor = UnlProcessor::new(config, table_info, vec!["happy".to_string()], vec!["fox".to_string()])?;
+
+        // An inline TEXT value spanning lines and holding a pipe and a backslash
+        let mut input = NamedTempFile::new()?;
+        write!(input, "1|AB\\|12|first line\\\nsecond \\| line C:\\\\tmp|\n")?;
+        let output = NamedTempFile::new()?;
+        processor.process_file("tickets", input.path(), output.path())?;
+
+        let rows = UnlReader::open(output.path())?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get_field(2), Some("first line\nsecond | line C:\\tmp"));
+        // Masked and generated values are escaped as they are written
+        let code = rows[0].get_field(1).unwrap();
+        assert_eq!((code.len(), &code[2..3]), (5, "|"));
+        assert_ne!(code, "AB|12");
+        assert_eq!(rows[1].get_field(1), Some("new|line\nhere"));
+        let written = std::fs::read_to_string(output.path())?;
+        assert!(written.contains("|first line\\\nsecond \\| line C:\\\\tmp|"), "{}", written);
+        assert!(written.ends_with("|new\\|line\\\nhere||\n"), "{}", written);
+        Ok(())
+    }
+
+    #[test]
+    fn test_collection_elements_transformed() -> Result<(), Error> {
+        let table_info = crate::processor::sql::parse_sql_file(r#"
+            { TABLE "informix".contacts row size = 20 number of columns = 2 index size = 0 }
+            { unload file name = conta00100.unl number of rows = 1 }
+            create table "informix".contacts 
+            (
+                id serial not null,
+                phones set(char(12) not null)
+            ) extent size 16 next size 16 lock mode row;
+        "#)?;
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.masking = serde_yaml::from_str("[{ table: contacts, fields: [phones], keep_last: 4 }]")?;
+        let processor = UnlProcessor::new(config.clone(), table_info.clone(), vec!["happy".to_string()], vec!["fox".to_string()])?;
+
+        let mut row = UnlRow::from_line("1|SET{'555-123-4567','555-987-6543'}|")?;
+        processor.process_row("contacts", &mut row)?;
+        let masked = Collection::parse(row.get_field(1).unwrap()).unwrap();
+        assert_eq!(masked.elements.len(), 2);
+        for (element, original) in masked.elements.iter().zip(["555-123-4567", "555-987-6543"]) {
+            assert!(matches!(element, Element::Quoted(text) if text.ends_with(&original[8..]) && text != original));
+        }
+
+        // Escaped pipes and newlines inside elements reach the collection parser whole
+        let mut row = UnlRow::from_line("1|SET{'ext\\|5550','c'}|")?;
+        assert_eq!(row.get_field(1), Some("SET{'ext|5550','c'}"));
+        processor.process_row("contacts", &mut row)?;
+        let masked = Collection::parse(row.get_field(1).unwrap()).unwrap();
+        assert!(matches!(&masked.elements[0], Element::Quoted(text) if text.len() == 8 && text.ends_with("5550") && text.contains('|')));
+        assert!(row.to_line().contains("\\|5550'"));
+        let mut input = NamedTempFile::new()?;
+        write!(input, "2|SET{{'two\\\nlines','a\\|b'}}|\n3|SET{{}}|\n")?;
+        let rows = UnlReader::open(input.path())?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(rows.len(), 2);
+        let set = Collection::parse(rows[0].get_field(1).unwrap()).unwrap();
+        assert_eq!(set.elements, vec![Element::Quoted("two\nlines".to_string()), Element::Quoted("a|b".to_string())]);
+
+        // Replacing the whole set with a generated value would break it
+        config.masking.clear();
+        config.scrubbing.generators = serde_yaml::from_str("[{ table: contacts, fields: [phones], generator: phone }]")?;
+        let result = UnlProcessor::new(config, table_info, vec!["happy".to_string()], vec!["fox".to_string()]);
+        assert!(matches!(result, Err(Error::Config(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_generators() -> Result<(), Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.standardize.email.fields.clear();
+        config.standardize.phone.fields.clear();
+        config.scrubbing.generators = serde_yaml::from_str(r#"
+            - table: customers
+              fields: [email]
+              generator: email
+              name_fields: [first_name, last_name]
+            - table: customers
+              fields: [phone]
+              generator: phone
+        "#)?;
+        let processor = UnlProcessor::new(
+            config,
+            create_test_table_info(),
+            vec!["happy".to_string()],
+            vec!["fox".to_string()],
+        )?;
+
+        let mut row = create_test_row();
+        processor.process_row("customers", &mut row)?;
+
+        // Email is derived from the scrubbed names, not the originals
+        let email = row.get_field(3).unwrap();
+        assert!(email.starts_with("happyfox.happyfox"), "unexpected email {}", email);
+        assert!(!email.contains("essential1"));
+        assert!(generators::is_reserved_domain(email.split('@').nth(1).unwrap()));
+        assert!(row.get_field(5).unwrap().contains("-555-01"));
+
+        // NULL values stay NULL
+        let mut row = create_test_row();
+        row.set_field(5, String::new())?;
+        processor.process_row("customers", &mut row)?;
+        assert_eq!(row.get_field(5), Some(""));
+        Ok(())
+    }
+
+    #[test]
+    fn test_generator_rejects_real_domain() -> Result<(), Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.standardize.email.fields.clear();
+        config.scrubbing.generators = serde_yaml::from_str(r#"
+            - table: customers
+              fields: [email]
+              generator: email
+              domain: gmail.com
+        "#)?;
+        // Rejected up front, before any table is processed
+        assert!(matches!(
+            UnlProcessor::new(config, create_test_table_info(), vec!["a".to_string()], vec!["b".to_string()]),
+            Err(Error::Config(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_person_names() -> Result<(), Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.scrubbing.random_names = serde_yaml::from_str(r#"
+            - table: people
+              fields: [first_name, last_name]
+              style: person
+              gender_field: gender
+            - table: people
+              fields: [full_name]
+              style: person
+              keep_gender: true
+            - table: people
+              fields: [nickname]
+              style: first_name
+              keep_gender: true
+        "#)?;
+        let mut table_info = HashMap::new();
+        table_info.insert(
+            "people".to_string(),
+            TableInfo {
+                unl_file: "peopl00100.unl".to_string(),
+                fields: vec![
+                    "id".to_string(),
+                    "first_name".to_string(),
+                    "last_name".to_string(),
+                    "gender".to_string(),
+                    "full_name".to_string(),
+                    "nickname".to_string(),
+                ],
+                ..Default::default()
+            },
+        );
+        let processor = UnlProcessor::new(config, table_info, vec!["a".to_string()], vec!["b".to_string()])?;
+
+        let mut row = UnlRow::from_line("1|John|Doe|F|Mary Smith|Robert|")?;
+        processor.process_row("people", &mut row)?;
+
+        // The gender column wins over the original first name
+        assert!(names::FEMALE_FIRST_NAMES.contains(&row.get_field(1).unwrap()));
+        assert!(names::SURNAMES.contains(&row.get_field(2).unwrap()));
+        assert_eq!(row.get_field(3), Some("F"));
+
+        // A full name keeps the gender of the original first word
+        let full: Vec<&str> = row.get_field(4).unwrap().split(' ').collect();
+        assert_eq!(full.len(), 2);
+        assert!(names::FEMALE_FIRST_NAMES.contains(&full[0]));
+        assert!(names::SURNAMES.contains(&full[1]));
+        assert!(names::MALE_FIRST_NAMES.contains(&row.get_field(5).unwrap()));
+
+        // Deterministic for the same input
+        let mut again = UnlRow::from_line("1|John|Doe|F|Mary Smith|Robert|")?;
+        processor.process_row("people", &mut again)?;
+        assert_eq!(row, again);
+        Ok(())
+    }
+
+    #[test]
+    fn test_person_name_part_mismatch() -> Result<(), Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.scrubbing.random_names = serde_yaml::from_str(r#"
+            - table: customers
+              fields: [first_name, last_name]
+              style: person
+              parts: [full]
+        "#)?;
+        let words = || vec!["a".to_string()];
+        assert!(matches!(UnlProcessor::new(config.clone(), create_test_table_info(), words(), words()), Err(Error::Config(_))));
+
+        config.scrubbing.random_names = serde_yaml::from_str(r#"
+            - table: customers
+              fields: [first_name, last_name]
+              style: person
+              gender_field: sex
+        "#)?;
+        assert!(matches!(UnlProcessor::new(config, create_test_table_info(), words(), words()), Err(Error::Config(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rule_dictionaries() -> Result<(), Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.scrubbing.random_names = serde_yaml::from_str(r#"
+            - table: customers
+              fields: [first_name]
+              style: github
+              dictionaries: [colors, animals]
+            - table: customers
+              fields: [last_name]
+              style: github
+              nouns: words/animals.txt
+        "#)?;
+        let mut word_lists = WordLists::default();
+        word_lists.dictionaries.insert("colors".to_string(), vec!["red".to_string()]);
+        word_lists.dictionaries.insert("animals".to_string(), vec!["owl".to_string()]);
+        word_lists.files.insert("words/animals.txt".to_string(), vec!["lynx".to_string()]);
+        let processor = UnlProcessor::new(
+            config,
+            create_test_table_info(),
+            vec!["happy".to_string()],
+            vec!["fox".to_string()],
+        )?.with_word_lists(word_lists);
+
+        let mut row = create_test_row();
+        processor.process_row("customers", &mut row)?;
+        assert_eq!(row.get_field(1), Some("red-owl"));
+        assert_eq!(row.get_field(2), Some("happy-lynx"));
+        Ok(())
+    }
+
+    fn create_unique_processor(unique: bool, unique_from_schema: bool) -> Result<UnlProcessor, Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.scrubbing.random_names[0].unique = unique;
+        config.scrubbing.unique_from_schema = unique_from_schema;
+        let mut table_info = create_test_table_info();
+        table_info.get_mut("customers").unwrap().unique_keys = vec![vec!["first_name".to_string()]];
+        UnlProcessor::new(
+            config,
+            table_info,
+            vec!["happy".to_string(), "quick".to_string()],
+            vec!["fox".to_string(), "dog".to_string()],
+        )
+    }
+
+    fn process_identical_rows(processor: &UnlProcessor, count: usize) -> Result<Vec<String>, Error> {
+        let mut input_file = NamedTempFile::new()?;
+        for _ in 0..count {
+            writeln!(input_file, "1001|John|Doe|john@example.com|123 Main St|555-1234|")?;
+        }
+        let output_file = NamedTempFile::new()?;
+        processor.process_file("customers", input_file.path(), output_file.path())?;
+
+        let content = std::fs::read_to_string(output_file.path())?;
+        content.lines()
+            .map(|line| Ok(UnlRow::from_line(line)?.get_field(1).unwrap_or_default().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_unique_random_names() -> Result<(), Error> {
+        // Identical input rows would all get the same name without uniqueness
+        let names = process_identical_rows(&create_unique_processor(false, false)?, 4)?;
+        assert!(names.iter().all(|name| name == &names[0]));
+
+        let processor = create_unique_processor(true, false)?;
+        let names = process_identical_rows(&processor, 4)?;
+        let distinct: std::collections::HashSet<_> = names.iter().collect();
+        assert_eq!(distinct.len(), 4, "names should be unique: {:?}", names);
+
+        // Deterministic, and retries start from a clean slate
+        assert_eq!(process_identical_rows(&processor, 4)?, names);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_from_schema() -> Result<(), Error> {
+        let names = process_identical_rows(&create_unique_processor(false, true)?, 4)?;
+        let distinct: std::collections::HashSet<_> = names.iter().collect();
+        assert_eq!(distinct.len(), 4, "names should be unique: {:?}", names);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_space_too_small() -> Result<(), Error> {
+        // 2 adjectives x 2 nouns cannot name 5 rows uniquely
+        let result = process_identical_rows(&create_unique_processor(true, false)?, 5);
+        match result {
+            Err(Error::Config(msg)) => assert!(msg.contains("only 4 distinct values"), "unexpected message: {}", msg),
+            other => panic!("Expected Config error, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_space_counts_kept_records() -> Result<(), Error> {
+        let mut config = Config::from_str(include_str!("../../test_data/config.yml"))?;
+        config.scrubbing.random_names[0].unique = true;
+        config.row_filters = serde_yaml::from_str(r#"
+            - table: customers
+              where:
+                - field: last_name
+                  equals: Gone
+        "#)?;
+        let processor = UnlProcessor::new(
+            config,
+            create_test_table_info(),
+            vec!["happy".to_string(), "quick".to_string()],
+            vec!["fox".to_string(), "dog".to_string()],
+        )?;
+
+        // Seven lines, but six records, two of them filtered out: the 4 names are enough
+        let mut input_file = NamedTempFile::new()?;
+        write!(input_file, "1|John|Doe|a@example.com|1 Main St\\\nApt 2|555-1234|\n")?;
+        for (id, last_name) in [(2, "Doe"), (3, "Gone"), (4, "Doe"), (5, "Gone"), (6, "Doe")] {
+            writeln!(input_file, "{}|John|{}|a@example.com|1 Main St|555-1234|", id, last_name)?;
+        }
+        let output_file = NamedTempFile::new()?;
+        processor.process_file("customers", input_file.path(), output_file.path())?;
+        let written: Vec<UnlRow> = UnlReader::open(output_file.path())?.collect::<Result<_, _>>()?;
+        assert_eq!(written.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_suffix() {
+        assert_eq!(with_suffix("happy-fox", 2, None).as_deref(), Some("happy-fox-2"));
+        assert_eq!(with_suffix("jane.smith@example.com", 3, None).as_deref(), Some("jane.smith-3@example.com"));
+        // Shortened to fit the column
+        assert_eq!(with_suffix("happyfox", 12, Some(8)).as_deref(), Some("happy-12"));
+        assert_eq!(with_suffix("jane.smith@example.com", 3, Some(20)).as_deref(), Some("jane.s-3@example.com"));
+        assert_eq!(with_suffix("happy-fox", 123, Some(4)), None);
+    }
+
     #[test]
     fn test_unl_row_parsing() -> Result<(), Error> {
         // Test parsing with trailing pipe
@@ -522,6 +2647,22 @@ mod tests {
         assert_eq!(row.fields.len(), 3);
         assert_eq!(row.fields, vec!["1", "2", "3"]);
 
+        // Escaped delimiters, backslashes and newlines are part of the value
+        let row = UnlRow::from_line("1|a\\|b|c:\\\\dir|two\\\nlines|\\|")?;
+        assert_eq!(row.fields, vec!["1", "a|b", "c:\\dir", "two\nlines", "|"]);
+        assert_eq!(row.to_line(), "1|a\\|b|c:\\\\dir|two\\\nlines|\\||");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unl_reader_joins_continued_lines() -> Result<(), Error> {
+        let text = "1|first\\\nsecond|\r\n2|ends with \\\\|\n3|no newline|";
+        let rows = UnlReader::new(text.as_bytes()).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].fields, vec!["1", "first\nsecond"]);
+        assert_eq!(rows[1].fields, vec!["2", "ends with \\"]);
+        assert_eq!(rows[2].fields, vec!["3", "no newline"]);
         Ok(())
     }
 